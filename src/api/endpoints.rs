@@ -3,23 +3,31 @@
 //! This module implements the HTTP endpoints for the Claude-to-OpenAI proxy,
 //! including message creation, token counting, and health checks.
 
-use crate::conversion::request_converter::convert_claude_to_openai;
+use crate::conversion::request_converter::{
+    check_capabilities, convert_claude_to_openai, estimate_tokens,
+};
 use crate::conversion::response_converter::{
+    called_tool_names, check_streamed_tool_choice, check_tool_choice,
     convert_openai_streaming_to_claude_with_cancellation, convert_openai_to_claude,
 };
+use crate::core::cancellation::{CancelOnDrop, ProviderStream};
+use crate::core::circuit_breaker::{CircuitBreakers, CircuitState};
 use crate::core::config::Config;
 use crate::core::model_manager::ModelManager;
-use crate::core::provider::Provider;
-use crate::models::claude::{ClaudeMessage, ClaudeMessagesRequest, ClaudeTokenCountRequest, MessageContent};
+use crate::core::provider::{Provider, ProviderError};
+use crate::core::registry::ProviderRegistry;
+use crate::models::claude::{ClaudeMessagesRequest, ClaudeTokenCountRequest};
+use crate::models::openai::OpenAIChatCompletionRequest;
 use axum::{
     Json, Router,
+    body::Body,
     extract::State,
     http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response, Sse},
     routing::{get, post},
 };
 use futures::StreamExt;
-use serde_json::json;
+use serde_json::{Value, json};
 use std::convert::Infallible;
 use std::sync::Arc;
 use tracing::{debug, error, warn};
@@ -29,7 +37,8 @@ use tracing::{debug, error, warn};
 pub struct AppState {
     pub config: Arc<Config>,
     pub model_manager: Arc<ModelManager>,
-    pub provider: Arc<dyn Provider>,
+    pub registry: Arc<ProviderRegistry>,
+    pub circuit_breakers: Arc<CircuitBreakers>,
 }
 
 /// Create the API router with all endpoints
@@ -71,166 +80,406 @@ fn validate_api_key(headers: &HeaderMap, config: &Config) -> Result<(), StatusCo
     }
 }
 
+/// Build an Anthropic-style error response for a provider failure
+fn provider_error_response(e: &ProviderError) -> Response {
+    let (status, error_type) = match e {
+        // Anthropic upstreams already answered in the client's format
+        ProviderError::Relayed { status, body } => {
+            let status = StatusCode::from_u16(*status).unwrap_or(StatusCode::BAD_GATEWAY);
+            return (status, Json(body.clone())).into_response();
+        }
+        ProviderError::BadRequest(_) | ProviderError::Unsupported(_) => {
+            (StatusCode::BAD_REQUEST, "invalid_request_error")
+        }
+        ProviderError::Authentication(_) => (StatusCode::UNAUTHORIZED, "authentication_error"),
+        ProviderError::RateLimit(_) => (StatusCode::TOO_MANY_REQUESTS, "rate_limit_error"),
+        ProviderError::CircuitOpen(_) => (StatusCode::SERVICE_UNAVAILABLE, "api_error"),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, "api_error"),
+    };
+    let error_response = json!({
+        "type": "error",
+        "error": {
+            "type": error_type,
+            "message": e.to_string()
+        }
+    });
+    (status, Json(error_response)).into_response()
+}
+
 /// POST /v1/messages - Create a message
 async fn create_message(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(request): Json<ClaudeMessagesRequest>,
+    Json(body): Json<Value>,
 ) -> Result<Response, StatusCode> {
     // Validate API key
     validate_api_key(&headers, &state.config)?;
 
-    // Capture request details before move
-    let model_name = request.model.clone();
-    let stream = request.stream;
+    // Providers that speak the Messages API get the body exactly as sent,
+    // so only the model and stream flag are read before routing
+    let Some(model_name) = body.get("model").and_then(|m| m.as_str()).map(String::from) else {
+        let error_response = json!({
+            "type": "error",
+            "error": {
+                "type": "invalid_request_error",
+                "message": "model: field required"
+            }
+        });
+        return Ok((StatusCode::BAD_REQUEST, Json(error_response)).into_response());
+    };
+    let stream = body
+        .get("stream")
+        .and_then(|s| s.as_bool())
+        .unwrap_or(false);
+    let anthropic_beta = anthropic_beta(&headers);
 
-    // Log incoming request details
     tracing::info!(
-        "Incoming Claude API request: model={}, stream={}, messages={}",
+        "Incoming Claude API request: model={}, stream={}",
         model_name,
-        stream,
-        request.messages.len()
+        stream
     );
 
-    // Log the full request payload in debug mode
-    debug!("Full request payload: {:?}", request);
+    // Converting providers need the typed request; a body that does not
+    // parse is only an error once one of them is tried
+    let processed_request = match serde_json::from_value::<ClaudeMessagesRequest>(body.clone()) {
+        Ok(request) => Ok(prepare_request(request, state.config.max_messages_limit)),
+        Err(e) => {
+            warn!("Invalid Claude request body: {}", e);
+            Err(e.to_string())
+        }
+    };
 
-    debug!(
-        "Processing Claude request: model={}, stream={}",
-        model_name, stream
-    );
+    // Try the tier's provider first, then its fallbacks in order
+    let routes = state.model_manager.resolve_with_fallbacks(&model_name);
+    let mut last_error = None;
+
+    for (attempt, route) in routes.iter().enumerate() {
+        let Some(provider) = state.registry.get(route.provider) else {
+            error!("No provider configured for {:?}", route.provider);
+            last_error = Some(ProviderError::Unexpected(format!(
+                "No provider configured for {:?}",
+                route.provider
+            )));
+            continue;
+        };
+
+        // Generate unique request ID for cancellation tracking
+        let request_id = uuid::Uuid::new_v4().to_string();
+        let capabilities = provider.capabilities(&route.model);
+
+        let unsupported = processed_request
+            .as_ref()
+            .ok()
+            .and_then(|request| check_capabilities(request, &capabilities).err());
+
+        // Held across the request so a client disconnect frees a probe slot
+        let admission = match unsupported {
+            Some(_) => None,
+            None => state.circuit_breakers.admit(route.provider, &route.model),
+        };
+
+        // Providers that speak the Messages API get the original body unchanged
+        let result = if let Some(message) = unsupported {
+            Err(ProviderError::Unsupported(format!(
+                "{} ({}): {}",
+                provider.provider_name(),
+                route.model,
+                message
+            )))
+        } else if admission.is_none() {
+            Err(ProviderError::CircuitOpen(format!(
+                "{:?} ({})",
+                route.provider, route.model
+            )))
+        } else if provider.accepts_claude_format(&route.model) {
+            forward_claude_message(
+                provider.clone(),
+                body.clone(),
+                anthropic_beta.clone(),
+                route.model.clone(),
+                stream,
+                request_id,
+            )
+            .await
+        } else {
+            match &processed_request {
+                Ok(request) => {
+                    // Convert Claude request to OpenAI format for this provider and model
+                    let mut openai_request = convert_claude_to_openai(
+                        request,
+                        &state.model_manager,
+                        state.config.min_tokens_limit,
+                        state.config.max_tokens_limit,
+                        &capabilities,
+                    );
+                    openai_request.model = route.model.clone();
+                    let think_tags = state.model_manager.uses_think_tags(&route.model);
+                    send_openai_request(
+                        provider.clone(),
+                        openai_request,
+                        model_name.clone(),
+                        think_tags,
+                        stream,
+                        request_id,
+                    )
+                    .await
+                }
+                Err(message) => Err(ProviderError::BadRequest(message.clone())),
+            }
+        };
+
+        if let Some(admission) = admission {
+            match &result {
+                Ok(_) => admission.success(),
+                Err(e) if e.should_fallback() => admission.failure(&e.to_string()),
+                // Client errors and cancellations say nothing about the upstream
+                Err(_) => admission.release(),
+            }
+        }
+
+        match result {
+            Ok(response) => return Ok(response),
+            Err(e) if e.should_fallback() && attempt + 1 < routes.len() => {
+                let next = &routes[attempt + 1];
+                warn!(
+                    "{} failed for {}: {}; falling back to {:?} ({})",
+                    provider.provider_name(),
+                    route.model,
+                    e,
+                    next.provider,
+                    next.model
+                );
+                last_error = Some(e);
+            }
+            Err(e) => {
+                error!("Provider API error: {}", e);
+                return Ok(provider_error_response(&e));
+            }
+        }
+    }
 
-    // Generate unique request ID for cancellation tracking
-    let request_id = uuid::Uuid::new_v4().to_string();
+    let e = last_error
+        .unwrap_or_else(|| ProviderError::Unexpected("No provider available".to_string()));
+    error!("Provider API error: {}", e);
+    Ok(provider_error_response(&e))
+}
+
+/// Truncate the message history of a request to be converted
+fn prepare_request(mut request: ClaudeMessagesRequest, max_messages: u32) -> ClaudeMessagesRequest {
+    // Log the full request payload in debug mode
+    debug!("Full request payload: {:?}", request);
 
     // Apply context truncation if needed
-    let messages = if request.messages.len() > state.config.max_messages_limit as usize {
-        let original_count = request.messages.len();
-        let truncated_messages: Vec<crate::models::claude::ClaudeMessage> = request
+    let original_count = request.messages.len();
+    if original_count > max_messages as usize {
+        request
             .messages
-            .iter()
-            .skip(original_count - state.config.max_messages_limit as usize)
-            .cloned()
-            .collect();
+            .drain(..original_count - max_messages as usize);
 
         tracing::warn!(
             "Context truncated by message count: {} messages → {} messages (removed {} oldest messages)",
             original_count,
-            truncated_messages.len(),
-            original_count - truncated_messages.len()
+            request.messages.len(),
+            original_count - request.messages.len()
         );
-        truncated_messages
-    } else {
-        request.messages.clone()
-    };
+    }
 
     // TODO: Implement token-based truncation
     // For now, we only use message count truncation above
-    let messages = messages;
-
-    // Create a processed request for conversion
-    // Move the request here to avoid multiple borrows
-    let mut processed_request = request;
-    processed_request.messages = messages;
-    processed_request.model = model_name.clone();
-    processed_request.stream = stream;
-
-    // Convert Claude request to OpenAI format
-    let openai_request = convert_claude_to_openai(
-        &processed_request,
-        &state.model_manager,
-        state.config.min_tokens_limit,
-        state.config.max_tokens_limit,
-    );
+    request
+}
+
+/// Beta features the client asked for in `anthropic-beta` headers
+fn anthropic_beta(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all("anthropic-beta")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|beta| beta.trim().to_string())
+        .filter(|beta| !beta.is_empty())
+        .collect()
+}
+
+/// Wait for the first stream item so that upstream errors raised before
+/// anything reaches the client can still fall back to another provider
+async fn peek_stream(mut provider_stream: ProviderStream) -> Result<ProviderStream, ProviderError> {
+    match provider_stream.next().await {
+        Some(Err(e)) => Err(e),
+        Some(Ok(first)) => Ok(Box::pin(
+            futures::stream::once(async move { Ok(first) }).chain(provider_stream),
+        )),
+        None => Ok(provider_stream),
+    }
+}
+
+/// Error for a reply that ignores the requested `tool_choice`
+///
+/// Reported as a bad upstream reply, so the request falls back if it can.
+fn tool_choice_error(provider: &dyn Provider, message: String) -> ProviderError {
+    ProviderError::ApiError {
+        status: 502,
+        message: format!(
+            "{} ignored tool_choice: {}",
+            provider.provider_name(),
+            message
+        ),
+    }
+}
+
+/// Send an OpenAI-format request and convert the result to Claude format
+async fn send_openai_request(
+    provider: Arc<dyn Provider>,
+    openai_request: OpenAIChatCompletionRequest,
+    model_name: String,
+    think_tags: bool,
+    stream: bool,
+    request_id: String,
+) -> Result<Response, ProviderError> {
+    // Forced tool choices the provider cannot enforce are checked on the reply
+    let unenforced_tool_choice = openai_request
+        .tool_choice
+        .clone()
+        .filter(|choice| choice != "auto")
+        .filter(|_| !provider.capabilities(&openai_request.model).tool_choice);
+
+    // Axum drops this handler if the client disconnects while we wait
+    let cancel_guard = CancelOnDrop::new(provider.clone(), request_id.clone());
 
     if stream {
         // Streaming response with client disconnection detection
-        match state
-            .provider
+        let provider_stream = provider
             .create_chat_completion_stream(openai_request, Some(request_id.clone()))
-            .await
-        {
-            Ok(provider_stream) => {
-                // Wrap ProviderError in a String-based error for the stream
-                #[derive(Debug)]
-                struct StreamError(String);
-                impl std::fmt::Display for StreamError {
-                    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                        write!(f, "{}", self.0)
-                    }
-                }
-                impl std::error::Error for StreamError {}
-
-                // Convert provider stream to Claude SSE format with cancellation support
-                let provider = state.provider.clone();
-                let model_name = model_name.clone();
-
-                let claude_stream = convert_openai_streaming_to_claude_with_cancellation(
-                    provider_stream.map(|r| r.map_err(|e| StreamError(e.to_string()))),
-                    model_name,
-                    provider,
-                    request_id,
-                )
-                .await;
-
-                // Convert Result<String, String> to SSE events
-                let sse_stream = claude_stream.map(|item| match item {
-                    Ok(data) => {
-                        Ok::<_, Infallible>(axum::response::sse::Event::default().data(data))
-                    }
-                    Err(e) => {
-                        error!("Stream error: {}", e);
-                        Ok(axum::response::sse::Event::default()
-                            .data(format!("event: error\ndata: {}\n\n", e)))
-                    }
-                });
-
-                // Create SSE response with proper headers
-                let mut response = Sse::new(sse_stream)
-                    .keep_alive(axum::response::sse::KeepAlive::default())
-                    .into_response();
-
-                // Add CORS and cache control headers
-                let response_headers = response.headers_mut();
-                response_headers.insert("Cache-Control", "no-cache".parse().unwrap());
-                response_headers.insert("Connection", "keep-alive".parse().unwrap());
-                Ok(response)
+            .await?;
+        let provider_stream = match &unenforced_tool_choice {
+            Some(tool_choice) => {
+                let provider = provider.clone();
+                check_streamed_tool_choice(provider_stream, tool_choice.clone(), move |e| {
+                    tool_choice_error(provider.as_ref(), e)
+                })
             }
-            Err(e) => {
-                error!("Provider streaming error: {}", e);
-                let error_response = json!({
-                    "type": "error",
-                    "error": {
-                        "type": "api_error",
-                        "message": e.to_string()
-                    }
-                });
-                Ok((StatusCode::INTERNAL_SERVER_ERROR, Json(error_response)).into_response())
+            None => provider_stream,
+        };
+        let provider_stream = peek_stream(provider_stream).await?;
+        // The converter cancels from here on
+        cancel_guard.disarm();
+
+        // Wrap ProviderError in a String-based error for the stream
+        #[derive(Debug)]
+        struct StreamError(String);
+        impl std::fmt::Display for StreamError {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.0)
             }
         }
+        impl std::error::Error for StreamError {}
+
+        // Convert provider stream to Claude SSE format with cancellation support
+        let claude_stream = convert_openai_streaming_to_claude_with_cancellation(
+            provider_stream.map(|r| r.map_err(|e| StreamError(e.to_string()))),
+            model_name,
+            think_tags,
+            provider,
+            request_id,
+        )
+        .await;
+
+        // Convert Result<String, String> to SSE events
+        let sse_stream = claude_stream.map(|item| match item {
+            Ok(data) => Ok::<_, Infallible>(axum::response::sse::Event::default().data(data)),
+            Err(e) => {
+                error!("Stream error: {}", e);
+                Ok(axum::response::sse::Event::default()
+                    .data(format!("event: error\ndata: {}\n\n", e)))
+            }
+        });
+
+        // Create SSE response with proper headers
+        let mut response = Sse::new(sse_stream)
+            .keep_alive(axum::response::sse::KeepAlive::default())
+            .into_response();
+
+        // Add CORS and cache control headers
+        let response_headers = response.headers_mut();
+        response_headers.insert("Cache-Control", "no-cache".parse().unwrap());
+        response_headers.insert("Connection", "keep-alive".parse().unwrap());
+        Ok(response)
     } else {
         // Non-streaming response
-        match state
-            .provider
-            .create_chat_completion(&openai_request, None)
-            .await
-        {
-            Ok(provider_response) => {
-                let claude_response = convert_openai_to_claude(&provider_response, &model_name);
-                Ok(Json(claude_response).into_response())
-            }
+        let provider_response = provider
+            .create_chat_completion(&openai_request, Some(request_id))
+            .await?;
+        cancel_guard.disarm();
+        if let Some(tool_choice) = &unenforced_tool_choice {
+            check_tool_choice(tool_choice, &called_tool_names(&provider_response))
+                .map_err(|e| tool_choice_error(provider.as_ref(), e))?;
+        }
+        let claude_response = convert_openai_to_claude(&provider_response, &model_name);
+        Ok(Json(claude_response).into_response())
+    }
+}
+
+/// Forward an Anthropic Messages request to a provider that accepts it natively
+///
+/// Only the model name is rewritten and client betas added; the upstream SSE
+/// stream is relayed verbatim.
+async fn forward_claude_message(
+    provider: Arc<dyn Provider>,
+    mut body: Value,
+    anthropic_beta: Vec<String>,
+    upstream_model: String,
+    stream: bool,
+    request_id: String,
+) -> Result<Response, ProviderError> {
+    body["model"] = Value::String(upstream_model);
+    // Client betas travel in the body, as Vertex AI expects them; the
+    // Anthropic provider turns them back into a header
+    if !anthropic_beta.is_empty() && body.get("anthropic_beta").is_none() {
+        body["anthropic_beta"] = json!(anthropic_beta);
+    }
+
+    // Axum drops this handler, or the response body, if the client disconnects
+    let cancel_guard = CancelOnDrop::new(provider.clone(), request_id.clone());
+
+    if stream {
+        let provider_stream = provider
+            .create_claude_message_stream(body, Some(request_id))
+            .await?;
+        let provider_stream = peek_stream(provider_stream).await?;
+
+        let sse_stream = provider_stream.map(|item| match item {
+            Ok(line) => Ok::<_, Infallible>(format!("{}\n", line)),
             Err(e) => {
-                error!("Provider API error: {}", e);
-                let error_response = json!({
+                error!("Stream error: {}", e);
+                let error_event = json!({
                     "type": "error",
                     "error": {
                         "type": "api_error",
-                        "message": e.to_string()
+                        "message": format!("Stream error: {}", e)
                     }
                 });
-                Ok((StatusCode::INTERNAL_SERVER_ERROR, Json(error_response)).into_response())
+                Ok(format!("\nevent: error\ndata: {}\n\n", error_event))
             }
-        }
+        });
+
+        // The guard lives as long as the relayed body
+        let sse_stream = sse_stream.chain(futures::stream::once(async move {
+            cancel_guard.disarm();
+            Ok(String::new())
+        }));
+
+        let mut response = Body::from_stream(sse_stream).into_response();
+        let response_headers = response.headers_mut();
+        response_headers.insert("Content-Type", "text/event-stream".parse().unwrap());
+        response_headers.insert("Cache-Control", "no-cache".parse().unwrap());
+        response_headers.insert("Connection", "keep-alive".parse().unwrap());
+        Ok(response)
+    } else {
+        let claude_response = provider
+            .create_claude_message(&body, Some(request_id))
+            .await?;
+        cancel_guard.disarm();
+        Ok(Json(claude_response).into_response())
     }
 }
 
@@ -246,39 +495,7 @@ async fn count_tokens(
     debug!("Token counting for model: {}", request.model);
 
     // Simple character-based token estimation
-    // Rough estimation: 4 characters per token
-    let mut total_chars = 0;
-
-    // Count system message characters
-    if let Some(ref system) = request.system {
-        use crate::models::claude::SystemContent;
-        match system {
-            SystemContent::String(s) => total_chars += s.len(),
-            SystemContent::Blocks(blocks) => {
-                for block in blocks {
-                    total_chars += block.text.len();
-                }
-            }
-        }
-    }
-
-    // Count message characters
-    for msg in &request.messages {
-        match &msg.content {
-            MessageContent::String(s) => total_chars += s.len(),
-            MessageContent::Blocks(blocks) => {
-                for block in blocks {
-                    use crate::models::claude::ClaudeContentBlock;
-                    if let ClaudeContentBlock::Text(text_block) = block {
-                        total_chars += text_block.text.len();
-                    }
-                }
-            }
-        }
-    }
-
-    // Rough estimation: 4 characters per token
-    let estimated_tokens = std::cmp::max(1, total_chars / 4);
+    let estimated_tokens = estimate_tokens(request.system.as_ref(), &request.messages);
 
     let response = json!({
         "input_tokens": estimated_tokens
@@ -293,9 +510,9 @@ async fn root(State(state): State<AppState>) -> impl IntoResponse {
         "message": "Claude-to-OpenAI API Proxy v1.0.0",
         "status": "running",
         "config": {
-            "openai_base_url": state.config.openai_base_url,
+            "openai_base_url": state.config.default_provider_config().base_url,
             "max_tokens_limit": state.config.max_tokens_limit,
-            "api_key_configured": !state.config.openai_api_key.is_empty(),
+            "api_key_configured": !state.config.default_provider_config().api_key.is_empty(),
             "client_api_key_validation": state.config.anthropic_api_key.is_some(),
             "big_model": state.config.big_model,
             "middle_model": state.config.middle_model,
@@ -311,14 +528,65 @@ async fn root(State(state): State<AppState>) -> impl IntoResponse {
 }
 
 /// GET /health - Health check endpoint
+///
+/// Reports "degraded" while any circuit is open, and "unhealthy" with a 503
+/// when some model tier has no route left with a closed or probing circuit.
 async fn health_check(State(state): State<AppState>) -> impl IntoResponse {
-    Json(json!({
-        "status": "healthy",
-        "timestamp": chrono::Utc::now().to_rfc3339(),
-        "openai_api_configured": !state.config.openai_api_key.is_empty(),
-        "api_key_valid": state.config.validate_api_key(),
-        "client_api_key_validation": state.config.anthropic_api_key.is_some(),
-    }))
+    let breakers = &state.circuit_breakers;
+
+    let tiers: serde_json::Map<String, Value> = ["opus", "sonnet", "haiku"]
+        .into_iter()
+        .map(|tier| {
+            let routes = state.model_manager.resolve_with_fallbacks(tier);
+            let available = routes
+                .iter()
+                .any(|route| breakers.state(route.provider, &route.model) != CircuitState::Open);
+            (
+                tier.to_string(),
+                json!({
+                    "provider": format!("{:?}", routes[0].provider),
+                    "model": routes[0].model,
+                    "available": available,
+                }),
+            )
+        })
+        .collect();
+
+    let circuits: Vec<Value> = breakers
+        .statuses()
+        .into_iter()
+        .map(|status| {
+            json!({
+                "provider": format!("{:?}", status.provider),
+                "model": status.model,
+                "state": status.state.as_str(),
+                "requests": status.requests,
+                "error_rate": status.error_rate,
+                "retry_in_secs": status.retry_in.map(|d| d.as_secs()),
+                "last_error": status.last_error,
+            })
+        })
+        .collect();
+
+    let (status_code, status) = if tiers.values().any(|tier| tier["available"] == false) {
+        (StatusCode::SERVICE_UNAVAILABLE, "unhealthy")
+    } else if circuits.iter().any(|circuit| circuit["state"] != "closed") {
+        (StatusCode::OK, "degraded")
+    } else {
+        (StatusCode::OK, "healthy")
+    };
+
+    (
+        status_code,
+        Json(json!({
+            "status": status,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "openai_api_configured": !state.config.default_provider_config().api_key.is_empty(),
+            "client_api_key_validation": state.config.anthropic_api_key.is_some(),
+            "tiers": tiers,
+            "circuits": circuits,
+        })),
+    )
 }
 
 /// GET /test-connection - Test OpenAI API connectivity
@@ -326,36 +594,63 @@ async fn test_connection(State(state): State<AppState>) -> impl IntoResponse {
     // Actual API test
     use crate::models::openai::{OpenAIChatCompletionRequest, OpenAIMessage};
 
-    let test_request = OpenAIChatCompletionRequest {
-        model: state.config.small_model.clone(),
-        messages: vec![OpenAIMessage {
-            role: "user".to_string(),
-            content: Some(serde_json::Value::String("Hello".to_string())),
-            tool_calls: None,
-            tool_call_id: None,
-        }],
-        max_tokens: Some(5),
-        temperature: Some(1.0),
-        top_p: None,
-        stop: None,
-        stream: false,
-        stream_options: None,
-        tools: None,
-        tool_choice: None,
+    let Some(provider) = state.registry.get(state.config.small_provider) else {
+        return Json(json!({
+            "status": "failed",
+            "error_type": "Configuration Error",
+            "message": format!("No provider configured for {:?}", state.config.small_provider),
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+        }));
+    };
+
+    let result = if provider.accepts_claude_format(&state.config.small_model) {
+        let test_request = json!({
+            "model": state.config.small_model,
+            "max_tokens": 5,
+            "messages": [{"role": "user", "content": "Hello"}],
+        });
+        provider
+            .create_claude_message(&test_request, None)
+            .await
+            .map(|response| response["id"].as_str().unwrap_or_default().to_string())
+    } else {
+        let test_request = OpenAIChatCompletionRequest {
+            model: state.config.small_model.clone(),
+            messages: vec![OpenAIMessage {
+                role: "user".to_string(),
+                content: Some(serde_json::Value::String("Hello".to_string())),
+                tool_calls: None,
+                tool_call_id: None,
+                reasoning_content: None,
+                reasoning: None,
+                reasoning_details: None,
+            }],
+            max_tokens: Some(5),
+            temperature: Some(1.0),
+            top_p: None,
+            stop: None,
+            stream: false,
+            stream_options: None,
+            tools: None,
+            tool_choice: None,
+            parallel_tool_calls: None,
+            reasoning_effort: None,
+            reasoning: None,
+        };
+        provider
+            .create_chat_completion(&test_request, None)
+            .await
+            .map(|response| response.id)
     };
 
-    match state
-        .provider
-        .create_chat_completion(&test_request, None)
-        .await
-    {
-        Ok(response) => Json(json!({
+    match result {
+        Ok(response_id) => Json(json!({
             "status": "success",
-            "message": format!("Successfully connected to {} API", state.provider.provider_name()),
-            "provider": state.provider.provider_name(),
+            "message": format!("Successfully connected to {} API", provider.provider_name()),
+            "provider": provider.provider_name(),
             "model_used": state.config.small_model,
             "timestamp": chrono::Utc::now().to_rfc3339(),
-            "response_id": response.id,
+            "response_id": response_id,
         })),
         Err(e) => {
             error!("API connectivity test failed: {}", e);
@@ -363,7 +658,7 @@ async fn test_connection(State(state): State<AppState>) -> impl IntoResponse {
                 "status": "failed",
                 "error_type": "API Error",
                 "message": e.to_string(),
-                "provider": state.provider.provider_name(),
+                "provider": provider.provider_name(),
                 "timestamp": chrono::Utc::now().to_rfc3339(),
                 "suggestions": [
                     "Check your API key is valid",
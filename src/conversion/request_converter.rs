@@ -5,14 +5,16 @@
 
 use crate::core::constants::{content, role, tool};
 use crate::core::model_manager::ModelManager;
+use crate::core::provider::ProviderCapabilities;
 use crate::models::claude::{
     ClaudeContentBlock, ClaudeMessage, ClaudeMessagesRequest, MessageContent, SystemContent,
     ToolResultContent,
 };
 use crate::models::openai::{
-    OpenAIChatCompletionRequest, OpenAIFunctionDef, OpenAIMessage, OpenAITool, OpenAIToolCall,
+    OpenAIChatCompletionRequest, OpenAIFunctionDef, OpenAIMessage, OpenAIReasoning, OpenAITool,
+    OpenAIToolCall,
 };
-use serde_json::Value;
+use serde_json::{Value, json};
 use std::collections::HashMap;
 use tracing::debug;
 
@@ -28,11 +30,13 @@ use tracing::debug;
 /// * `model_manager` - Model manager for mapping Claude models to OpenAI models
 /// * `min_tokens` - Minimum token limit
 /// * `max_tokens` - Maximum token limit
+/// * `capabilities` - Features of the target provider and model
 pub fn convert_claude_to_openai(
     claude_request: &ClaudeMessagesRequest,
     model_manager: &ModelManager,
     min_tokens: u32,
     max_tokens: u32,
+    capabilities: &ProviderCapabilities,
 ) -> OpenAIChatCompletionRequest {
     // Map model
     let openai_model = model_manager.map_claude_model_to_openai(&claude_request.model);
@@ -54,12 +58,22 @@ pub fn convert_claude_to_openai(
             }
         };
 
+        // Models without a system role get the system prompt as a user turn
+        let system_role = if capabilities.system_role {
+            role::SYSTEM
+        } else {
+            role::USER
+        };
+
         if !system_text.trim().is_empty() {
             openai_messages.push(OpenAIMessage {
-                role: role::SYSTEM.to_string(),
+                role: system_role.to_string(),
                 content: Some(Value::String(system_text.trim().to_string())),
                 tool_calls: None,
                 tool_call_id: None,
+                reasoning_content: None,
+                reasoning: None,
+                reasoning_details: None,
             });
         }
     }
@@ -73,7 +87,8 @@ pub fn convert_claude_to_openai(
             let openai_message = convert_claude_user_message(msg);
             openai_messages.push(openai_message);
         } else if msg.role == role::ASSISTANT {
-            let openai_message = convert_claude_assistant_message(msg);
+            let openai_message =
+                convert_claude_assistant_message(msg, capabilities.reasoning_input);
             openai_messages.push(openai_message);
 
             // Check if next message contains tool results
@@ -91,11 +106,12 @@ pub fn convert_claude_to_openai(
         i += 1;
     }
 
-    // Clamp max_tokens to configured limits
+    // Clamp max_tokens to configured limits and the model's output limit
     let clamped_max_tokens = claude_request
         .max_tokens
         .max(min_tokens)
-        .min(max_tokens);
+        .min(max_tokens)
+        .min(capabilities.max_output_tokens.unwrap_or(u32::MAX));
 
     // Build OpenAI request
     let mut openai_request = OpenAIChatCompletionRequest {
@@ -109,6 +125,17 @@ pub fn convert_claude_to_openai(
         stream_options: None, // Will be set by client if streaming
         tools: None,
         tool_choice: None,
+        parallel_tool_calls: None,
+        reasoning_effort: None,
+        // Providers translate the thinking budget into their own controls;
+        // it has to leave room for the answer within max_tokens
+        reasoning: claude_request
+            .thinking
+            .as_ref()
+            .and_then(|thinking| thinking.budget())
+            .map(|budget| OpenAIReasoning {
+                max_tokens: Some(budget.min(clamped_max_tokens.saturating_sub(1))),
+            }),
     };
 
     // Convert tools
@@ -133,31 +160,15 @@ pub fn convert_claude_to_openai(
 
     // Convert tool choice
     if let Some(ref tool_choice) = claude_request.tool_choice {
-        if let Some(choice_type) = tool_choice.get("type").and_then(|v| v.as_str()) {
-            openai_request.tool_choice = match choice_type {
-                "auto" | "any" => Some(Value::String("auto".to_string())),
-                "tool" => {
-                    if let Some(name) = tool_choice.get("name").and_then(|v| v.as_str()) {
-                        let mut choice_obj = HashMap::new();
-                        choice_obj.insert(
-                            "type".to_string(),
-                            Value::String(tool::FUNCTION.to_string()),
-                        );
-
-                        let mut function_obj = HashMap::new();
-                        function_obj.insert("name".to_string(), Value::String(name.to_string()));
-                        choice_obj.insert(
-                            tool::FUNCTION.to_string(),
-                            Value::Object(function_obj.into_iter().collect()),
-                        );
-
-                        Some(Value::Object(choice_obj.into_iter().collect()))
-                    } else {
-                        Some(Value::String("auto".to_string()))
-                    }
-                }
-                _ => Some(Value::String("auto".to_string())),
-            };
+        openai_request.tool_choice = convert_tool_choice(tool_choice);
+
+        // Only meaningful (and only accepted by OpenAI) alongside tools
+        let disable_parallel = tool_choice
+            .get("disable_parallel_tool_use")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        if disable_parallel && openai_request.tools.is_some() {
+            openai_request.parallel_tool_calls = Some(false);
         }
     }
 
@@ -165,6 +176,130 @@ pub fn convert_claude_to_openai(
     openai_request
 }
 
+/// Convert a Claude `tool_choice` to the OpenAI one
+///
+/// `any` forces some tool call ("required"), `tool` forces the named one and
+/// `none` forbids tool calls.
+fn convert_tool_choice(tool_choice: &HashMap<String, Value>) -> Option<Value> {
+    let choice_type = tool_choice.get("type").and_then(|v| v.as_str())?;
+    let choice = match choice_type {
+        "any" => Value::String("required".to_string()),
+        "none" => Value::String("none".to_string()),
+        "tool" => {
+            if let Some(name) = tool_choice.get("name").and_then(|v| v.as_str()) {
+                let mut choice_obj = HashMap::new();
+                choice_obj.insert(
+                    "type".to_string(),
+                    Value::String(tool::FUNCTION.to_string()),
+                );
+
+                let mut function_obj = HashMap::new();
+                function_obj.insert("name".to_string(), Value::String(name.to_string()));
+                choice_obj.insert(
+                    tool::FUNCTION.to_string(),
+                    Value::Object(function_obj.into_iter().collect()),
+                );
+
+                Value::Object(choice_obj.into_iter().collect())
+            } else {
+                Value::String("auto".to_string())
+            }
+        }
+        _ => Value::String("auto".to_string()),
+    };
+    Some(choice)
+}
+
+/// Check that the target provider and model support everything a request uses
+///
+/// # Errors
+///
+/// Returns a message describing the first unsupported feature, suitable for
+/// an `invalid_request_error`.
+pub fn check_capabilities(
+    claude_request: &ClaudeMessagesRequest,
+    capabilities: &ProviderCapabilities,
+) -> Result<(), String> {
+    let has_tools = claude_request
+        .tools
+        .as_ref()
+        .is_some_and(|tools| !tools.is_empty());
+    if has_tools && !capabilities.tools {
+        return Err("tool use is not supported by this model".to_string());
+    }
+
+    let has_images = claude_request.messages.iter().any(|msg| {
+        matches!(&msg.content, MessageContent::Blocks(blocks)
+            if blocks.iter().any(|block| matches!(block, ClaudeContentBlock::Image(_))))
+    });
+    if has_images && !capabilities.vision {
+        return Err("image inputs are not supported by this model".to_string());
+    }
+
+    if claude_request.stream && !capabilities.streaming {
+        return Err("streaming is not supported by this model".to_string());
+    }
+
+    if let Some(stop_sequences) = &claude_request.stop_sequences
+        && let Some(limit) = capabilities.max_stop_sequences
+        && stop_sequences.len() > limit
+    {
+        return Err(format!(
+            "stop_sequences: {} given, but this model accepts at most {}",
+            stop_sequences.len(),
+            limit
+        ));
+    }
+
+    if let Some(limit) = capabilities.max_context_tokens {
+        let estimated_tokens =
+            estimate_tokens(claude_request.system.as_ref(), &claude_request.messages);
+        if estimated_tokens > limit as usize {
+            return Err(format!(
+                "prompt is too long: about {} tokens > {} maximum",
+                estimated_tokens, limit
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Roughly estimate the input tokens of a system prompt and messages
+///
+/// Counts text only, at about 4 characters per token.
+pub fn estimate_tokens(system: Option<&SystemContent>, messages: &[ClaudeMessage]) -> usize {
+    let mut total_chars = 0;
+
+    // Count system message characters
+    if let Some(system) = system {
+        match system {
+            SystemContent::String(s) => total_chars += s.len(),
+            SystemContent::Blocks(blocks) => {
+                for block in blocks {
+                    total_chars += block.text.len();
+                }
+            }
+        }
+    }
+
+    // Count message characters
+    for msg in messages {
+        match &msg.content {
+            MessageContent::String(s) => total_chars += s.len(),
+            MessageContent::Blocks(blocks) => {
+                for block in blocks {
+                    if let ClaudeContentBlock::Text(text_block) = block {
+                        total_chars += text_block.text.len();
+                    }
+                }
+            }
+        }
+    }
+
+    std::cmp::max(1, total_chars / 4)
+}
+
 /// Convert Claude user message to OpenAI format
 fn convert_claude_user_message(msg: &ClaudeMessage) -> OpenAIMessage {
     match &msg.content {
@@ -173,6 +308,9 @@ fn convert_claude_user_message(msg: &ClaudeMessage) -> OpenAIMessage {
             content: Some(Value::String(s.clone())),
             tool_calls: None,
             tool_call_id: None,
+            reasoning_content: None,
+            reasoning: None,
+            reasoning_details: None,
         },
         MessageContent::Blocks(blocks) => {
             // Handle multimodal content
@@ -232,6 +370,9 @@ fn convert_claude_user_message(msg: &ClaudeMessage) -> OpenAIMessage {
                                 content: Some(text.clone()),
                                 tool_calls: None,
                                 tool_call_id: None,
+                                reasoning_content: None,
+                                reasoning: None,
+                                reasoning_details: None,
                             };
                         }
                     }
@@ -243,29 +384,57 @@ fn convert_claude_user_message(msg: &ClaudeMessage) -> OpenAIMessage {
                 content: Some(Value::Array(openai_content)),
                 tool_calls: None,
                 tool_call_id: None,
+                reasoning_content: None,
+                reasoning: None,
+                reasoning_details: None,
             }
         }
     }
 }
 
 /// Convert Claude assistant message to OpenAI format
-fn convert_claude_assistant_message(msg: &ClaudeMessage) -> OpenAIMessage {
+///
+/// Thinking blocks are forwarded as reasoning when `forward_reasoning` is
+/// set, and dropped otherwise.
+fn convert_claude_assistant_message(msg: &ClaudeMessage, forward_reasoning: bool) -> OpenAIMessage {
     match &msg.content {
         MessageContent::String(s) => OpenAIMessage {
             role: role::ASSISTANT.to_string(),
             content: Some(Value::String(s.clone())),
             tool_calls: None,
             tool_call_id: None,
+            reasoning_content: None,
+            reasoning: None,
+            reasoning_details: None,
         },
         MessageContent::Blocks(blocks) => {
             let mut text_parts = Vec::new();
             let mut tool_calls = Vec::new();
+            let mut reasoning_parts = Vec::new();
+            let mut reasoning_details = Vec::new();
 
             for block in blocks {
                 match block {
                     ClaudeContentBlock::Text(text_block) => {
                         text_parts.push(text_block.text.clone());
                     }
+                    ClaudeContentBlock::Thinking(thinking) if forward_reasoning => {
+                        reasoning_parts.push(thinking.thinking.clone());
+                        let mut detail = json!({
+                            "type": "reasoning.text",
+                            "text": thinking.thinking
+                        });
+                        if !thinking.signature.is_empty() {
+                            detail["signature"] = json!(thinking.signature);
+                        }
+                        reasoning_details.push(detail);
+                    }
+                    ClaudeContentBlock::RedactedThinking(redacted) if forward_reasoning => {
+                        reasoning_details.push(json!({
+                            "type": "reasoning.encrypted",
+                            "data": redacted.data
+                        }));
+                    }
                     ClaudeContentBlock::ToolUse(tool_use) => {
                         tool_calls.push(OpenAIToolCall {
                             id: tool_use.id.clone(),
@@ -298,6 +467,10 @@ fn convert_claude_assistant_message(msg: &ClaudeMessage) -> OpenAIMessage {
                 content,
                 tool_calls: tool_calls_opt,
                 tool_call_id: None,
+                reasoning_content: (!reasoning_parts.is_empty())
+                    .then(|| reasoning_parts.join("\n\n")),
+                reasoning: None,
+                reasoning_details: (!reasoning_details.is_empty()).then_some(reasoning_details),
             }
         }
     }
@@ -316,6 +489,9 @@ fn convert_claude_tool_results(msg: &ClaudeMessage) -> Vec<OpenAIMessage> {
                     content: Some(Value::String(content)),
                     tool_calls: None,
                     tool_call_id: Some(tool_result.tool_use_id.clone()),
+                    reasoning_content: None,
+                    reasoning: None,
+                    reasoning_details: None,
                 });
             }
         }
@@ -371,3 +547,135 @@ fn parse_tool_result_content(content: &ToolResultContent) -> String {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(body: Value) -> ClaudeMessagesRequest {
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn test_thinking_history_forwarded_or_stripped() {
+        let message: ClaudeMessage = serde_json::from_value(json!({
+            "role": "assistant",
+            "content": [
+                {"type": "thinking", "thinking": "Check the files.", "signature": "sig-1"},
+                {"type": "redacted_thinking", "data": "opaque"},
+                {"type": "thinking", "thinking": "List them.", "signature": "sig-2"},
+                {"type": "text", "text": "Listing."},
+                {"type": "tool_use", "id": "toolu_1", "name": "Bash", "input": {"command": "ls"}}
+            ]
+        }))
+        .unwrap();
+
+        let forwarded = convert_claude_assistant_message(&message, true);
+        assert_eq!(forwarded.content, Some(json!("Listing.")));
+        assert_eq!(forwarded.tool_calls.as_ref().unwrap().len(), 1);
+        assert_eq!(
+            forwarded.reasoning_content.as_deref(),
+            Some("Check the files.\n\nList them.")
+        );
+        // Every thinking block keeps its own signature
+        assert_eq!(
+            forwarded.reasoning_details,
+            Some(vec![
+                json!({"type": "reasoning.text", "text": "Check the files.", "signature": "sig-1"}),
+                json!({"type": "reasoning.encrypted", "data": "opaque"}),
+                json!({"type": "reasoning.text", "text": "List them.", "signature": "sig-2"}),
+            ])
+        );
+        let body = serde_json::to_value(&forwarded).unwrap();
+        assert!(body.get("reasoning_signature").is_none());
+        assert!(body.get("reasoning").is_none());
+
+        let stripped = convert_claude_assistant_message(&message, false);
+        assert_eq!(stripped.content, Some(json!("Listing.")));
+        assert!(stripped.reasoning_content.is_none());
+        assert!(stripped.reasoning_details.is_none());
+    }
+
+    #[test]
+    fn test_convert_tool_choice() {
+        let choice = |body: Value| {
+            let tool_choice: HashMap<String, Value> = serde_json::from_value(body).unwrap();
+            convert_tool_choice(&tool_choice)
+        };
+
+        assert_eq!(choice(json!({"type": "auto"})), Some(json!("auto")));
+        assert_eq!(choice(json!({"type": "any"})), Some(json!("required")));
+        assert_eq!(choice(json!({"type": "none"})), Some(json!("none")));
+        assert_eq!(
+            choice(json!({"type": "tool", "name": "read", "disable_parallel_tool_use": true})),
+            Some(json!({"type": "function", "function": {"name": "read"}}))
+        );
+        assert_eq!(choice(json!({"disable_parallel_tool_use": true})), None);
+    }
+
+    #[test]
+    fn test_check_capabilities_rejects_unsupported_features() {
+        let with_tools = request(json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": "hi"}],
+            "tools": [{"name": "read", "input_schema": {"type": "object"}}],
+            "stop_sequences": ["a", "b", "c", "d", "e"]
+        }));
+
+        assert!(check_capabilities(&with_tools, &ProviderCapabilities::default()).is_ok());
+
+        let no_tools = ProviderCapabilities {
+            tools: false,
+            ..ProviderCapabilities::default()
+        };
+        assert!(
+            check_capabilities(&with_tools, &no_tools)
+                .unwrap_err()
+                .contains("tool use")
+        );
+
+        let four_stops = ProviderCapabilities {
+            max_stop_sequences: Some(4),
+            ..ProviderCapabilities::default()
+        };
+        assert!(
+            check_capabilities(&with_tools, &four_stops)
+                .unwrap_err()
+                .contains("at most 4")
+        );
+    }
+
+    #[test]
+    fn test_check_capabilities_vision_and_context() {
+        let with_image = request(json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": [
+                {"type": "text", "text": "What is this?"},
+                {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"}}
+            ]}]
+        }));
+
+        let no_vision = ProviderCapabilities {
+            vision: false,
+            ..ProviderCapabilities::default()
+        };
+        assert!(
+            check_capabilities(&with_image, &no_vision)
+                .unwrap_err()
+                .contains("image")
+        );
+
+        let tiny_context = ProviderCapabilities {
+            max_context_tokens: Some(1),
+            ..ProviderCapabilities::default()
+        };
+        assert!(
+            check_capabilities(&with_image, &tiny_context)
+                .unwrap_err()
+                .starts_with("prompt is too long")
+        );
+    }
+}
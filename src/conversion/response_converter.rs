@@ -3,8 +3,10 @@
 //! This module converts OpenAI API responses back to Claude API format,
 //! supporting both streaming and non-streaming responses.
 
+use crate::core::cancellation::{CancelOnDrop, ProviderStream};
 use crate::core::constants::{content, delta as delta_const, event, role, stop};
-use crate::models::openai::{OpenAIChatCompletionResponse, OpenAIStreamingChunk};
+use crate::core::provider::ProviderError;
+use crate::models::openai::{OpenAIChatCompletionResponse, OpenAIMessage, OpenAIStreamingChunk};
 use futures::Stream;
 use serde_json::{Value, json};
 use std::collections::HashMap;
@@ -30,6 +32,9 @@ pub fn convert_openai_to_claude(
     // Build content blocks
     let mut content_blocks = Vec::new();
 
+    // Reasoning comes first, as thinking blocks
+    content_blocks.extend(thinking_blocks(message));
+
     // Add text content if present
     if let Some(ref content) = message.content {
         if let Some(text) = content.as_str() {
@@ -70,6 +75,7 @@ pub fn convert_openai_to_claude(
         Some("stop") => "end_turn",
         Some("length") => "max_tokens",
         Some("tool_calls") => "tool_use",
+        Some("content_filter") => stop::REFUSAL,
         _ => "end_turn",
     };
 
@@ -127,6 +133,7 @@ pub fn convert_streaming_chunk_to_claude(
                 "stop" => "end_turn",
                 "length" => "max_tokens",
                 "tool_calls" => "tool_use",
+                "content_filter" => stop::REFUSAL,
                 _ => "end_turn",
             };
 
@@ -194,6 +201,153 @@ pub fn create_message_stop_event() -> String {
     format!("event: message_stop\ndata: {}\n", event)
 }
 
+/// Check the tools a reply calls against the OpenAI `tool_choice` it was
+/// requested with
+///
+/// For providers that cannot enforce the choice upstream.
+///
+/// # Errors
+///
+/// Returns a message describing how the reply ignores the choice.
+pub fn check_tool_choice(tool_choice: &Value, called: &[String]) -> Result<(), String> {
+    match tool_choice {
+        Value::String(choice) if choice == "required" && called.is_empty() => {
+            Err("tool_choice requires a tool call, but the reply has none".to_string())
+        }
+        Value::String(choice) if choice == "none" && !called.is_empty() => Err(format!(
+            "tool_choice is none, but the reply calls {}",
+            called.join(", ")
+        )),
+        Value::Object(choice) => {
+            let Some(name) = choice
+                .get("function")
+                .and_then(|f| f.get("name"))
+                .and_then(|n| n.as_str())
+            else {
+                return Ok(());
+            };
+            if called.is_empty() {
+                Err(format!(
+                    "tool_choice requires a call to {}, but the reply has none",
+                    name
+                ))
+            } else if let Some(other) = called.iter().find(|called_name| *called_name != name) {
+                Err(format!(
+                    "tool_choice requires a call to {}, but the reply calls {}",
+                    name, other
+                ))
+            } else {
+                Ok(())
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Names of the tools called in an OpenAI response
+pub fn called_tool_names(openai_response: &OpenAIChatCompletionResponse) -> Vec<String> {
+    openai_response
+        .choices
+        .iter()
+        .filter_map(|choice| choice.message.tool_calls.as_ref())
+        .flatten()
+        .map(|tool_call| tool_call.function.name.clone())
+        .collect()
+}
+
+/// Names of the tools whose calls start in an OpenAI SSE line, and whether
+/// the line ends the reply
+fn streamed_tool_calls(line: &str) -> (Vec<String>, bool) {
+    let data = line.trim().strip_prefix("data: ").unwrap_or_default();
+    if data.trim() == "[DONE]" {
+        return (Vec::new(), true);
+    }
+    let Ok(chunk) = serde_json::from_str::<Value>(data) else {
+        return (Vec::new(), false);
+    };
+
+    let choice = &chunk["choices"][0];
+    let names = choice["delta"]["tool_calls"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|tc| tc["function"]["name"].as_str())
+        .map(str::to_string)
+        .collect();
+    (names, choice["finish_reason"].is_string())
+}
+
+/// Check a streamed reply against the OpenAI `tool_choice` it was requested
+/// with, for providers that cannot enforce the choice upstream
+///
+/// Text and reasoning pass straight through. From the first tool call or
+/// finish chunk on, lines are held until the reply is complete, so a
+/// violation surfaces as an error (built by `violation`) before any tool use
+/// reaches the client.
+pub fn check_streamed_tool_choice<F>(
+    provider_stream: ProviderStream,
+    tool_choice: Value,
+    violation: F,
+) -> ProviderStream
+where
+    F: Fn(String) -> ProviderError + Send + 'static,
+{
+    use futures::StreamExt;
+
+    let stream = async_stream::stream! {
+        let mut provider_stream = provider_stream;
+        let mut held = Vec::new();
+        let mut called = Vec::new();
+        let mut checked = false;
+
+        while let Some(line) = provider_stream.next().await {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            if checked {
+                yield Ok(line);
+                continue;
+            }
+
+            let (names, finished) = streamed_tool_calls(&line);
+            if held.is_empty() && names.is_empty() && !finished {
+                yield Ok(line);
+                continue;
+            }
+
+            called.extend(names);
+            held.push(line);
+            if finished {
+                if let Err(message) = check_tool_choice(&tool_choice, &called) {
+                    yield Err(violation(message));
+                    return;
+                }
+                checked = true;
+                for line in held.drain(..) {
+                    yield Ok(line);
+                }
+            }
+        }
+
+        // The upstream ended without a finish chunk
+        if !checked {
+            if let Err(message) = check_tool_choice(&tool_choice, &called) {
+                yield Err(violation(message));
+                return;
+            }
+            for line in held {
+                yield Ok(line);
+            }
+        }
+    };
+
+    Box::pin(stream)
+}
+
 /// Tool call tracking structure for streaming
 #[derive(Debug, Clone)]
 struct ToolCallState {
@@ -205,282 +359,382 @@ struct ToolCallState {
     started: bool,
 }
 
-/// Convert OpenAI streaming to Claude SSE format with full tool call support
+/// Thinking blocks for a reply's reasoning
 ///
-/// This async generator function processes an OpenAI SSE stream and yields
-/// Claude-formatted SSE events.
-pub async fn convert_openai_streaming_to_claude<S, E>(
-    openai_stream: S,
-    original_model: String,
-) -> Pin<Box<dyn Stream<Item = Result<String, String>> + Send>>
-where
-    S: Stream<Item = Result<String, E>> + Send + 'static,
-    E: std::error::Error + Send + 'static,
-{
-    use futures::StreamExt;
+/// Each `reasoning.text` detail becomes its own block with its own
+/// signature; plain reasoning text becomes a single unsigned block.
+fn thinking_blocks(message: &OpenAIMessage) -> Vec<Value> {
+    let blocks: Vec<Value> = message
+        .reasoning_details
+        .iter()
+        .flatten()
+        .filter(|detail| detail["type"] == "reasoning.text")
+        .map(|detail| {
+            json!({
+                "type": content::THINKING,
+                "thinking": detail["text"].as_str().unwrap_or_default(),
+                "signature": detail["signature"].as_str().unwrap_or_default()
+            })
+        })
+        .collect();
+    if !blocks.is_empty() {
+        return blocks;
+    }
 
-    let message_id = format!(
-        "msg_{}",
-        uuid::Uuid::new_v4().simple().to_string()[..24].to_string()
-    );
+    match message.reasoning_text() {
+        Some(reasoning) if !reasoning.is_empty() => vec![json!({
+            "type": content::THINKING,
+            "thinking": reasoning,
+            "signature": ""
+        })],
+        _ => Vec::new(),
+    }
+}
 
-    let stream = async_stream::stream! {
-        // Send initial SSE events
-        let message_start = json!({
-            "type": event::MESSAGE_START,
-            "message": {
-                "id": &message_id,
-                "type": "message",
-                "role": role::ASSISTANT,
-                "model": &original_model,
-                "content": [],
-                "stop_reason": null,
-                "stop_sequence": null,
-                "usage": {
-                    "input_tokens": 0,
-                    "output_tokens": 0
-                }
-            }
-        });
-        yield Ok(format!("event: {}\ndata: {}\n\n", event::MESSAGE_START, message_start));
+/// Format a Claude SSE event
+fn sse_event(event_type: &str, data: &Value) -> String {
+    format!("event: {}\ndata: {}\n\n", event_type, data)
+}
 
-        let content_block_start = json!({
-            "type": event::CONTENT_BLOCK_START,
-            "index": 0,
-            "content_block": {
-                "type": content::TEXT,
-                "text": ""
-            }
-        });
-        yield Ok(format!("event: {}\ndata: {}\n\n", event::CONTENT_BLOCK_START, content_block_start));
+/// Reasoning text in a streaming delta (`reasoning_content` from DeepSeek
+/// and the Responses API, `reasoning` from OpenRouter)
+fn reasoning_text(delta: &Value) -> Option<&str> {
+    ["reasoning_content", "reasoning"]
+        .iter()
+        .find_map(|key| delta.get(key).and_then(|v| v.as_str()))
+        .filter(|text| !text.is_empty())
+}
 
-        let ping = json!({"type": event::PING});
-        yield Ok(format!("event: {}\ndata: {}\n\n", event::PING, ping));
+/// Reasoning signatures in a streaming delta (`reasoning_signature`, or one
+/// per signed entry of OpenRouter's `reasoning_details`)
+fn reasoning_signatures(delta: &Value) -> Vec<&str> {
+    if let Some(signature) = delta.get("reasoning_signature").and_then(|s| s.as_str()) {
+        return vec![signature];
+    }
 
-        // Track state
-        let text_block_index = 0u32;
-        let mut tool_block_counter = 0u32;
-        let mut current_tool_calls: HashMap<usize, ToolCallState> = HashMap::new();
-        let mut final_stop_reason = stop::END_TURN;
-        let mut usage_data = json!({
-            "input_tokens": 0,
-            "output_tokens": 0
-        });
+    delta
+        .get("reasoning_details")
+        .and_then(|details| details.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|detail| detail.get("signature").and_then(|s| s.as_str()))
+        .filter(|signature| !signature.is_empty())
+        .collect()
+}
 
-        // Process stream
-        tokio::pin!(openai_stream);
+const THINK_OPEN: &str = "<think>";
+const THINK_CLOSE: &str = "</think>";
 
-        while let Some(line_result) = openai_stream.next().await {
-            let line = match line_result {
-                Ok(l) => l,
-                Err(e) => {
-                    error!("Stream error: {}", e);
-                    let error_event = json!({
-                        "type": "error",
-                        "error": {
-                            "type": "api_error",
-                            "message": format!("Stream error: {}", e)
-                        }
-                    });
-                    yield Ok(format!("event: error\ndata: {}\n\n", error_event));
-                    break;
-                }
-            };
+/// Splitter for `<think>...</think>` reasoning inlined in streamed content
+///
+/// A tag may be split across chunks, so a trailing partial tag is held back
+/// until the next chunk shows whether it completes. Whitespace leading the
+/// answer (before the first tag or after a closing one) is dropped.
+struct ThinkTags {
+    inside: bool,
+    pending: String,
+    trim_start: bool,
+}
 
-            let trimmed = line.trim();
-            if trimmed.is_empty() {
-                continue;
-            }
+impl ThinkTags {
+    fn new() -> Self {
+        Self {
+            inside: false,
+            pending: String::new(),
+            trim_start: true,
+        }
+    }
 
-            if !trimmed.starts_with("data: ") {
+    /// Split a content chunk into `(is_thinking, text)` pieces
+    fn split(&mut self, text: &str) -> Vec<(bool, String)> {
+        self.pending.push_str(text);
+        let mut pieces = Vec::new();
+
+        loop {
+            let tag = if self.inside { THINK_CLOSE } else { THINK_OPEN };
+            if let Some(pos) = self.pending.find(tag) {
+                let piece: String = self.pending.drain(..pos).collect();
+                self.pending.drain(..tag.len());
+                self.push_piece(&mut pieces, &piece);
+                self.inside = !self.inside;
+                self.trim_start |= !self.inside;
                 continue;
             }
 
-            let chunk_data = &trimmed[6..];
-            if chunk_data.trim() == "[DONE]" {
-                break;
-            }
+            let held = (1..tag.len())
+                .rev()
+                .find(|&len| self.pending.ends_with(&tag[..len]))
+                .unwrap_or(0);
+            let piece: String = self.pending.drain(..self.pending.len() - held).collect();
+            self.push_piece(&mut pieces, &piece);
+            return pieces;
+        }
+    }
 
-            let chunk: Value = match serde_json::from_str(chunk_data) {
-                Ok(c) => c,
-                Err(e) => {
-                    warn!("Failed to parse chunk: {}, error: {}", chunk_data, e);
-                    continue;
-                }
-            };
+    /// Pieces still held back when the stream ends
+    fn flush(&mut self) -> Vec<(bool, String)> {
+        let piece = std::mem::take(&mut self.pending);
+        let mut pieces = Vec::new();
+        self.push_piece(&mut pieces, &piece);
+        pieces
+    }
 
-            // Extract usage if present
-            if let Some(usage) = chunk.get("usage") {
-                let prompt_tokens = usage.get("prompt_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
-                let completion_tokens = usage.get("completion_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
-                let cache_tokens = usage
-                    .get("prompt_tokens_details")
-                    .and_then(|d| d.get("cached_tokens"))
-                    .and_then(|v| v.as_u64())
-                    .unwrap_or(0);
-
-                usage_data = json!({
-                    "input_tokens": prompt_tokens,
-                    "output_tokens": completion_tokens,
-                    "cache_read_input_tokens": cache_tokens
-                });
-            }
+    fn push_piece(&mut self, pieces: &mut Vec<(bool, String)>, piece: &str) {
+        let piece = if !self.inside && self.trim_start {
+            piece.trim_start()
+        } else {
+            piece
+        };
+        if piece.is_empty() {
+            return;
+        }
+        if !self.inside {
+            self.trim_start = false;
+        }
+        pieces.push((self.inside, piece.to_string()));
+    }
+}
 
-            let choices = match chunk.get("choices").and_then(|c| c.as_array()) {
-                Some(c) if !c.is_empty() => c,
-                _ => continue,
-            };
+/// Content block bookkeeping for a streamed reply
+///
+/// Blocks are numbered in the order they are opened. Reasoning goes into
+/// thinking blocks, which Claude clients expect before the text and tool use
+/// blocks, so they are closed as soon as either starts; later reasoning is
+/// dropped. A signature completes its thinking block, so reasoning after it
+/// opens the next one, as `thinking_blocks` does for whole replies.
+struct ContentBlocks {
+    next_index: u32,
+    thinking_index: Option<u32>,
+    thinking_done: bool,
+    /// Signature of the open thinking block, once it has arrived
+    signature: Option<String>,
+    text_index: Option<u32>,
+    think_tags: Option<ThinkTags>,
+}
 
-            let choice = &choices[0];
-            let delta = choice.get("delta");
-            let finish_reason = choice.get("finish_reason").and_then(|f| f.as_str());
-
-            // Handle text content delta
-            if let Some(content_text) = delta.and_then(|d| d.get("content")).and_then(|c| c.as_str()) {
-                if !content_text.is_empty() {
-                    let content_delta = json!({
-                        "type": event::CONTENT_BLOCK_DELTA,
-                        "index": text_block_index,
-                        "delta": {
-                            "type": delta_const::TEXT,
-                            "text": content_text
-                        }
-                    });
-                    yield Ok(format!("event: {}\ndata: {}\n\n", event::CONTENT_BLOCK_DELTA, content_delta));
-                }
-            }
+impl ContentBlocks {
+    /// With `think_tags`, `<think>` sections of the content become reasoning
+    fn new(think_tags: bool) -> Self {
+        Self {
+            next_index: 0,
+            thinking_index: None,
+            thinking_done: false,
+            signature: None,
+            text_index: None,
+            think_tags: think_tags.then(ThinkTags::new),
+        }
+    }
 
-            // Handle tool calls (check for non-empty array)
-            if let Some(tool_calls) = delta.and_then(|d| d.get("tool_calls")).and_then(|tc| tc.as_array()) {
-                if !tool_calls.is_empty() {
-                for tc_delta in tool_calls {
-                    let tc_index = tc_delta.get("index").and_then(|i| i.as_u64()).unwrap_or(0) as usize;
-
-                    // Initialize tool call state if needed
-                    if !current_tool_calls.contains_key(&tc_index) {
-                        current_tool_calls.insert(tc_index, ToolCallState {
-                            id: None,
-                            name: None,
-                            args_buffer: String::new(),
-                            json_sent: false,
-                            claude_index: None,
-                            started: false,
-                        });
-                    }
+    /// Events for a piece of reply content
+    fn content_delta(&mut self, text: &str) -> Vec<String> {
+        let pieces = match &mut self.think_tags {
+            Some(think_tags) => think_tags.split(text),
+            None => vec![(false, text.to_string())],
+        };
+        self.pieces(pieces)
+    }
 
-                    let tool_call = current_tool_calls.get_mut(&tc_index).unwrap();
+    /// Events for content pieces split by `ThinkTags`
+    fn pieces(&mut self, pieces: Vec<(bool, String)>) -> Vec<String> {
+        let mut events = Vec::new();
+        for (is_thinking, text) in pieces {
+            if is_thinking {
+                events.extend(self.thinking_delta(&text));
+            } else {
+                events.extend(self.text_delta(&text));
+            }
+        }
+        events
+    }
 
-                    // Update ID
-                    if let Some(id) = tc_delta.get("id").and_then(|i| i.as_str()) {
-                        tool_call.id = Some(id.to_string());
-                    }
+    /// Events for a piece of answer text, opening the text block if needed
+    fn text_delta(&mut self, text: &str) -> Vec<String> {
+        let (index, mut events) = self.text_block();
+        events.push(sse_event(
+            event::CONTENT_BLOCK_DELTA,
+            &json!({
+                "type": event::CONTENT_BLOCK_DELTA,
+                "index": index,
+                "delta": {
+                    "type": delta_const::TEXT,
+                    "text": text
+                }
+            }),
+        ));
+        events
+    }
 
-                    // Update function name
-                    if let Some(func) = tc_delta.get("function") {
-                        if let Some(name) = func.get("name").and_then(|n| n.as_str()) {
-                            tool_call.name = Some(name.to_string());
-                        }
+    /// Events for content held back by `ThinkTags`
+    fn flush_content(&mut self) -> Vec<String> {
+        match self.think_tags.as_mut().map(ThinkTags::flush) {
+            Some(pieces) => self.pieces(pieces),
+            None => Vec::new(),
+        }
+    }
 
-                        // Start content block when we have complete initial data
-                        if let (Some(id), Some(name)) = (&tool_call.id, &tool_call.name) {
-                            if !tool_call.started {
-                                tool_block_counter += 1;
-                                let claude_index = text_block_index + tool_block_counter;
-                                tool_call.claude_index = Some(claude_index);
-                                tool_call.started = true;
-
-                                let tool_start = json!({
-                                    "type": event::CONTENT_BLOCK_START,
-                                    "index": claude_index,
-                                    "content_block": {
-                                        "type": content::TOOL_USE,
-                                        "id": id,
-                                        "name": name,
-                                        "input": {}
-                                    }
-                                });
-                                yield Ok(format!("event: {}\ndata: {}\n\n", event::CONTENT_BLOCK_START, tool_start));
-                            }
-                        }
+    /// Index of the open thinking block, with the events opening it if needed
+    ///
+    /// A block that already has its signature is complete, so it is closed
+    /// and a new one opened.
+    fn thinking_block(&mut self) -> (u32, Vec<String>) {
+        let mut events = Vec::new();
+        if self.signature.is_some() {
+            events.extend(self.close_thinking_block());
+        }
+        if let Some(index) = self.thinking_index {
+            return (index, events);
+        }
 
-                        // Handle arguments
-                        if let Some(args) = func.get("arguments").and_then(|a| a.as_str()) {
-                            if tool_call.started && !args.is_empty() {
-                                tool_call.args_buffer.push_str(args);
-
-                                // Try to parse and send when we have valid JSON
-                                if let Ok(_) = serde_json::from_str::<Value>(&tool_call.args_buffer) {
-                                    if !tool_call.json_sent {
-                                        if let Some(claude_idx) = tool_call.claude_index {
-                                            let input_delta = json!({
-                                                "type": event::CONTENT_BLOCK_DELTA,
-                                                "index": claude_idx,
-                                                "delta": {
-                                                    "type": delta_const::INPUT_JSON,
-                                                    "partial_json": &tool_call.args_buffer
-                                                }
-                                            });
-                                            yield Ok(format!("event: {}\ndata: {}\n\n", event::CONTENT_BLOCK_DELTA, input_delta));
-                                            tool_call.json_sent = true;
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+        let index = self.next_index;
+        self.next_index += 1;
+        self.thinking_index = Some(index);
+        events.push(sse_event(
+            event::CONTENT_BLOCK_START,
+            &json!({
+                "type": event::CONTENT_BLOCK_START,
+                "index": index,
+                "content_block": {
+                    "type": content::THINKING,
+                    "thinking": ""
                 }
+            }),
+        ));
+        (index, events)
+    }
+
+    /// Events for a piece of reasoning, opening a thinking block if needed
+    fn thinking_delta(&mut self, thinking: &str) -> Vec<String> {
+        if self.thinking_done {
+            return Vec::new();
+        }
+
+        let (index, mut events) = self.thinking_block();
+        events.push(sse_event(
+            event::CONTENT_BLOCK_DELTA,
+            &json!({
+                "type": event::CONTENT_BLOCK_DELTA,
+                "index": index,
+                "delta": {
+                    "type": delta_const::THINKING,
+                    "thinking": thinking
                 }
-            }
+            }),
+        ));
+        events
+    }
 
-            // Handle finish reason
-            if let Some(reason) = finish_reason {
-                final_stop_reason = match reason {
-                    "length" => stop::MAX_TOKENS,
-                    "tool_calls" | "function_call" => stop::TOOL_USE,
-                    "stop" => stop::END_TURN,
-                    _ => stop::END_TURN,
-                };
-                break;
-            }
+    /// Events for the signature completing the open thinking block
+    ///
+    /// A signature with no reasoning before it (encrypted reasoning without a
+    /// summary) gets an empty thinking block so it still reaches the client.
+    fn signature(&mut self, signature: &str) -> Vec<String> {
+        if self.thinking_done {
+            return Vec::new();
         }
 
-        // Send closing events
-        let content_stop = json!({
-            "type": event::CONTENT_BLOCK_STOP,
-            "index": text_block_index
-        });
-        yield Ok(format!("event: {}\ndata: {}\n\n", event::CONTENT_BLOCK_STOP, content_stop));
+        let (_, events) = self.thinking_block();
+        self.signature = Some(signature.to_string());
+        events
+    }
 
-        // Stop tool call blocks
-        for tool_data in current_tool_calls.values() {
-            if tool_data.started {
-                if let Some(idx) = tool_data.claude_index {
-                    let tool_stop = json!({
-                        "type": event::CONTENT_BLOCK_STOP,
-                        "index": idx
-                    });
-                    yield Ok(format!("event: {}\ndata: {}\n\n", event::CONTENT_BLOCK_STOP, tool_stop));
-                }
-            }
+    /// Events closing the open thinking block, with its signature
+    fn close_thinking_block(&mut self) -> Vec<String> {
+        let Some(index) = self.thinking_index.take() else {
+            return Vec::new();
+        };
+
+        vec![
+            sse_event(
+                event::CONTENT_BLOCK_DELTA,
+                &json!({
+                    "type": event::CONTENT_BLOCK_DELTA,
+                    "index": index,
+                    "delta": {
+                        "type": delta_const::SIGNATURE,
+                        "signature": self.signature.take().unwrap_or_default()
+                    }
+                }),
+            ),
+            sse_event(
+                event::CONTENT_BLOCK_STOP,
+                &json!({"type": event::CONTENT_BLOCK_STOP, "index": index}),
+            ),
+        ]
+    }
+
+    /// Events closing reasoning for good, before text or tool use
+    fn close_thinking(&mut self) -> Vec<String> {
+        self.thinking_done = true;
+        self.close_thinking_block()
+    }
+
+    /// Index of the text block, with the events opening it if needed
+    fn text_block(&mut self) -> (u32, Vec<String>) {
+        if let Some(index) = self.text_index {
+            return (index, Vec::new());
         }
 
-        // Message delta with stop reason and usage
-        let message_delta = json!({
-            "type": event::MESSAGE_DELTA,
-            "delta": {
-                "stop_reason": final_stop_reason,
-                "stop_sequence": null
-            },
-            "usage": usage_data
-        });
-        yield Ok(format!("event: {}\ndata: {}\n\n", event::MESSAGE_DELTA, message_delta));
+        let mut events = self.close_thinking();
+        let index = self.next_index;
+        self.next_index += 1;
+        self.text_index = Some(index);
+        events.push(sse_event(
+            event::CONTENT_BLOCK_START,
+            &json!({
+                "type": event::CONTENT_BLOCK_START,
+                "index": index,
+                "content_block": {
+                    "type": content::TEXT,
+                    "text": ""
+                }
+            }),
+        ));
+        (index, events)
+    }
 
-        // Message stop
-        let message_stop = json!({"type": event::MESSAGE_STOP});
-        yield Ok(format!("event: {}\ndata: {}\n\n", event::MESSAGE_STOP, message_stop));
-    };
+    /// Index for a new tool use block, with the events that must precede it
+    fn tool_block(&mut self) -> (u32, Vec<String>) {
+        let mut events = self.flush_content();
+        events.extend(self.close_thinking());
+        let index = self.next_index;
+        self.next_index += 1;
+        (index, events)
+    }
 
-    Box::pin(stream)
+    /// Events closing the thinking and text blocks
+    ///
+    /// A reply without any content still gets an empty text block.
+    fn finish(&mut self) -> Vec<String> {
+        let mut events = self.flush_content();
+        events.extend(self.close_thinking());
+        if self.next_index == 0 {
+            events.extend(self.text_block().1);
+        }
+        if let Some(index) = self.text_index {
+            events.push(sse_event(
+                event::CONTENT_BLOCK_STOP,
+                &json!({"type": event::CONTENT_BLOCK_STOP, "index": index}),
+            ));
+        }
+        events
+    }
+}
+
+/// Convert OpenAI streaming to Claude SSE format with full tool call support
+///
+/// This async generator function processes an OpenAI SSE stream and yields
+/// Claude-formatted SSE events. With `think_tags`, reasoning the model inlines
+/// in `<think>...</think>` tags is moved into a thinking block.
+pub async fn convert_openai_streaming_to_claude<S, E>(
+    openai_stream: S,
+    original_model: String,
+    think_tags: bool,
+) -> Pin<Box<dyn Stream<Item = Result<String, String>> + Send>>
+where
+    S: Stream<Item = Result<String, E>> + Send + 'static,
+    E: std::error::Error + Send + 'static,
+{
+    claude_event_stream(openai_stream, original_model, think_tags, None)
 }
 
 /// Convert OpenAI streaming to Claude SSE format with client disconnection detection
@@ -490,15 +744,38 @@ where
 pub async fn convert_openai_streaming_to_claude_with_cancellation<S, E>(
     openai_stream: S,
     original_model: String,
+    think_tags: bool,
     provider: std::sync::Arc<dyn crate::core::provider::Provider>,
     request_id: String,
 ) -> Pin<Box<dyn Stream<Item = Result<String, String>> + Send>>
+where
+    S: Stream<Item = Result<String, E>> + Send + 'static,
+    E: std::error::Error + Send + 'static,
+{
+    // Dropped with the stream if the client disconnects mid-reply
+    let cancel_guard = CancelOnDrop::new(provider, request_id);
+    claude_event_stream(
+        openai_stream,
+        original_model,
+        think_tags,
+        Some(cancel_guard),
+    )
+}
+
+/// Claude SSE events for an OpenAI stream, shared by both converters
+///
+/// `cancel_guard` is disarmed once the upstream reply is complete.
+fn claude_event_stream<S, E>(
+    openai_stream: S,
+    original_model: String,
+    think_tags: bool,
+    cancel_guard: Option<CancelOnDrop>,
+) -> Pin<Box<dyn Stream<Item = Result<String, String>> + Send>>
 where
     S: Stream<Item = Result<String, E>> + Send + 'static,
     E: std::error::Error + Send + 'static,
 {
     use futures::StreamExt;
-    use tokio::time::{Duration, interval};
 
     let message_id = format!(
         "msg_{}",
@@ -525,22 +802,11 @@ where
         });
         yield Ok(format!("event: {}\ndata: {}\n\n", event::MESSAGE_START, message_start));
 
-        let content_block_start = json!({
-            "type": event::CONTENT_BLOCK_START,
-            "index": 0,
-            "content_block": {
-                "type": content::TEXT,
-                "text": ""
-            }
-        });
-        yield Ok(format!("event: {}\ndata: {}\n\n", event::CONTENT_BLOCK_START, content_block_start));
-
         let ping = json!({"type": event::PING});
         yield Ok(format!("event: {}\ndata: {}\n\n", event::PING, ping));
 
         // Track state
-        let text_block_index = 0u32;
-        let mut tool_block_counter = 0u32;
+        let mut blocks = ContentBlocks::new(think_tags);
         let mut current_tool_calls: HashMap<usize, ToolCallState> = HashMap::new();
         let mut final_stop_reason = stop::END_TURN;
         let mut usage_data = json!({
@@ -548,215 +814,205 @@ where
             "output_tokens": 0
         });
 
-        // Create a heartbeat to check for cancellation
-        let mut heartbeat = interval(Duration::from_millis(100));
-        let provider_arc = provider.clone();
-        let req_id = request_id.clone();
-
         // Process stream
         tokio::pin!(openai_stream);
 
-        let cancelled = false;
-
         loop {
-            tokio::select! {
-                // Check heartbeat for potential cancellation signals
-                _ = heartbeat.tick() => {
-                    // Just continue - this keeps the loop responsive
-                    continue;
-                }
+            match openai_stream.next().await {
+                Some(Ok(line)) => {
+                    let trimmed = line.trim();
+                    if trimmed.is_empty() {
+                        continue;
+                    }
 
-                // Process stream items
-                line_result = openai_stream.next() => {
-                    match line_result {
-                        Some(Ok(line)) => {
-                            let trimmed = line.trim();
-                            if trimmed.is_empty() {
-                                continue;
-                            }
+                    if !trimmed.starts_with("data: ") {
+                        continue;
+                    }
 
-                            if !trimmed.starts_with("data: ") {
-                                continue;
-                            }
+                    let chunk_data = &trimmed[6..];
+                    if chunk_data.trim() == "[DONE]" {
+                        break;
+                    }
 
-                            let chunk_data = &trimmed[6..];
-                            if chunk_data.trim() == "[DONE]" {
-                                break;
-                            }
+                    let chunk: Value = match serde_json::from_str(chunk_data) {
+                        Ok(c) => c,
+                        Err(e) => {
+                            warn!("Failed to parse chunk: {}, error: {}", chunk_data, e);
+                            continue;
+                        }
+                    };
+
+                    // Extract usage if present
+                    if let Some(usage) = chunk.get("usage") {
+                        let prompt_tokens = usage.get("prompt_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
+                        let completion_tokens = usage.get("completion_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
+                        let cache_tokens = usage
+                            .get("prompt_tokens_details")
+                            .and_then(|d| d.get("cached_tokens"))
+                            .and_then(|v| v.as_u64())
+                            .unwrap_or(0);
+
+                        usage_data = json!({
+                            "input_tokens": prompt_tokens,
+                            "output_tokens": completion_tokens,
+                            "cache_read_input_tokens": cache_tokens
+                        });
+                    }
 
-                            let chunk: Value = match serde_json::from_str(chunk_data) {
-                                Ok(c) => c,
-                                Err(e) => {
-                                    warn!("Failed to parse chunk: {}, error: {}", chunk_data, e);
-                                    continue;
-                                }
-                            };
-
-                            // Extract usage if present
-                            if let Some(usage) = chunk.get("usage") {
-                                let prompt_tokens = usage.get("prompt_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
-                                let completion_tokens = usage.get("completion_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
-                                let cache_tokens = usage
-                                    .get("prompt_tokens_details")
-                                    .and_then(|d| d.get("cached_tokens"))
-                                    .and_then(|v| v.as_u64())
-                                    .unwrap_or(0);
-
-                                usage_data = json!({
-                                    "input_tokens": prompt_tokens,
-                                    "output_tokens": completion_tokens,
-                                    "cache_read_input_tokens": cache_tokens
-                                });
+                    let choices = match chunk.get("choices").and_then(|c| c.as_array()) {
+                        Some(c) if !c.is_empty() => c,
+                        _ => continue,
+                    };
+
+                    let choice = &choices[0];
+                    let delta = choice.get("delta");
+                    let finish_reason = choice.get("finish_reason").and_then(|f| f.as_str());
+
+                    // Handle reasoning delta
+                    if let Some(reasoning) = delta.and_then(reasoning_text) {
+                        for thinking_event in blocks.thinking_delta(reasoning) {
+                            yield Ok(thinking_event);
+                        }
+                    }
+                    for signature in delta.map(reasoning_signatures).unwrap_or_default() {
+                        for signature_event in blocks.signature(signature) {
+                            yield Ok(signature_event);
+                        }
+                    }
+
+                    // Handle text content delta
+                    if let Some(content_text) = delta.and_then(|d| d.get("content")).and_then(|c| c.as_str()) {
+                        if !content_text.is_empty() {
+                            for content_event in blocks.content_delta(content_text) {
+                                yield Ok(content_event);
                             }
+                        }
+                    }
 
-                            let choices = match chunk.get("choices").and_then(|c| c.as_array()) {
-                                Some(c) if !c.is_empty() => c,
-                                _ => continue,
-                            };
-
-                            let choice = &choices[0];
-                            let delta = choice.get("delta");
-                            let finish_reason = choice.get("finish_reason").and_then(|f| f.as_str());
-
-                            // Handle text content delta
-                            if let Some(content_text) = delta.and_then(|d| d.get("content")).and_then(|c| c.as_str()) {
-                                if !content_text.is_empty() {
-                                    let content_delta = json!({
-                                        "type": event::CONTENT_BLOCK_DELTA,
-                                        "index": text_block_index,
-                                        "delta": {
-                                            "type": delta_const::TEXT,
-                                            "text": content_text
-                                        }
-                                    });
-                                    yield Ok(format!("event: {}\ndata: {}\n\n", event::CONTENT_BLOCK_DELTA, content_delta));
-                                }
+                    // Handle tool calls (check for non-empty array)
+                    if let Some(tool_calls) = delta.and_then(|d| d.get("tool_calls")).and_then(|tc| tc.as_array()) {
+                        if !tool_calls.is_empty() {
+                        for tc_delta in tool_calls {
+                            let tc_index = tc_delta.get("index").and_then(|i| i.as_u64()).unwrap_or(0) as usize;
+
+                            // Initialize tool call state if needed
+                            if !current_tool_calls.contains_key(&tc_index) {
+                                current_tool_calls.insert(tc_index, ToolCallState {
+                                    id: None,
+                                    name: None,
+                                    args_buffer: String::new(),
+                                    json_sent: false,
+                                    claude_index: None,
+                                    started: false,
+                                });
                             }
 
-                            // Handle tool calls (check for non-empty array)
-                            if let Some(tool_calls) = delta.and_then(|d| d.get("tool_calls")).and_then(|tc| tc.as_array()) {
-                                if !tool_calls.is_empty() {
-                                for tc_delta in tool_calls {
-                                    let tc_index = tc_delta.get("index").and_then(|i| i.as_u64()).unwrap_or(0) as usize;
-
-                                    // Initialize tool call state if needed
-                                    if !current_tool_calls.contains_key(&tc_index) {
-                                        current_tool_calls.insert(tc_index, ToolCallState {
-                                            id: None,
-                                            name: None,
-                                            args_buffer: String::new(),
-                                            json_sent: false,
-                                            claude_index: None,
-                                            started: false,
-                                        });
-                                    }
+                            let tool_call = current_tool_calls.get_mut(&tc_index).unwrap();
 
-                                    let tool_call = current_tool_calls.get_mut(&tc_index).unwrap();
+                            // Update ID
+                            if let Some(id) = tc_delta.get("id").and_then(|i| i.as_str()) {
+                                tool_call.id = Some(id.to_string());
+                            }
 
-                                    // Update ID
-                                    if let Some(id) = tc_delta.get("id").and_then(|i| i.as_str()) {
-                                        tool_call.id = Some(id.to_string());
-                                    }
+                            // Update function name
+                            if let Some(func) = tc_delta.get("function") {
+                                if let Some(name) = func.get("name").and_then(|n| n.as_str()) {
+                                    tool_call.name = Some(name.to_string());
+                                }
 
-                                    // Update function name
-                                    if let Some(func) = tc_delta.get("function") {
-                                        if let Some(name) = func.get("name").and_then(|n| n.as_str()) {
-                                            tool_call.name = Some(name.to_string());
+                                // Start content block when we have complete initial data
+                                if let (Some(id), Some(name)) = (&tool_call.id, &tool_call.name) {
+                                    if !tool_call.started {
+                                        let (claude_index, close_events) = blocks.tool_block();
+                                        for close_event in close_events {
+                                            yield Ok(close_event);
                                         }
-
-                                        // Start content block when we have complete initial data
-                                        if let (Some(id), Some(name)) = (&tool_call.id, &tool_call.name) {
-                                            if !tool_call.started {
-                                                tool_block_counter += 1;
-                                                let claude_index = text_block_index + tool_block_counter;
-                                                tool_call.claude_index = Some(claude_index);
-                                                tool_call.started = true;
-
-                                                let tool_start = json!({
-                                                    "type": event::CONTENT_BLOCK_START,
-                                                    "index": claude_index,
-                                                    "content_block": {
-                                                        "type": content::TOOL_USE,
-                                                        "id": id,
-                                                        "name": name,
-                                                        "input": {}
-                                                    }
-                                                });
-                                                yield Ok(format!("event: {}\ndata: {}\n\n", event::CONTENT_BLOCK_START, tool_start));
+                                        tool_call.claude_index = Some(claude_index);
+                                        tool_call.started = true;
+
+                                        let tool_start = json!({
+                                            "type": event::CONTENT_BLOCK_START,
+                                            "index": claude_index,
+                                            "content_block": {
+                                                "type": content::TOOL_USE,
+                                                "id": id,
+                                                "name": name,
+                                                "input": {}
                                             }
-                                        }
+                                        });
+                                        yield Ok(format!("event: {}\ndata: {}\n\n", event::CONTENT_BLOCK_START, tool_start));
+                                    }
+                                }
 
-                                        // Handle arguments
-                                        if let Some(args) = func.get("arguments").and_then(|a| a.as_str()) {
-                                            if tool_call.started && !args.is_empty() {
-                                                tool_call.args_buffer.push_str(args);
-
-                                                // Try to parse and send when we have valid JSON
-                                                if let Ok(_) = serde_json::from_str::<Value>(&tool_call.args_buffer) {
-                                                    if !tool_call.json_sent {
-                                                        if let Some(claude_idx) = tool_call.claude_index {
-                                                            let input_delta = json!({
-                                                                "type": event::CONTENT_BLOCK_DELTA,
-                                                                "index": claude_idx,
-                                                                "delta": {
-                                                                    "type": delta_const::INPUT_JSON,
-                                                                    "partial_json": &tool_call.args_buffer
-                                                                }
-                                                            });
-                                                            yield Ok(format!("event: {}\ndata: {}\n\n", event::CONTENT_BLOCK_DELTA, input_delta));
-                                                            tool_call.json_sent = true;
+                                // Handle arguments
+                                if let Some(args) = func.get("arguments").and_then(|a| a.as_str()) {
+                                    if tool_call.started && !args.is_empty() {
+                                        tool_call.args_buffer.push_str(args);
+
+                                        // Try to parse and send when we have valid JSON
+                                        if let Ok(_) = serde_json::from_str::<Value>(&tool_call.args_buffer) {
+                                            if !tool_call.json_sent {
+                                                if let Some(claude_idx) = tool_call.claude_index {
+                                                    let input_delta = json!({
+                                                        "type": event::CONTENT_BLOCK_DELTA,
+                                                        "index": claude_idx,
+                                                        "delta": {
+                                                            "type": delta_const::INPUT_JSON,
+                                                            "partial_json": &tool_call.args_buffer
                                                         }
-                                                    }
+                                                    });
+                                                    yield Ok(format!("event: {}\ndata: {}\n\n", event::CONTENT_BLOCK_DELTA, input_delta));
+                                                    tool_call.json_sent = true;
                                                 }
                                             }
                                         }
                                     }
                                 }
-                                }
-                            }
-
-                            // Handle finish reason
-                            if let Some(reason) = finish_reason {
-                                final_stop_reason = match reason {
-                                    "length" => stop::MAX_TOKENS,
-                                    "tool_calls" | "function_call" => stop::TOOL_USE,
-                                    "stop" => stop::END_TURN,
-                                    _ => stop::END_TURN,
-                                };
-                                break;
                             }
                         }
-                        Some(Err(e)) => {
-                            error!("Stream error: {}", e);
-                            let error_event = json!({
-                                "type": "error",
-                                "error": {
-                                    "type": "api_error",
-                                    "message": format!("Stream error: {}", e)
-                                }
-                            });
-                            yield Ok(format!("event: error\ndata: {}\n\n", error_event));
-                            break;
-                        }
-                        None => {
-                            // Stream ended
-                            break;
                         }
                     }
+
+                    // Handle finish reason
+                    if let Some(reason) = finish_reason {
+                        final_stop_reason = match reason {
+                            "length" => stop::MAX_TOKENS,
+                            "tool_calls" | "function_call" => stop::TOOL_USE,
+                            "content_filter" => stop::REFUSAL,
+                            "stop" => stop::END_TURN,
+                            _ => stop::END_TURN,
+                        };
+                        break;
+                    }
+                }
+                Some(Err(e)) => {
+                    error!("Stream error: {}", e);
+                    let error_event = json!({
+                        "type": "error",
+                        "error": {
+                            "type": "api_error",
+                            "message": format!("Stream error: {}", e)
+                        }
+                    });
+                    yield Ok(format!("event: error\ndata: {}\n\n", error_event));
+                    break;
+                }
+                None => {
+                    // Stream ended
+                    break;
                 }
             }
+        }
 
-            if cancelled {
-                break;
-            }
+        // The upstream reply is complete
+        if let Some(cancel_guard) = cancel_guard {
+            cancel_guard.disarm();
         }
 
         // Send closing events
-        let content_stop = json!({
-            "type": event::CONTENT_BLOCK_STOP,
-            "index": text_block_index
-        });
-        yield Ok(format!("event: {}\ndata: {}\n\n", event::CONTENT_BLOCK_STOP, content_stop));
+        for stop_event in blocks.finish() {
+            yield Ok(stop_event);
+        }
 
         // Stop tool call blocks
         for tool_data in current_tool_calls.values() {
@@ -789,3 +1045,304 @@ where
 
     Box::pin(stream)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    fn events(lines: Vec<Value>, think_tags: bool) -> Vec<Value> {
+        let lines: Vec<Result<String, std::io::Error>> = lines
+            .into_iter()
+            .map(|chunk| Ok(format!("data: {}", chunk)))
+            .collect();
+
+        // Through the converter the endpoints use
+        let provider = std::sync::Arc::new(crate::core::providers::MockProvider::new(Vec::new()));
+        futures::executor::block_on(async {
+            convert_openai_streaming_to_claude_with_cancellation(
+                futures::stream::iter(lines),
+                "claude".to_string(),
+                think_tags,
+                provider,
+                "req-1".to_string(),
+            )
+            .await
+            .map(|event| {
+                let event = event.unwrap();
+                let data = event.lines().nth(1).unwrap().trim_start_matches("data: ");
+                serde_json::from_str(data).unwrap()
+            })
+            .collect()
+            .await
+        })
+    }
+
+    #[test]
+    fn test_stream_reasoning_becomes_thinking_block() {
+        let chunk = |delta: Value| json!({"choices": [{"index": 0, "delta": delta}]});
+        let events = events(
+            vec![
+                chunk(json!({"role": "assistant", "reasoning_content": "Let me "})),
+                chunk(json!({"reasoning": "think."})),
+                chunk(json!({"reasoning_signature": "sig"})),
+                chunk(json!({"content": "Answer"})),
+                json!({"choices": [{"index": 0, "delta": {}, "finish_reason": "stop"}]}),
+            ],
+            false,
+        );
+
+        let summary: Vec<(String, Value)> = events
+            .iter()
+            .filter(|e| e["type"] != "ping" && e["type"] != "message_start")
+            .map(|e| (e["type"].as_str().unwrap().to_string(), e["index"].clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("content_block_start".to_string(), json!(0)),
+                ("content_block_delta".to_string(), json!(0)),
+                ("content_block_delta".to_string(), json!(0)),
+                ("content_block_delta".to_string(), json!(0)),
+                ("content_block_stop".to_string(), json!(0)),
+                ("content_block_start".to_string(), json!(1)),
+                ("content_block_delta".to_string(), json!(1)),
+                ("content_block_stop".to_string(), json!(1)),
+                ("message_delta".to_string(), Value::Null),
+                ("message_stop".to_string(), Value::Null),
+            ]
+        );
+
+        let thinking_start = &events[2];
+        assert_eq!(thinking_start["content_block"]["type"], "thinking");
+        assert_eq!(events[3]["delta"]["thinking"], "Let me ");
+        assert_eq!(events[4]["delta"]["thinking"], "think.");
+        assert_eq!(
+            events[5]["delta"],
+            json!({"type": "signature_delta", "signature": "sig"})
+        );
+        assert_eq!(events[8]["delta"]["text"], "Answer");
+    }
+
+    #[test]
+    fn test_stream_signed_reasoning_details_become_separate_blocks() {
+        let chunk = |delta: Value| json!({"choices": [{"index": 0, "delta": delta}]});
+        let detail = |text: &str, signature: Option<&str>| json!({"type": "reasoning.text", "text": text, "signature": signature});
+        let events = events(
+            vec![
+                chunk(
+                    json!({"reasoning": "First.", "reasoning_details": [detail("First.", None)]}),
+                ),
+                chunk(json!({"reasoning_details": [detail("", Some("sig-1"))]})),
+                chunk(
+                    json!({"reasoning": "Second.", "reasoning_details": [detail("Second.", None)]}),
+                ),
+                chunk(json!({"reasoning_details": [detail("", Some("sig-2"))]})),
+                chunk(json!({"content": "Answer"})),
+                json!({"choices": [{"index": 0, "delta": {}, "finish_reason": "stop"}]}),
+            ],
+            false,
+        );
+
+        let thinking: Vec<(Value, Value)> = events
+            .iter()
+            .filter(|e| e["type"] == "content_block_delta" && e["delta"]["type"] != "text_delta")
+            .map(|e| (e["index"].clone(), e["delta"].clone()))
+            .collect();
+        assert_eq!(
+            thinking,
+            vec![
+                (
+                    json!(0),
+                    json!({"type": "thinking_delta", "thinking": "First."})
+                ),
+                (
+                    json!(0),
+                    json!({"type": "signature_delta", "signature": "sig-1"})
+                ),
+                (
+                    json!(1),
+                    json!({"type": "thinking_delta", "thinking": "Second."})
+                ),
+                (
+                    json!(1),
+                    json!({"type": "signature_delta", "signature": "sig-2"})
+                ),
+            ]
+        );
+        let text_start = events
+            .iter()
+            .find(|e| e["type"] == "content_block_start" && e["content_block"]["type"] == "text")
+            .unwrap();
+        assert_eq!(text_start["index"], 2);
+    }
+
+    #[test]
+    fn test_stream_think_tags_become_thinking_block() {
+        let chunk = |text: &str| json!({"choices": [{"index": 0, "delta": {"content": text}}]});
+        let lines = vec![
+            chunk("<thi"),
+            chunk("nk>Check the <b>"),
+            chunk(" file.</th"),
+            chunk("ink>\n\nIt is "),
+            chunk("fine <"),
+            chunk("3"),
+            json!({"choices": [{"index": 0, "delta": {}, "finish_reason": "stop"}]}),
+        ];
+
+        let deltas = |events: &[Value]| -> Vec<(u64, String)> {
+            events
+                .iter()
+                .filter(|e| e["type"] == "content_block_delta")
+                .filter_map(|e| {
+                    let delta = &e["delta"];
+                    let text = delta["thinking"].as_str().or(delta["text"].as_str())?;
+                    Some((e["index"].as_u64().unwrap(), text.to_string()))
+                })
+                .collect()
+        };
+
+        let split = events(lines.clone(), true);
+        assert_eq!(split[2]["content_block"]["type"], "thinking");
+        let thinking: String = deltas(&split)
+            .iter()
+            .filter(|(index, _)| *index == 0)
+            .map(|(_, text)| text.as_str())
+            .collect();
+        let text: String = deltas(&split)
+            .iter()
+            .filter(|(index, _)| *index == 1)
+            .map(|(_, text)| text.as_str())
+            .collect();
+        assert_eq!(thinking, "Check the <b> file.");
+        assert_eq!(text, "It is fine <3");
+
+        // Without the option the tags pass through untouched
+        let raw: String = deltas(&events(lines, false))
+            .into_iter()
+            .map(|(_, text)| text)
+            .collect();
+        assert_eq!(raw, "<think>Check the <b> file.</think>\n\nIt is fine <3");
+    }
+
+    #[test]
+    fn test_check_tool_choice() {
+        let called =
+            |names: &[&str]| -> Vec<String> { names.iter().map(|n| n.to_string()).collect() };
+        let read = json!({"type": "function", "function": {"name": "read"}});
+
+        assert!(check_tool_choice(&json!("auto"), &[]).is_ok());
+        assert!(check_tool_choice(&json!("required"), &called(&["ls"])).is_ok());
+        assert!(check_tool_choice(&json!("required"), &[]).is_err());
+        assert!(check_tool_choice(&json!("none"), &[]).is_ok());
+        assert!(
+            check_tool_choice(&json!("none"), &called(&["ls"]))
+                .unwrap_err()
+                .contains("calls ls")
+        );
+        assert!(check_tool_choice(&read, &called(&["read", "read"])).is_ok());
+        assert!(check_tool_choice(&read, &[]).is_err());
+        assert!(
+            check_tool_choice(&read, &called(&["read", "ls"]))
+                .unwrap_err()
+                .contains("calls ls")
+        );
+    }
+
+    #[test]
+    fn test_streamed_tool_choice_holds_only_tool_calls() {
+        let line = |chunk: Value| format!("data: {}", chunk);
+        let text = line(json!({"choices": [{"index": 0, "delta": {"content": "Sure."}}]}));
+        let tool_call = line(json!({"choices": [{"index": 0, "delta": {"tool_calls": [
+            {"index": 0, "id": "call_1", "function": {"name": "ls", "arguments": "{}"}}
+        ]}}]}));
+        let finish = line(json!({"choices": [{"index": 0, "delta": {}, "finish_reason": "stop"}]}));
+
+        let check = |lines: Vec<String>, tool_choice: Value| {
+            let upstream: ProviderStream =
+                Box::pin(futures::stream::iter(lines.into_iter().map(Ok)));
+            let checked = check_streamed_tool_choice(upstream, tool_choice, |message| {
+                ProviderError::ApiError {
+                    status: 502,
+                    message,
+                }
+            });
+            futures::executor::block_on(checked.collect::<Vec<_>>())
+        };
+
+        // Text is relayed as it arrives, then the complete tool call
+        let items = check(
+            vec![text.clone(), tool_call.clone(), finish.clone()],
+            json!("required"),
+        );
+        let lines: Vec<String> = items.into_iter().map(|item| item.unwrap()).collect();
+        assert_eq!(lines, vec![text.clone(), tool_call.clone(), finish.clone()]);
+
+        // A forbidden tool call becomes an error before it is relayed
+        let items = check(vec![text.clone(), tool_call, finish.clone()], json!("none"));
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].as_ref().unwrap(), &text);
+        assert!(matches!(
+            &items[1],
+            Err(ProviderError::ApiError { message, .. }) if message.contains("calls ls")
+        ));
+
+        // So does a missing one, even if the stream ends without a finish chunk
+        let items = check(vec![text.clone()], json!("required"));
+        assert_eq!(items.len(), 2);
+        assert!(items[1].is_err());
+    }
+
+    #[test]
+    fn test_non_streaming_reasoning_becomes_thinking_block() {
+        let convert = |message: Value| {
+            let response: OpenAIChatCompletionResponse = serde_json::from_value(json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 0,
+                "model": "deepseek/deepseek-r1",
+                "choices": [{"index": 0, "message": message, "finish_reason": "stop"}],
+                "usage": {"prompt_tokens": 1, "completion_tokens": 2, "total_tokens": 3}
+            }))
+            .unwrap();
+            convert_openai_to_claude(&response, "claude")
+        };
+
+        let claude =
+            convert(json!({"role": "assistant", "content": "Answer", "reasoning": "Because."}));
+        assert_eq!(
+            claude["content"][0],
+            json!({"type": "thinking", "thinking": "Because.", "signature": ""})
+        );
+        assert_eq!(claude["content"][1]["text"], "Answer");
+
+        // Some upstreams send both keys with the same text
+        let claude = convert(json!({
+            "role": "assistant",
+            "content": "Answer",
+            "reasoning_content": "Because.",
+            "reasoning": "Because."
+        }));
+        assert_eq!(claude["content"][0]["thinking"], "Because.");
+
+        // Structured details keep one block and signature each
+        let claude = convert(json!({
+            "role": "assistant",
+            "content": "Answer",
+            "reasoning": "First.Second.",
+            "reasoning_details": [
+                {"type": "reasoning.text", "text": "First.", "signature": "sig-1"},
+                {"type": "reasoning.text", "text": "Second.", "signature": "sig-2"}
+            ]
+        }));
+        assert_eq!(
+            claude["content"][0],
+            json!({"type": "thinking", "thinking": "First.", "signature": "sig-1"})
+        );
+        assert_eq!(
+            claude["content"][1],
+            json!({"type": "thinking", "thinking": "Second.", "signature": "sig-2"})
+        );
+        assert_eq!(claude["content"][2]["text"], "Answer");
+    }
+}
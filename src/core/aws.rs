@@ -0,0 +1,430 @@
+//! AWS request signing and event-stream decoding
+//!
+//! This module implements the small subset of the AWS protocol stack needed to
+//! talk to Bedrock without the AWS SDK: Signature Version 4 request signing and
+//! decoding of the binary `application/vnd.amazon.eventstream` framing.
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Static AWS credentials
+#[derive(Debug, Clone)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl AwsCredentials {
+    /// Resolve credentials from explicit values, falling back to the standard
+    /// `AWS_ACCESS_KEY_ID` / `AWS_SECRET_ACCESS_KEY` / `AWS_SESSION_TOKEN`
+    /// environment variables
+    pub fn resolve(
+        access_key_id: Option<String>,
+        secret_access_key: Option<String>,
+        session_token: Option<String>,
+    ) -> Option<Self> {
+        let access_key_id = access_key_id.or_else(|| std::env::var("AWS_ACCESS_KEY_ID").ok())?;
+        let secret_access_key =
+            secret_access_key.or_else(|| std::env::var("AWS_SECRET_ACCESS_KEY").ok())?;
+        let session_token = session_token.or_else(|| std::env::var("AWS_SESSION_TOKEN").ok());
+
+        Some(Self {
+            access_key_id,
+            secret_access_key,
+            session_token,
+        })
+    }
+}
+
+/// Percent-encode a string using the RFC 3986 unreserved set, as SigV4 requires
+///
+/// When `encode_slash` is false, `/` is kept so whole paths can be encoded.
+pub fn uri_encode(input: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Compute SigV4 authentication headers for a request
+///
+/// `headers` are additional headers that will be sent and should be signed
+/// (e.g. `content-type`). The `host` header is derived from the URL. Returns
+/// the headers the caller must add to the request: `authorization`,
+/// `x-amz-date` and, for temporary credentials, `x-amz-security-token`.
+///
+/// # Arguments
+///
+/// * `credentials` - AWS credentials to sign with
+/// * `region` - AWS region (e.g. "us-east-1")
+/// * `service` - AWS service signing name (e.g. "bedrock")
+/// * `method` - HTTP method
+/// * `url` - Full request URL; the path must already be percent-encoded
+/// * `headers` - Additional headers to sign
+/// * `payload` - Request body
+/// * `now` - Signing timestamp
+#[allow(clippy::too_many_arguments)]
+pub fn sign_request(
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    method: &str,
+    url: &reqwest::Url,
+    headers: &[(&str, &str)],
+    payload: &[u8],
+    now: DateTime<Utc>,
+) -> Vec<(String, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date_stamp = now.format("%Y%m%d").to_string();
+
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+
+    // Canonical headers must be lowercase, trimmed and sorted by name
+    let mut canonical: Vec<(String, String)> = headers
+        .iter()
+        .map(|(name, value)| (name.to_lowercase(), value.trim().to_string()))
+        .collect();
+    canonical.push(("host".to_string(), host));
+    canonical.push(("x-amz-date".to_string(), amz_date.clone()));
+    if let Some(ref token) = credentials.session_token {
+        canonical.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    canonical.sort();
+
+    let canonical_headers: String = canonical
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value))
+        .collect();
+    let signed_headers = canonical
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    // Non-S3 services encode each path segment a second time
+    let canonical_uri = if url.path().is_empty() {
+        "/".to_string()
+    } else {
+        uri_encode(url.path(), false)
+    };
+
+    let mut query_pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(k, v)| (uri_encode(&k, true), uri_encode(&v, true)))
+        .collect();
+    query_pairs.sort();
+    let canonical_query = query_pairs
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        canonical_uri,
+        canonical_query,
+        canonical_headers,
+        signed_headers,
+        sha256_hex(payload)
+    );
+
+    let scope = format!("{}/{}/{}/aws4_request", date_stamp, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        sha256_hex(canonical_request.as_bytes())
+    );
+
+    let k_date = hmac_sha256(
+        format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+        date_stamp.as_bytes(),
+    );
+    let k_region = hmac_sha256(&k_date, region.as_bytes());
+    let k_service = hmac_sha256(&k_region, service.as_bytes());
+    let k_signing = hmac_sha256(&k_service, b"aws4_request");
+    let signature = hex::encode(hmac_sha256(&k_signing, string_to_sign.as_bytes()));
+
+    let mut auth_headers = vec![
+        (
+            "authorization".to_string(),
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                credentials.access_key_id, scope, signed_headers, signature
+            ),
+        ),
+        ("x-amz-date".to_string(), amz_date),
+    ];
+    if let Some(ref token) = credentials.session_token {
+        auth_headers.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    auth_headers
+}
+
+/// A decoded event-stream message
+#[derive(Debug, Clone)]
+pub struct EventStreamMessage {
+    /// String-valued headers (e.g. `:event-type`, `:message-type`)
+    pub headers: HashMap<String, String>,
+    pub payload: Vec<u8>,
+}
+
+impl EventStreamMessage {
+    /// Get a header value by name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|v| v.as_str())
+    }
+}
+
+/// Size of the fixed prelude (total length, headers length, prelude CRC)
+const PRELUDE_LENGTH: usize = 12;
+
+/// Size of the trailing message CRC
+const MESSAGE_CRC_LENGTH: usize = 4;
+
+/// Incremental decoder for `application/vnd.amazon.eventstream` framing
+///
+/// Bytes are pushed as they arrive from the network; complete messages are
+/// returned once their full frame has been buffered, regardless of how the
+/// frames were split across chunks.
+#[derive(Debug, Default)]
+pub struct EventStreamDecoder {
+    buffer: Vec<u8>,
+}
+
+impl EventStreamDecoder {
+    /// Create an empty decoder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append received bytes to the internal buffer
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Decode the next complete message, if one is buffered
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a frame is malformed or fails its CRC check. The
+    /// stream cannot be resynchronised after such an error.
+    pub fn next_message(&mut self) -> Result<Option<EventStreamMessage>, String> {
+        if self.buffer.len() < PRELUDE_LENGTH {
+            return Ok(None);
+        }
+
+        let total_length = read_u32(&self.buffer[0..4]) as usize;
+        let headers_length = read_u32(&self.buffer[4..8]) as usize;
+        let prelude_crc = read_u32(&self.buffer[8..12]);
+
+        if crc32fast::hash(&self.buffer[0..8]) != prelude_crc {
+            return Err("Event stream prelude CRC mismatch".to_string());
+        }
+        if total_length < PRELUDE_LENGTH + headers_length + MESSAGE_CRC_LENGTH {
+            return Err(format!(
+                "Invalid event stream frame length {}",
+                total_length
+            ));
+        }
+        if self.buffer.len() < total_length {
+            return Ok(None);
+        }
+
+        let frame: Vec<u8> = self.buffer.drain(..total_length).collect();
+        let message_crc = read_u32(&frame[total_length - MESSAGE_CRC_LENGTH..]);
+        if crc32fast::hash(&frame[..total_length - MESSAGE_CRC_LENGTH]) != message_crc {
+            return Err("Event stream message CRC mismatch".to_string());
+        }
+
+        let headers_end = PRELUDE_LENGTH + headers_length;
+        let headers = parse_headers(&frame[PRELUDE_LENGTH..headers_end])?;
+        let payload = frame[headers_end..total_length - MESSAGE_CRC_LENGTH].to_vec();
+
+        Ok(Some(EventStreamMessage { headers, payload }))
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Parse event-stream headers, keeping only string-typed values
+fn parse_headers(mut bytes: &[u8]) -> Result<HashMap<String, String>, String> {
+    let mut headers = HashMap::new();
+    let truncated = || "Truncated event stream header".to_string();
+
+    while !bytes.is_empty() {
+        let name_length = bytes[0] as usize;
+        let name = bytes.get(1..1 + name_length).ok_or_else(truncated)?;
+        let name = String::from_utf8_lossy(name).to_string();
+        bytes = &bytes[1 + name_length..];
+
+        let value_type = *bytes.first().ok_or_else(truncated)?;
+        bytes = &bytes[1..];
+
+        let value_length = match value_type {
+            0 | 1 => 0,
+            2 => 1,
+            3 => 2,
+            4 => 4,
+            5 | 8 => 8,
+            9 => 16,
+            6 | 7 => {
+                let length = bytes.get(0..2).ok_or_else(truncated)?;
+                let length = u16::from_be_bytes([length[0], length[1]]) as usize;
+                bytes = &bytes[2..];
+                length
+            }
+            other => return Err(format!("Unknown event stream header type {}", other)),
+        };
+
+        let value = bytes.get(0..value_length).ok_or_else(truncated)?;
+        if value_type == 7 {
+            headers.insert(name, String::from_utf8_lossy(value).to_string());
+        }
+        bytes = &bytes[value_length..];
+    }
+
+    Ok(headers)
+}
+
+/// Encode one event-stream message with string headers
+#[cfg(test)]
+pub(crate) fn encode_message(headers: &[(&str, &str)], payload: &[u8]) -> Vec<u8> {
+    let mut header_bytes = Vec::new();
+    for (name, value) in headers {
+        header_bytes.push(name.len() as u8);
+        header_bytes.extend_from_slice(name.as_bytes());
+        header_bytes.push(7);
+        header_bytes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        header_bytes.extend_from_slice(value.as_bytes());
+    }
+
+    let total_length = PRELUDE_LENGTH + header_bytes.len() + payload.len() + MESSAGE_CRC_LENGTH;
+    let mut frame = Vec::new();
+    frame.extend_from_slice(&(total_length as u32).to_be_bytes());
+    frame.extend_from_slice(&(header_bytes.len() as u32).to_be_bytes());
+    let prelude_crc = crc32fast::hash(&frame);
+    frame.extend_from_slice(&prelude_crc.to_be_bytes());
+    frame.extend_from_slice(&header_bytes);
+    frame.extend_from_slice(payload);
+    let message_crc = crc32fast::hash(&frame);
+    frame.extend_from_slice(&message_crc.to_be_bytes());
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_sign_request_matches_aws_test_suite() {
+        // "get-vanilla" from the AWS SigV4 test suite
+        let credentials = AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        };
+        let url = reqwest::Url::parse("https://example.amazonaws.com/").unwrap();
+        let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+
+        let headers = sign_request(
+            &credentials,
+            "us-east-1",
+            "service",
+            "GET",
+            &url,
+            &[],
+            b"",
+            now,
+        );
+
+        assert_eq!(
+            headers[0].1,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+        assert_eq!(headers[1].1, "20150830T123600Z");
+    }
+
+    #[test]
+    fn test_uri_encode_double_encodes_model_ids() {
+        let path = format!(
+            "/model/{}/converse",
+            uri_encode("anthropic.claude-3-5-sonnet-20240620-v1:0", true)
+        );
+        assert_eq!(
+            path,
+            "/model/anthropic.claude-3-5-sonnet-20240620-v1%3A0/converse"
+        );
+        assert_eq!(
+            uri_encode(&path, false),
+            "/model/anthropic.claude-3-5-sonnet-20240620-v1%253A0/converse"
+        );
+    }
+
+    #[test]
+    fn test_decoder_handles_split_frames() {
+        let mut bytes = encode_message(
+            &[(":message-type", "event"), (":event-type", "messageStart")],
+            br#"{"role":"assistant"}"#,
+        );
+        bytes.extend(encode_message(
+            &[(":message-type", "event"), (":event-type", "messageStop")],
+            br#"{"stopReason":"end_turn"}"#,
+        ));
+
+        let mut decoder = EventStreamDecoder::new();
+        let mut messages = Vec::new();
+        for chunk in bytes.chunks(7) {
+            decoder.push(chunk);
+            while let Some(message) = decoder.next_message().unwrap() {
+                messages.push(message);
+            }
+        }
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].header(":event-type"), Some("messageStart"));
+        assert_eq!(messages[1].payload, br#"{"stopReason":"end_turn"}"#);
+    }
+
+    #[test]
+    fn test_decoder_rejects_corrupt_frame() {
+        let mut bytes = encode_message(&[(":event-type", "metadata")], b"{}");
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+
+        let mut decoder = EventStreamDecoder::new();
+        decoder.push(&bytes);
+        assert!(decoder.next_message().is_err());
+    }
+}
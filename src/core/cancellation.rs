@@ -0,0 +1,241 @@
+//! Cancellation of in-flight upstream requests
+//!
+//! Providers register each request under its id. `cancel` wakes the request,
+//! which then drops its upstream future or byte stream; dropping closes the
+//! HTTP connection, so the upstream stops generating (and billing) tokens.
+//! `CancelOnDrop` calls `cancel` through the provider when the client goes
+//! away before the reply is complete.
+
+use crate::core::provider::{Provider, ProviderError};
+use futures::StreamExt;
+use futures::stream::Stream;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Boxed stream of upstream lines returned by providers
+pub type ProviderStream = Pin<Box<dyn Stream<Item = Result<String, ProviderError>> + Send>>;
+
+/// Requests in flight for one provider, keyed by request id
+#[derive(Default)]
+pub struct ActiveRequests {
+    requests: Mutex<HashMap<String, Arc<Notify>>>,
+}
+
+impl ActiveRequests {
+    /// Track a request until the returned handle is dropped
+    ///
+    /// Requests without an id get a handle that is never cancelled.
+    pub fn register(self: &Arc<Self>, request_id: Option<String>) -> CancelHandle {
+        let notify = Arc::new(Notify::new());
+        let registration = request_id.map(|id| {
+            self.requests
+                .lock()
+                .unwrap()
+                .insert(id.clone(), notify.clone());
+            (self.clone(), id)
+        });
+
+        CancelHandle {
+            notify,
+            registration,
+        }
+    }
+
+    /// Cancel the request registered under `request_id`
+    ///
+    /// Returns false if no such request is in flight.
+    pub fn cancel(&self, request_id: &str) -> bool {
+        match self.requests.lock().unwrap().get(request_id) {
+            Some(notify) => {
+                // Stores a permit, so a cancel between awaits is not lost
+                notify.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Registration of one in-flight request; deregisters it when dropped
+pub struct CancelHandle {
+    notify: Arc<Notify>,
+    registration: Option<(Arc<ActiveRequests>, String)>,
+}
+
+impl CancelHandle {
+    /// Run `future` until it completes or the request is cancelled
+    pub async fn run<T, F>(&self, future: F) -> Result<T, ProviderError>
+    where
+        F: Future<Output = Result<T, ProviderError>>,
+    {
+        tokio::select! {
+            biased;
+            _ = self.notify.notified() => Err(ProviderError::Cancelled),
+            result = future => result,
+        }
+    }
+
+    /// Relay `stream` until it ends or the request is cancelled
+    ///
+    /// On cancel a single `Cancelled` error is yielded and the upstream
+    /// stream is dropped. The request stays registered until the returned
+    /// stream is dropped.
+    pub fn wrap_stream(self, stream: ProviderStream) -> ProviderStream {
+        let stream = async_stream::stream! {
+            let handle = self;
+            let mut stream = stream;
+
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = handle.notify.notified() => {
+                        tracing::info!("Upstream stream cancelled by client");
+                        yield Err(ProviderError::Cancelled);
+                        break;
+                    }
+                    item = stream.next() => match item {
+                        Some(item) => yield item,
+                        None => break,
+                    },
+                }
+            }
+        };
+
+        Box::pin(stream)
+    }
+}
+
+impl Drop for CancelHandle {
+    fn drop(&mut self) {
+        if let Some((active_requests, id)) = self.registration.take() {
+            active_requests.requests.lock().unwrap().remove(&id);
+        }
+    }
+}
+
+/// Cancels a request through its provider when dropped, unless disarmed
+///
+/// Held while a reply is relayed to the client. Axum drops the handler
+/// future or the SSE stream when the client disconnects, which drops this
+/// guard and cancels the upstream request.
+pub struct CancelOnDrop {
+    provider: Option<Arc<dyn Provider>>,
+    request_id: String,
+}
+
+impl CancelOnDrop {
+    pub fn new(provider: Arc<dyn Provider>, request_id: String) -> Self {
+        Self {
+            provider: Some(provider),
+            request_id,
+        }
+    }
+
+    /// The reply was delivered; nothing to cancel
+    pub fn disarm(mut self) {
+        self.provider = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let Some(provider) = self.provider.take() else {
+            return;
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+
+        let request_id = std::mem::take(&mut self.request_id);
+        runtime.spawn(async move {
+            if provider.cancel_request(&request_id).await {
+                tracing::info!(
+                    "Client disconnected; cancelled upstream request {}",
+                    request_id
+                );
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel_interrupts_pending_request() {
+        let active_requests = Arc::new(ActiveRequests::default());
+        let handle = active_requests.register(Some("req-1".to_string()));
+
+        assert!(active_requests.cancel("req-1"));
+        let result = handle
+            .run(futures::future::pending::<Result<(), ProviderError>>())
+            .await;
+        assert!(matches!(result, Err(ProviderError::Cancelled)));
+
+        drop(handle);
+        assert!(!active_requests.cancel("req-1"));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_guard_cancels_through_provider() {
+        use crate::core::providers::MockProvider;
+        use crate::core::providers::mock::MockFixture;
+
+        let fixture: MockFixture = serde_json::from_value(serde_json::json!({
+            "match": {},
+            "responses": [{"text": "slow", "stream": {"first_chunk_delay_ms": 60000}}]
+        }))
+        .unwrap();
+        let provider: Arc<dyn Provider> = Arc::new(MockProvider::new(vec![fixture]));
+        let request = serde_json::from_value(serde_json::json!({
+            "model": "mock",
+            "messages": [{"role": "user", "content": "hi"}]
+        }))
+        .unwrap();
+
+        let mut stream = provider
+            .create_chat_completion_stream(request, Some("req-1".to_string()))
+            .await
+            .unwrap();
+
+        // The client goes away before the reply is delivered
+        drop(CancelOnDrop::new(provider.clone(), "req-1".to_string()));
+
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(ProviderError::Cancelled))
+        ));
+        drop(stream);
+        assert!(!provider.cancel_request("req-1").await);
+    }
+
+    #[tokio::test]
+    async fn test_stream_deregisters_when_finished_or_cancelled() {
+        let active_requests = Arc::new(ActiveRequests::default());
+
+        let lines: ProviderStream = Box::pin(futures::stream::iter(vec![Ok("a".to_string())]));
+        let stream = active_requests
+            .register(Some("done".to_string()))
+            .wrap_stream(lines);
+        let items: Vec<_> = stream.collect().await;
+        assert_eq!(items.len(), 1);
+        assert!(!active_requests.cancel("done"));
+
+        let pending: ProviderStream = Box::pin(futures::stream::pending());
+        let mut stream = active_requests
+            .register(Some("stuck".to_string()))
+            .wrap_stream(pending);
+        assert!(active_requests.cancel("stuck"));
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(ProviderError::Cancelled))
+        ));
+        assert!(stream.next().await.is_none());
+        drop(stream);
+        assert!(!active_requests.cancel("stuck"));
+    }
+}
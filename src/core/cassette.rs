@@ -0,0 +1,614 @@
+//! Record and replay of upstream exchanges
+//!
+//! In record mode every request a provider sends upstream is saved together
+//! with its response (or raw stream lines, or error) as
+//! `<dir>/<key>.json`, where the key is a hash of the provider, the kind of
+//! call and the request. Replay mode serves those files instead of calling
+//! the upstream, so a reported stream can be fed through the converters
+//! again deterministically and without network access.
+
+use crate::core::cancellation::ProviderStream;
+use crate::core::config::ProviderConfig;
+use crate::core::provider::{Provider, ProviderCapabilities, ProviderError, ProviderType};
+use crate::core::providers::{
+    AnthropicProvider, BedrockProvider, GoogleAIProvider, MockProvider, OllamaProvider,
+    OpenAIProvider, OpenRouterProvider, VertexAIProvider, gemini,
+};
+use crate::models::openai::{OpenAIChatCompletionRequest, OpenAIChatCompletionResponse};
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// What a cassette provider does with upstream exchanges
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Call the upstream and save every exchange
+    Record,
+    /// Serve saved exchanges without calling the upstream
+    Replay,
+}
+
+impl CassetteMode {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "record" => Some(CassetteMode::Record),
+            "replay" => Some(CassetteMode::Replay),
+            _ => None,
+        }
+    }
+}
+
+/// A provider error as saved on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedError {
+    kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    message: String,
+    /// Error body of a relayed Anthropic error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<Value>,
+}
+
+impl RecordedError {
+    fn from_error(error: &ProviderError) -> Self {
+        let (kind, status, message) = match error {
+            ProviderError::Authentication(m) => ("authentication", None, m.clone()),
+            ProviderError::RateLimit(m) => ("rate_limit", None, m.clone()),
+            ProviderError::BadRequest(m) => ("bad_request", None, m.clone()),
+            ProviderError::ApiError { status, message } => {
+                ("api_error", Some(*status), message.clone())
+            }
+            ProviderError::Relayed { status, body } => {
+                return Self {
+                    kind: "relayed".to_string(),
+                    status: Some(*status),
+                    message: String::new(),
+                    body: Some(body.clone()),
+                };
+            }
+            ProviderError::Connection(m) => ("connection", None, m.clone()),
+            ProviderError::Unsupported(m) => ("unsupported", None, m.clone()),
+            ProviderError::CircuitOpen(m) => ("circuit_open", None, m.clone()),
+            ProviderError::Cancelled => ("cancelled", None, String::new()),
+            ProviderError::Unexpected(m) => ("unexpected", None, m.clone()),
+        };
+
+        Self {
+            kind: kind.to_string(),
+            status,
+            message,
+            body: None,
+        }
+    }
+
+    fn to_error(&self) -> ProviderError {
+        let message = self.message.clone();
+        match self.kind.as_str() {
+            "authentication" => ProviderError::Authentication(message),
+            "rate_limit" => ProviderError::RateLimit(message),
+            "bad_request" => ProviderError::BadRequest(message),
+            "api_error" => ProviderError::ApiError {
+                status: self.status.unwrap_or(500),
+                message,
+            },
+            "relayed" => ProviderError::Relayed {
+                status: self.status.unwrap_or(500),
+                body: self.body.clone().unwrap_or_default(),
+            },
+            "connection" => ProviderError::Connection(message),
+            "unsupported" => ProviderError::Unsupported(message),
+            "circuit_open" => ProviderError::CircuitOpen(message),
+            "cancelled" => ProviderError::Cancelled,
+            _ => ProviderError::Unexpected(message),
+        }
+    }
+}
+
+/// One saved upstream exchange
+#[derive(Debug, Serialize, Deserialize)]
+struct Exchange {
+    provider: String,
+    kind: String,
+    request: Value,
+    /// Body of a non-streaming reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    response: Option<Value>,
+    /// Raw lines of a streaming reply, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    stream: Vec<String>,
+    /// Error returned before or during the reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<RecordedError>,
+}
+
+impl Exchange {
+    fn save(&self, path: &Path) {
+        let result = serde_json::to_vec_pretty(self)
+            .map_err(std::io::Error::other)
+            .and_then(|bytes| std::fs::write(path, bytes));
+        match result {
+            Ok(()) => tracing::info!("Recorded upstream exchange {}", path.display()),
+            Err(e) => tracing::error!("Failed to record exchange {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Saves an exchange's stream lines as they pass, writing the file when the
+/// stream ends or is dropped
+struct StreamRecorder {
+    exchange: Exchange,
+    path: PathBuf,
+}
+
+impl Drop for StreamRecorder {
+    fn drop(&mut self) {
+        self.exchange.save(&self.path);
+    }
+}
+
+const CHAT_COMPLETION: &str = "chat_completion";
+const CHAT_COMPLETION_STREAM: &str = "chat_completion_stream";
+const CLAUDE_MESSAGE: &str = "claude_message";
+const CLAUDE_MESSAGE_STREAM: &str = "claude_message_stream";
+
+/// Provider wrapper that records or replays upstream exchanges
+pub struct CassetteProvider {
+    inner: Arc<dyn Provider>,
+    mode: CassetteMode,
+    dir: PathBuf,
+}
+
+impl CassetteProvider {
+    /// Wrap a provider
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - Provider that serves recorded requests
+    /// * `mode` - Record or replay
+    /// * `dir` - Directory holding the recorded exchanges
+    pub fn new(inner: Arc<dyn Provider>, mode: CassetteMode, dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        if mode == CassetteMode::Record
+            && let Err(e) = std::fs::create_dir_all(&dir)
+        {
+            tracing::error!(
+                "Failed to create cassette directory {}: {}",
+                dir.display(),
+                e
+            );
+        }
+
+        Self { inner, mode, dir }
+    }
+
+    /// Stable key for an exchange
+    ///
+    /// JSON objects serialize with sorted keys, so equal requests hash the
+    /// same regardless of map ordering.
+    pub fn request_key(provider: &str, kind: &str, request: &Value) -> String {
+        let canonical = json!({ "provider": provider, "kind": kind, "request": request });
+        let digest = Sha256::digest(canonical.to_string().as_bytes());
+        hex::encode(&digest[..12])
+    }
+
+    fn new_exchange(&self, kind: &str, request: Value) -> (Exchange, PathBuf) {
+        let key = Self::request_key(self.inner.provider_name(), kind, &request);
+        let path = self.dir.join(format!("{}.json", key));
+        let exchange = Exchange {
+            provider: self.inner.provider_name().to_string(),
+            kind: kind.to_string(),
+            request,
+            response: None,
+            stream: Vec::new(),
+            error: None,
+        };
+        (exchange, path)
+    }
+
+    /// Load the recorded exchange for a request
+    fn replay(&self, kind: &str, request: Value) -> Result<Exchange, ProviderError> {
+        let (_, path) = self.new_exchange(kind, request);
+        let content = std::fs::read_to_string(&path).map_err(|e| {
+            ProviderError::Unexpected(format!(
+                "No recorded exchange {} for this request: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        tracing::info!("Replaying upstream exchange {}", path.display());
+        serde_json::from_str(&content).map_err(|e| {
+            ProviderError::Unexpected(format!(
+                "Invalid recorded exchange {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Serve a recorded non-streaming reply
+    fn replay_response(&self, kind: &str, request: Value) -> Result<Value, ProviderError> {
+        let exchange = self.replay(kind, request)?;
+        match (exchange.error, exchange.response) {
+            (Some(error), _) => Err(error.to_error()),
+            (None, Some(response)) => Ok(response),
+            (None, None) => Err(ProviderError::Unexpected(
+                "Recorded exchange has no response".to_string(),
+            )),
+        }
+    }
+
+    /// Serve a recorded stream, ending with its error if one was recorded
+    fn replay_stream(&self, kind: &str, request: Value) -> Result<ProviderStream, ProviderError> {
+        let exchange = self.replay(kind, request)?;
+        if exchange.stream.is_empty()
+            && let Some(error) = exchange.error
+        {
+            return Err(error.to_error());
+        }
+
+        let lines = exchange.stream.into_iter().map(Ok);
+        let error = exchange.error.map(|error| Err(error.to_error()));
+        Ok(Box::pin(futures::stream::iter(lines.chain(error))))
+    }
+
+    /// Save a non-streaming exchange
+    fn record_response<T: Serialize>(
+        &self,
+        kind: &str,
+        request: Value,
+        result: &Result<T, ProviderError>,
+    ) {
+        if matches!(result, Err(ProviderError::Cancelled)) {
+            return;
+        }
+
+        let (mut exchange, path) = self.new_exchange(kind, request);
+        match result {
+            Ok(response) => exchange.response = serde_json::to_value(response).ok(),
+            Err(e) => exchange.error = Some(RecordedError::from_error(e)),
+        }
+        exchange.save(&path);
+    }
+
+    /// Save a streaming exchange as its lines pass through
+    fn record_stream(
+        &self,
+        kind: &str,
+        request: Value,
+        result: Result<ProviderStream, ProviderError>,
+    ) -> Result<ProviderStream, ProviderError> {
+        let (mut exchange, path) = self.new_exchange(kind, request);
+        let mut stream = match result {
+            Ok(stream) => stream,
+            Err(e) => {
+                if !matches!(e, ProviderError::Cancelled) {
+                    exchange.error = Some(RecordedError::from_error(&e));
+                    exchange.save(&path);
+                }
+                return Err(e);
+            }
+        };
+
+        let stream = async_stream::stream! {
+            let mut recorder = StreamRecorder { exchange, path };
+
+            while let Some(item) = stream.next().await {
+                match item {
+                    Ok(ref line) => recorder.exchange.stream.push(line.clone()),
+                    Err(ref e) => recorder.exchange.error = Some(RecordedError::from_error(e)),
+                }
+                yield item;
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// JSON form of a request, for hashing and saving
+fn request_value<T: Serialize>(request: &T) -> Value {
+    serde_json::to_value(request).unwrap_or(Value::Null)
+}
+
+#[async_trait]
+impl Provider for CassetteProvider {
+    async fn create_chat_completion(
+        &self,
+        request: &OpenAIChatCompletionRequest,
+        request_id: Option<String>,
+    ) -> Result<OpenAIChatCompletionResponse, ProviderError> {
+        let recorded = request_value(request);
+        match self.mode {
+            CassetteMode::Replay => {
+                let response = self.replay_response(CHAT_COMPLETION, recorded)?;
+                serde_json::from_value(response).map_err(|e| {
+                    ProviderError::Unexpected(format!("Invalid recorded response: {}", e))
+                })
+            }
+            CassetteMode::Record => {
+                let result = self.inner.create_chat_completion(request, request_id).await;
+                self.record_response(CHAT_COMPLETION, recorded, &result);
+                result
+            }
+        }
+    }
+
+    async fn create_chat_completion_stream(
+        &self,
+        request: OpenAIChatCompletionRequest,
+        request_id: Option<String>,
+    ) -> Result<ProviderStream, ProviderError> {
+        let recorded = request_value(&request);
+        match self.mode {
+            CassetteMode::Replay => self.replay_stream(CHAT_COMPLETION_STREAM, recorded),
+            CassetteMode::Record => {
+                let result = self
+                    .inner
+                    .create_chat_completion_stream(request, request_id)
+                    .await;
+                self.record_stream(CHAT_COMPLETION_STREAM, recorded, result)
+            }
+        }
+    }
+
+    fn capabilities(&self, model: &str) -> ProviderCapabilities {
+        self.inner.capabilities(model)
+    }
+
+    fn accepts_claude_format(&self, model: &str) -> bool {
+        self.inner.accepts_claude_format(model)
+    }
+
+    async fn create_claude_message(
+        &self,
+        request: &Value,
+        request_id: Option<String>,
+    ) -> Result<Value, ProviderError> {
+        match self.mode {
+            CassetteMode::Replay => self.replay_response(CLAUDE_MESSAGE, request.clone()),
+            CassetteMode::Record => {
+                let result = self.inner.create_claude_message(request, request_id).await;
+                self.record_response(CLAUDE_MESSAGE, request.clone(), &result);
+                result
+            }
+        }
+    }
+
+    async fn create_claude_message_stream(
+        &self,
+        request: Value,
+        request_id: Option<String>,
+    ) -> Result<ProviderStream, ProviderError> {
+        match self.mode {
+            CassetteMode::Replay => self.replay_stream(CLAUDE_MESSAGE_STREAM, request),
+            CassetteMode::Record => {
+                let result = self
+                    .inner
+                    .create_claude_message_stream(request.clone(), request_id)
+                    .await;
+                self.record_stream(CLAUDE_MESSAGE_STREAM, request, result)
+            }
+        }
+    }
+
+    async fn cancel_request(&self, request_id: &str) -> bool {
+        self.inner.cancel_request(request_id).await
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+}
+
+/// Stand-in for the upstream provider while replaying
+///
+/// Replay never calls the upstream, so no credentials or client are needed.
+/// The name and capabilities still come from the configured provider type,
+/// since they decide how requests are converted and keyed.
+pub struct ReplayTarget {
+    config: ProviderConfig,
+}
+
+impl ReplayTarget {
+    /// Stand in for the provider configured by `config`
+    pub fn new(config: ProviderConfig) -> Self {
+        Self { config }
+    }
+
+    fn no_upstream(&self) -> ProviderError {
+        ProviderError::Unexpected(format!(
+            "{} has no upstream while replaying",
+            self.provider_name()
+        ))
+    }
+}
+
+#[async_trait]
+impl Provider for ReplayTarget {
+    async fn create_chat_completion(
+        &self,
+        _request: &OpenAIChatCompletionRequest,
+        _request_id: Option<String>,
+    ) -> Result<OpenAIChatCompletionResponse, ProviderError> {
+        Err(self.no_upstream())
+    }
+
+    async fn create_chat_completion_stream(
+        &self,
+        _request: OpenAIChatCompletionRequest,
+        _request_id: Option<String>,
+    ) -> Result<ProviderStream, ProviderError> {
+        Err(self.no_upstream())
+    }
+
+    fn capabilities(&self, model: &str) -> ProviderCapabilities {
+        match self.config.provider {
+            ProviderType::OpenAI => {
+                OpenAIProvider::model_capabilities(model, self.config.openai_use_responses_api)
+            }
+            ProviderType::OpenRouter => OpenRouterProvider::model_capabilities(),
+            ProviderType::VertexAI => VertexAIProvider::model_capabilities(model),
+            ProviderType::Ollama => OllamaProvider::model_capabilities(),
+            ProviderType::Bedrock => BedrockProvider::model_capabilities(),
+            ProviderType::GoogleAI => gemini::capabilities(model),
+            ProviderType::Anthropic | ProviderType::Mock => ProviderCapabilities::default(),
+        }
+    }
+
+    fn accepts_claude_format(&self, model: &str) -> bool {
+        match self.config.provider {
+            ProviderType::Anthropic => true,
+            ProviderType::VertexAI => VertexAIProvider::is_claude_model(model),
+            _ => false,
+        }
+    }
+
+    async fn cancel_request(&self, _request_id: &str) -> bool {
+        false
+    }
+
+    fn provider_name(&self) -> &str {
+        match self.config.provider {
+            ProviderType::OpenAI if self.config.azure_api_version.is_some() => {
+                OpenAIProvider::AZURE_NAME
+            }
+            ProviderType::OpenAI => OpenAIProvider::NAME,
+            ProviderType::OpenRouter => OpenRouterProvider::NAME,
+            ProviderType::VertexAI => VertexAIProvider::NAME,
+            ProviderType::Anthropic => AnthropicProvider::NAME,
+            ProviderType::Ollama => OllamaProvider::NAME,
+            ProviderType::Bedrock => BedrockProvider::NAME,
+            ProviderType::GoogleAI => GoogleAIProvider::NAME,
+            ProviderType::Mock => MockProvider::NAME,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::providers::mock::MockFixture;
+
+    fn mock(text: &str) -> Arc<dyn Provider> {
+        let fixture: MockFixture = serde_json::from_value(json!({
+            "responses": [{
+                "text": text,
+                "error": {"status": 503, "after_chunks": 3},
+                "stream": {"chunk_chars": 2, "chunk_delay_ms": 0}
+            }]
+        }))
+        .unwrap();
+        Arc::new(MockProvider::new(vec![fixture]))
+    }
+
+    fn request() -> OpenAIChatCompletionRequest {
+        serde_json::from_value(json!({
+            "model": "mock-model",
+            "messages": [{"role": "user", "content": "hello"}]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_request_key_is_stable() {
+        let a = json!({"model": "m", "messages": [], "temperature": 0.5});
+        let b: Value =
+            serde_json::from_str(r#"{"temperature": 0.5, "messages": [], "model": "m"}"#).unwrap();
+        assert_eq!(
+            CassetteProvider::request_key("OpenAI", CHAT_COMPLETION, &a),
+            CassetteProvider::request_key("OpenAI", CHAT_COMPLETION, &b)
+        );
+        assert_ne!(
+            CassetteProvider::request_key("OpenAI", CHAT_COMPLETION, &a),
+            CassetteProvider::request_key("OpenAI", CHAT_COMPLETION_STREAM, &a)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replays_recorded_stream() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let recorder = CassetteProvider::new(mock("abcdef"), CassetteMode::Record, dir.path());
+        let recorded: Vec<_> = recorder
+            .create_chat_completion_stream(request(), None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // The replaying provider's own reply would differ; it must not be used
+        let player = CassetteProvider::new(mock("zzz"), CassetteMode::Replay, dir.path());
+        let replayed: Vec<_> = player
+            .create_chat_completion_stream(request(), None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        assert_eq!(recorded.len(), 4);
+        assert_eq!(replayed.len(), recorded.len());
+        for (recorded, replayed) in recorded.iter().zip(&replayed).take(3) {
+            assert_eq!(recorded.as_ref().unwrap(), replayed.as_ref().unwrap());
+        }
+        assert!(matches!(
+            replayed[3],
+            Err(ProviderError::ApiError { status: 503, .. })
+        ));
+
+        // Unrecorded requests fail instead of reaching the upstream
+        let result = player.create_chat_completion(&request(), None).await;
+        assert!(matches!(result, Err(ProviderError::Unexpected(_))));
+    }
+
+    #[tokio::test]
+    async fn test_replay_builds_no_upstream() {
+        use crate::core::config::Config;
+        use crate::core::registry::ProviderRegistry;
+        use std::io::Write;
+
+        // No credentials are configured for Vertex AI
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"
+            provider = "vertexai"
+
+            [vertexai]
+            project_id = "project"
+            location = "us-east5"
+
+            [cassette]
+            mode = "replay"
+
+            [models]
+            big_model = "claude-sonnet-4@20250514"
+            middle_model = "claude-sonnet-4@20250514"
+            small_model = "gemini-2.5-flash"
+        "#
+        )
+        .unwrap();
+        file.flush().unwrap();
+        let mut config = Config::from_file(file.path()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        config.cassette_dir = dir.path().display().to_string();
+
+        let registry = ProviderRegistry::from_config(&config);
+        let provider = registry.get(ProviderType::VertexAI).unwrap();
+        assert_eq!(provider.provider_name(), VertexAIProvider::NAME);
+        assert!(provider.accepts_claude_format("claude-sonnet-4@20250514"));
+        assert!(!provider.accepts_claude_format("gemini-2.5-flash"));
+        assert_eq!(
+            provider.capabilities("gemini-2.5-flash").max_output_tokens,
+            gemini::capabilities("gemini-2.5-flash").max_output_tokens
+        );
+
+        let result = provider.create_chat_completion(&request(), None).await;
+        assert!(matches!(result, Err(ProviderError::Unexpected(_))));
+    }
+}
@@ -0,0 +1,440 @@
+//! Per-upstream circuit breakers
+//!
+//! Tracks a rolling error rate for every (provider, upstream model) pair.
+//! When too many recent requests fail, the circuit opens and requests fail
+//! fast (or go to a fallback) instead of waiting on a struggling upstream.
+//! After a cooldown a single probe request is let through; its outcome
+//! closes the circuit again or reopens it.
+
+use crate::core::config::CircuitBreakerConfig;
+use crate::core::provider::ProviderType;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Breaker state for one upstream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally
+    Closed,
+    /// Requests fail fast until the cooldown ends
+    Open,
+    /// One probe request decides whether to close or reopen
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+}
+
+/// Point-in-time view of one circuit, as reported by `/health`
+#[derive(Debug, Clone)]
+pub struct CircuitStatus {
+    pub provider: ProviderType,
+    pub model: String,
+    pub state: CircuitState,
+    /// Requests recorded within the rolling window
+    pub requests: usize,
+    /// Fraction of those requests that failed
+    pub error_rate: f64,
+    /// Time until an open circuit admits a probe
+    pub retry_in: Option<Duration>,
+    pub last_error: Option<String>,
+}
+
+struct Circuit {
+    state: CircuitState,
+    /// (time, failed) for each request within the window
+    outcomes: VecDeque<(Instant, bool)>,
+    opened_at: Option<Instant>,
+    probe_in_flight: bool,
+    last_error: Option<String>,
+}
+
+impl Circuit {
+    fn new() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            outcomes: VecDeque::new(),
+            opened_at: None,
+            probe_in_flight: false,
+            last_error: None,
+        }
+    }
+
+    fn prune(&mut self, now: Instant, window: Duration) {
+        while let Some(&(at, _)) = self.outcomes.front() {
+            if now.duration_since(at) <= window {
+                break;
+            }
+            self.outcomes.pop_front();
+        }
+    }
+
+    fn error_rate(&self) -> f64 {
+        if self.outcomes.is_empty() {
+            return 0.0;
+        }
+        let failures = self.outcomes.iter().filter(|(_, failed)| *failed).count();
+        failures as f64 / self.outcomes.len() as f64
+    }
+
+    fn open(&mut self, now: Instant) {
+        self.state = CircuitState::Open;
+        self.opened_at = Some(now);
+        self.probe_in_flight = false;
+    }
+}
+
+/// One request admitted by [`CircuitBreakers::admit`]
+///
+/// Dropping the attempt without recording an outcome, as happens when the
+/// client disconnects mid-request, frees the probe slot it may hold so the
+/// circuit cannot stay half-open for good.
+pub struct CircuitAttempt<'a> {
+    breakers: &'a CircuitBreakers,
+    provider: ProviderType,
+    model: String,
+    /// Whether this request is the half-open probe
+    probe: bool,
+    finished: bool,
+}
+
+impl CircuitAttempt<'_> {
+    /// The upstream answered
+    pub fn success(mut self) {
+        self.finished = true;
+        self.breakers.record_success(self.provider, &self.model);
+    }
+
+    /// The request failed because of the upstream
+    pub fn failure(mut self, error: &str) {
+        self.finished = true;
+        self.breakers
+            .record_failure(self.provider, &self.model, error);
+    }
+
+    /// The outcome says nothing about the upstream
+    ///
+    /// Client errors and cancellations leave the circuit as it is, but free
+    /// the half-open probe slot so the next request can probe instead.
+    pub fn release(self) {
+        // Dropping frees the probe slot
+    }
+}
+
+impl Drop for CircuitAttempt<'_> {
+    fn drop(&mut self) {
+        if !self.finished && self.probe {
+            self.breakers.release(self.provider, &self.model);
+        }
+    }
+}
+
+/// Circuit breakers for every upstream the proxy has sent requests to
+pub struct CircuitBreakers {
+    config: CircuitBreakerConfig,
+    circuits: Mutex<HashMap<(ProviderType, String), Circuit>>,
+}
+
+impl CircuitBreakers {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn window(&self) -> Duration {
+        Duration::from_secs(self.config.window_secs)
+    }
+
+    fn cooldown(&self) -> Duration {
+        Duration::from_secs(self.config.open_secs)
+    }
+
+    /// Admit a request to `model` on `provider`, or `None` to fail fast
+    ///
+    /// An open circuit whose cooldown has passed moves to half-open and
+    /// admits exactly one probe; further requests are refused until that
+    /// probe's outcome is recorded or its attempt is dropped.
+    pub fn admit(&self, provider: ProviderType, model: &str) -> Option<CircuitAttempt<'_>> {
+        let attempt = |probe| {
+            Some(CircuitAttempt {
+                breakers: self,
+                provider,
+                model: model.to_string(),
+                probe,
+                finished: false,
+            })
+        };
+        if !self.config.enabled {
+            return attempt(false);
+        }
+
+        let mut circuits = self.circuits.lock().unwrap();
+        let Some(circuit) = circuits.get_mut(&(provider, model.to_string())) else {
+            return attempt(false);
+        };
+
+        match circuit.state {
+            CircuitState::Closed => attempt(false),
+            CircuitState::Open => {
+                let cooled_down = circuit
+                    .opened_at
+                    .is_none_or(|at| at.elapsed() >= self.cooldown());
+                if !cooled_down {
+                    return None;
+                }
+                tracing::info!(
+                    "Circuit for {:?} {} half-open; sending probe",
+                    provider,
+                    model
+                );
+                circuit.state = CircuitState::HalfOpen;
+                circuit.probe_in_flight = true;
+                attempt(true)
+            }
+            CircuitState::HalfOpen => {
+                if circuit.probe_in_flight {
+                    return None;
+                }
+                circuit.probe_in_flight = true;
+                attempt(true)
+            }
+        }
+    }
+
+    /// Record a request the upstream answered
+    pub fn record_success(&self, provider: ProviderType, model: &str) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let now = Instant::now();
+        let window = self.window();
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits
+            .entry((provider, model.to_string()))
+            .or_insert_with(Circuit::new);
+
+        if circuit.state != CircuitState::Closed {
+            tracing::info!("Circuit for {:?} {} closed", provider, model);
+            circuit.state = CircuitState::Closed;
+            circuit.opened_at = None;
+            circuit.probe_in_flight = false;
+            circuit.outcomes.clear();
+        }
+
+        circuit.outcomes.push_back((now, false));
+        circuit.prune(now, window);
+    }
+
+    /// Record a request that failed because of the upstream
+    pub fn record_failure(&self, provider: ProviderType, model: &str, error: &str) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let now = Instant::now();
+        let window = self.window();
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits
+            .entry((provider, model.to_string()))
+            .or_insert_with(Circuit::new);
+
+        circuit.last_error = Some(error.to_string());
+        circuit.outcomes.push_back((now, true));
+        circuit.prune(now, window);
+
+        match circuit.state {
+            CircuitState::HalfOpen => {
+                tracing::warn!("Probe to {:?} {} failed; circuit reopened", provider, model);
+                circuit.open(now);
+            }
+            CircuitState::Closed => {
+                let error_rate = circuit.error_rate();
+                if circuit.outcomes.len() >= self.config.min_requests as usize
+                    && error_rate >= self.config.failure_rate
+                {
+                    tracing::warn!(
+                        "Circuit for {:?} {} opened ({:.0}% of {} requests failed)",
+                        provider,
+                        model,
+                        error_rate * 100.0,
+                        circuit.outcomes.len()
+                    );
+                    circuit.open(now);
+                }
+            }
+            CircuitState::Open => {}
+        }
+    }
+
+    /// Free the half-open probe slot without changing the circuit
+    fn release(&self, provider: ProviderType, model: &str) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let mut circuits = self.circuits.lock().unwrap();
+        if let Some(circuit) = circuits.get_mut(&(provider, model.to_string()))
+            && circuit.state == CircuitState::HalfOpen
+        {
+            circuit.probe_in_flight = false;
+        }
+    }
+
+    /// Current state of every tracked circuit
+    pub fn statuses(&self) -> Vec<CircuitStatus> {
+        let now = Instant::now();
+        let window = self.window();
+        let cooldown = self.cooldown();
+        let mut circuits = self.circuits.lock().unwrap();
+
+        let mut statuses: Vec<CircuitStatus> = circuits
+            .iter_mut()
+            .map(|((provider, model), circuit)| {
+                circuit.prune(now, window);
+                CircuitStatus {
+                    provider: *provider,
+                    model: model.clone(),
+                    state: circuit.state,
+                    requests: circuit.outcomes.len(),
+                    error_rate: circuit.error_rate(),
+                    retry_in: match circuit.state {
+                        CircuitState::Open => circuit
+                            .opened_at
+                            .map(|at| cooldown.saturating_sub(now.duration_since(at))),
+                        _ => None,
+                    },
+                    last_error: circuit.last_error.clone(),
+                }
+            })
+            .collect();
+
+        statuses.sort_by(|a, b| {
+            format!("{:?}", a.provider)
+                .cmp(&format!("{:?}", b.provider))
+                .then_with(|| a.model.cmp(&b.model))
+        });
+        statuses
+    }
+
+    /// State of the circuit for `model` on `provider`
+    pub fn state(&self, provider: ProviderType, model: &str) -> CircuitState {
+        self.circuits
+            .lock()
+            .unwrap()
+            .get(&(provider, model.to_string()))
+            .map(|circuit| circuit.state)
+            .unwrap_or(CircuitState::Closed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breakers(open_secs: u64) -> CircuitBreakers {
+        CircuitBreakers::new(CircuitBreakerConfig {
+            enabled: true,
+            window_secs: 60,
+            min_requests: 4,
+            failure_rate: 0.5,
+            open_secs,
+        })
+    }
+
+    #[test]
+    fn test_opens_after_error_rate_exceeded() {
+        let breakers = breakers(30);
+        let provider = ProviderType::OpenAI;
+
+        breakers.record_success(provider, "gpt-4o");
+        breakers.record_failure(provider, "gpt-4o", "503");
+        breakers.record_failure(provider, "gpt-4o", "503");
+        assert_eq!(breakers.state(provider, "gpt-4o"), CircuitState::Closed);
+
+        breakers.record_failure(provider, "gpt-4o", "503");
+        assert_eq!(breakers.state(provider, "gpt-4o"), CircuitState::Open);
+        assert!(breakers.admit(provider, "gpt-4o").is_none());
+
+        // Other models on the same provider are unaffected
+        assert!(breakers.admit(provider, "gpt-4o-mini").is_some());
+
+        let status = &breakers.statuses()[0];
+        assert_eq!(status.requests, 4);
+        assert_eq!(status.error_rate, 0.75);
+        assert!(status.retry_in.is_some());
+    }
+
+    #[test]
+    fn test_half_open_probe_closes_or_reopens() {
+        let breakers = breakers(0);
+        let provider = ProviderType::VertexAI;
+        for _ in 0..4 {
+            breakers.record_failure(provider, "gemini-2.5-pro", "429");
+        }
+        assert_eq!(
+            breakers.state(provider, "gemini-2.5-pro"),
+            CircuitState::Open
+        );
+
+        // Only one probe at a time
+        let probe = breakers.admit(provider, "gemini-2.5-pro").unwrap();
+        assert!(breakers.admit(provider, "gemini-2.5-pro").is_none());
+        probe.failure("429");
+        assert_eq!(
+            breakers.state(provider, "gemini-2.5-pro"),
+            CircuitState::Open
+        );
+
+        // A cancelled or rejected probe frees the slot without closing
+        breakers
+            .admit(provider, "gemini-2.5-pro")
+            .unwrap()
+            .release();
+        assert_eq!(
+            breakers.state(provider, "gemini-2.5-pro"),
+            CircuitState::HalfOpen
+        );
+
+        breakers
+            .admit(provider, "gemini-2.5-pro")
+            .unwrap()
+            .success();
+        assert_eq!(
+            breakers.state(provider, "gemini-2.5-pro"),
+            CircuitState::Closed
+        );
+        assert!(breakers.admit(provider, "gemini-2.5-pro").is_some());
+    }
+
+    #[test]
+    fn test_dropped_probe_frees_the_slot() {
+        let breakers = breakers(0);
+        let provider = ProviderType::OpenAI;
+
+        // Admitted while closed, so it holds no probe slot
+        let early = breakers.admit(provider, "gpt-4o").unwrap();
+        for _ in 0..4 {
+            breakers.record_failure(provider, "gpt-4o", "503");
+        }
+
+        let probe = breakers.admit(provider, "gpt-4o").unwrap();
+        drop(early);
+        assert!(breakers.admit(provider, "gpt-4o").is_none());
+
+        // The client disconnected before the probe's outcome was recorded
+        drop(probe);
+        assert_eq!(breakers.state(provider, "gpt-4o"), CircuitState::HalfOpen);
+        assert!(breakers.admit(provider, "gpt-4o").is_some());
+    }
+}
@@ -4,9 +4,7 @@
 //! endpoints (including Azure OpenAI). It supports request cancellation through
 //! a cancellation token system.
 
-use crate::models::openai::{
-    OpenAIChatCompletionRequest, OpenAIChatCompletionResponse,
-};
+use crate::models::openai::{OpenAIChatCompletionRequest, OpenAIChatCompletionResponse};
 use anyhow::Result;
 use futures::stream::Stream;
 use reqwest::Client;
@@ -3,9 +3,17 @@
 //! This module handles loading and validating configuration from TOML files.
 //! Following JPL Rule 24: All configuration is validated at startup.
 
+use crate::core::aws::AwsCredentials;
+use crate::core::cassette::CassetteMode;
+use crate::core::gcp_auth::GcpCredentials;
+use crate::core::key_pool::KeySelection;
+use crate::core::model_manager::ModelRoute;
 use crate::core::provider::ProviderType;
+use crate::core::providers::mock::MockFixture;
 use anyhow::{Context, Result};
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use std::fs;
 use std::path::Path;
 
@@ -26,16 +34,33 @@ const DEFAULT_PORT: u16 = 8082;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct OpenAIConfig {
-    pub api_key: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Several keys to spread requests across (used together with `api_key`)
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+    /// "round_robin" (default) or "least_in_flight"
+    #[serde(default)]
+    pub key_selection: Option<String>,
     #[serde(default)]
     pub base_url: Option<String>,
     #[serde(default)]
     pub azure_api_version: Option<String>,
+    /// Use the Responses API (`/responses`) instead of `/chat/completions`
+    #[serde(default)]
+    pub use_responses_api: bool,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct OpenRouterConfig {
-    pub api_key: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Several keys to spread requests across (used together with `api_key`)
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+    /// "round_robin" (default) or "least_in_flight"
+    #[serde(default)]
+    pub key_selection: Option<String>,
     #[serde(default)]
     pub base_url: Option<String>,
     #[serde(default)]
@@ -44,11 +69,78 @@ pub struct OpenRouterConfig {
     pub app_name: Option<String>,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnthropicConfig {
+    pub api_key: String,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub anthropic_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaConfig {
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Context window size (`options.num_ctx`)
+    #[serde(default)]
+    pub num_ctx: Option<u32>,
+    /// How long the model stays loaded after a request (e.g. "10m", "-1")
+    #[serde(default)]
+    pub keep_alive: Option<String>,
+    /// Additional model options passed through verbatim
+    #[serde(default)]
+    pub options: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct MockConfig {
+    /// Directory of `*.json` fixtures; without one every request is echoed
+    #[serde(default)]
+    pub fixtures_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GoogleAIConfig {
+    pub api_key: String,
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct BedrockConfig {
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Endpoint override (default: https://bedrock-runtime.{region}.amazonaws.com)
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Static credentials; fall back to the AWS_* environment variables
+    #[serde(default)]
+    pub access_key_id: Option<String>,
+    #[serde(default)]
+    pub secret_access_key: Option<String>,
+    #[serde(default)]
+    pub session_token: Option<String>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct VertexAIConfig {
     pub project_id: String,
     pub location: String,
-    pub access_token: String,
+    /// Pre-minted access token; takes precedence over `credentials_file`
+    #[serde(default)]
+    pub access_token: Option<String>,
+    /// Service-account key or application default credentials JSON
+    /// (default: GOOGLE_APPLICATION_CREDENTIALS, then the gcloud ADC file)
+    #[serde(default)]
+    pub credentials_file: Option<String>,
+    /// OAuth token endpoint override
+    #[serde(default)]
+    pub token_url: Option<String>,
+    /// Gemini safety thresholds by harm category
+    /// (e.g. HARM_CATEGORY_HARASSMENT = "BLOCK_ONLY_HIGH")
+    #[serde(default)]
+    pub safety_settings: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -56,6 +148,101 @@ pub struct ModelConfig {
     pub big_model: String,
     pub middle_model: String,
     pub small_model: String,
+    /// Per-tier provider overrides (default: the top-level `provider`)
+    #[serde(default)]
+    pub big_provider: Option<String>,
+    #[serde(default)]
+    pub middle_provider: Option<String>,
+    #[serde(default)]
+    pub small_provider: Option<String>,
+    /// Upstream models (or model name prefixes) that inline their reasoning
+    /// in `<think>...</think>` tags, which are moved into thinking blocks
+    #[serde(default)]
+    pub think_tag_models: Vec<String>,
+}
+
+/// A (provider, model) pair to retry a failed request against
+#[derive(Debug, Clone, Deserialize)]
+pub struct FallbackTarget {
+    pub provider: String,
+    pub model: String,
+}
+
+/// Ordered fallback targets per model tier
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct FallbackConfig {
+    #[serde(default)]
+    pub big: Vec<FallbackTarget>,
+    #[serde(default)]
+    pub middle: Vec<FallbackTarget>,
+    #[serde(default)]
+    pub small: Vec<FallbackTarget>,
+}
+
+/// Recording or replay of upstream exchanges
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct CassetteConfig {
+    /// "record" or "replay"; unset leaves upstream calls untouched
+    #[serde(default)]
+    pub mode: Option<String>,
+    /// Directory holding the recorded exchanges
+    #[serde(default = "default_cassette_dir")]
+    pub dir: String,
+}
+
+fn default_cassette_dir() -> String {
+    "cassettes".to_string()
+}
+
+/// Circuit breaker thresholds, shared by every (provider, model) pair
+#[derive(Debug, Clone, Deserialize)]
+pub struct CircuitBreakerConfig {
+    #[serde(default = "default_circuit_enabled")]
+    pub enabled: bool,
+    /// Rolling window over which the error rate is measured
+    #[serde(default = "default_circuit_window_secs")]
+    pub window_secs: u64,
+    /// Requests needed within the window before the circuit can open
+    #[serde(default = "default_circuit_min_requests")]
+    pub min_requests: u32,
+    /// Error rate (0.0 - 1.0) at which the circuit opens
+    #[serde(default = "default_circuit_failure_rate")]
+    pub failure_rate: f64,
+    /// How long an open circuit fails fast before probing the upstream
+    #[serde(default = "default_circuit_open_secs")]
+    pub open_secs: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_circuit_enabled(),
+            window_secs: default_circuit_window_secs(),
+            min_requests: default_circuit_min_requests(),
+            failure_rate: default_circuit_failure_rate(),
+            open_secs: default_circuit_open_secs(),
+        }
+    }
+}
+
+fn default_circuit_enabled() -> bool {
+    true
+}
+
+fn default_circuit_window_secs() -> u64 {
+    60
+}
+
+fn default_circuit_min_requests() -> u32 {
+    5
+}
+
+fn default_circuit_failure_rate() -> f64 {
+    0.5
+}
+
+fn default_circuit_open_secs() -> u64 {
+    30
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -147,42 +334,265 @@ pub struct TomlConfig {
     pub openrouter: Option<OpenRouterConfig>,
     #[serde(default)]
     pub vertexai: Option<VertexAIConfig>,
+    #[serde(default)]
+    pub anthropic: Option<AnthropicConfig>,
+    #[serde(default)]
+    pub ollama: Option<OllamaConfig>,
+    #[serde(default)]
+    pub bedrock: Option<BedrockConfig>,
+    #[serde(default)]
+    pub googleai: Option<GoogleAIConfig>,
+    #[serde(default)]
+    pub mock: Option<MockConfig>,
     pub models: ModelConfig,
     #[serde(default)]
     pub server: ServerConfig,
     #[serde(default)]
     pub request: RequestConfig,
+    #[serde(default)]
+    pub fallbacks: FallbackConfig,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+    #[serde(default)]
+    pub cassette: CassetteConfig,
 }
 
-/// Application configuration loaded from TOML files
-///
-/// All configuration values are loaded and validated at startup to ensure
-/// the application fails fast if misconfigured (JPL Rule 24).
+/// Connection settings for one upstream provider
 #[derive(Debug, Clone)]
-pub struct Config {
-    /// Provider type (OpenAI, OpenRouter, or VertexAI)
+pub struct ProviderConfig {
+    /// Provider type
     pub provider: ProviderType,
 
-    /// OpenAI API key (required for OpenAI provider)
-    pub openai_api_key: String,
+    /// Upstream API key (OpenAI, OpenRouter, Anthropic, or Google AI)
+    pub api_key: String,
+
+    /// All upstream API keys, starting with `api_key` (OpenAI and OpenRouter)
+    pub api_keys: Vec<String>,
 
-    /// Optional Anthropic API key for client validation
-    pub anthropic_api_key: Option<String>,
+    /// How requests are spread across `api_keys`
+    pub key_selection: KeySelection,
 
-    /// OpenAI/OpenRouter API base URL
-    pub openai_base_url: String,
+    /// Upstream API base URL
+    pub base_url: String,
 
     /// Azure API version (for Azure OpenAI deployments)
     pub azure_api_version: Option<String>,
 
+    /// Send OpenAI requests through the Responses API
+    pub openai_use_responses_api: bool,
+
     /// OpenRouter specific settings
     pub openrouter_site_url: Option<String>,
     pub openrouter_app_name: Option<String>,
 
+    /// Anthropic `anthropic-version` header override
+    pub anthropic_version: Option<String>,
+
+    /// Ollama specific settings
+    pub ollama_num_ctx: Option<u32>,
+    pub ollama_keep_alive: Option<String>,
+    pub ollama_options: HashMap<String, serde_json::Value>,
+
+    /// AWS Bedrock specific settings
+    pub bedrock_region: Option<String>,
+    pub bedrock_credentials: Option<AwsCredentials>,
+
     /// Vertex AI specific settings
     pub vertexai_project_id: Option<String>,
     pub vertexai_location: Option<String>,
-    pub vertexai_access_token: Option<String>,
+    pub vertexai_credentials: Option<GcpCredentials>,
+    pub vertexai_token_url: Option<String>,
+    pub vertexai_safety_settings: HashMap<String, String>,
+
+    /// Scripted replies for the mock provider
+    pub mock_fixtures: Vec<MockFixture>,
+}
+
+impl ProviderConfig {
+    /// Resolve the settings for `provider` from its TOML section
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the provider's section is required but missing, or
+    /// its credentials file cannot be loaded.
+    fn from_toml(config: &TomlConfig, provider: ProviderType) -> Result<Self> {
+        let mut resolved = ProviderConfig {
+            provider,
+            api_key: String::new(),
+            api_keys: Vec::new(),
+            key_selection: KeySelection::default(),
+            base_url: String::new(),
+            azure_api_version: None,
+            openai_use_responses_api: false,
+            openrouter_site_url: None,
+            openrouter_app_name: None,
+            anthropic_version: None,
+            ollama_num_ctx: None,
+            ollama_keep_alive: None,
+            ollama_options: HashMap::new(),
+            bedrock_region: None,
+            bedrock_credentials: None,
+            vertexai_project_id: None,
+            vertexai_location: None,
+            vertexai_credentials: None,
+            vertexai_token_url: None,
+            vertexai_safety_settings: HashMap::new(),
+            mock_fixtures: Vec::new(),
+        };
+
+        match provider {
+            ProviderType::OpenAI => {
+                let openai_config = config
+                    .openai
+                    .clone()
+                    .context("OpenAI configuration missing for OpenAI provider")?;
+                resolved.api_keys = key_list(openai_config.api_key, openai_config.api_keys)
+                    .context("OpenAI configuration needs api_key or api_keys")?;
+                resolved.api_key = resolved.api_keys[0].clone();
+                resolved.key_selection = parse_key_selection(openai_config.key_selection)?;
+                resolved.base_url = openai_config
+                    .base_url
+                    .unwrap_or_else(|| "https://api.openai.com/v1".to_string());
+                resolved.azure_api_version = openai_config.azure_api_version;
+                resolved.openai_use_responses_api = openai_config.use_responses_api;
+            }
+            ProviderType::OpenRouter => {
+                let openrouter_config = config
+                    .openrouter
+                    .clone()
+                    .context("OpenRouter configuration missing for OpenRouter provider")?;
+                resolved.api_keys = key_list(openrouter_config.api_key, openrouter_config.api_keys)
+                    .context("OpenRouter configuration needs api_key or api_keys")?;
+                resolved.api_key = resolved.api_keys[0].clone();
+                resolved.key_selection = parse_key_selection(openrouter_config.key_selection)?;
+                resolved.base_url = openrouter_config
+                    .base_url
+                    .unwrap_or_else(|| "https://openrouter.ai/api/v1".to_string());
+                resolved.openrouter_site_url = openrouter_config.site_url;
+                resolved.openrouter_app_name = openrouter_config.app_name;
+            }
+            ProviderType::VertexAI => {
+                let vertexai_config = config
+                    .vertexai
+                    .clone()
+                    .context("Vertex AI configuration missing for Vertex AI provider")?;
+                resolved.vertexai_credentials = match vertexai_config.access_token {
+                    Some(token) => Some(GcpCredentials::StaticToken(token)),
+                    None => vertexai_config
+                        .credentials_file
+                        .or_else(GcpCredentials::default_credentials_path)
+                        .map(GcpCredentials::from_file)
+                        .transpose()?,
+                };
+                resolved.vertexai_project_id = Some(vertexai_config.project_id);
+                resolved.vertexai_location = Some(vertexai_config.location);
+                resolved.vertexai_token_url = vertexai_config.token_url;
+                resolved.vertexai_safety_settings = vertexai_config.safety_settings;
+            }
+            ProviderType::Anthropic => {
+                let anthropic_config = config
+                    .anthropic
+                    .clone()
+                    .context("Anthropic configuration missing for Anthropic provider")?;
+                resolved.api_key = anthropic_config.api_key;
+                resolved.base_url = anthropic_config.base_url.unwrap_or_else(|| {
+                    crate::core::providers::anthropic::DEFAULT_BASE_URL.to_string()
+                });
+                resolved.anthropic_version = anthropic_config.anthropic_version;
+            }
+            ProviderType::Ollama => {
+                // Local server, no API key required
+                let ollama_config = config.ollama.clone().unwrap_or(OllamaConfig {
+                    base_url: None,
+                    num_ctx: None,
+                    keep_alive: None,
+                    options: HashMap::new(),
+                });
+                resolved.base_url = ollama_config.base_url.unwrap_or_else(|| {
+                    crate::core::providers::ollama::DEFAULT_BASE_URL.to_string()
+                });
+                resolved.ollama_num_ctx = ollama_config.num_ctx;
+                resolved.ollama_keep_alive = ollama_config.keep_alive;
+                resolved.ollama_options = ollama_config.options;
+            }
+            ProviderType::Bedrock => {
+                // Signed with AWS credentials
+                let bedrock_config = config.bedrock.clone().unwrap_or_default();
+                let region = bedrock_config
+                    .region
+                    .or_else(|| std::env::var("AWS_REGION").ok())
+                    .unwrap_or_else(|| crate::core::providers::bedrock::DEFAULT_REGION.to_string());
+                resolved.base_url = bedrock_config.base_url.unwrap_or_else(|| {
+                    crate::core::providers::BedrockProvider::default_base_url(&region)
+                });
+                resolved.bedrock_credentials = AwsCredentials::resolve(
+                    bedrock_config.access_key_id,
+                    bedrock_config.secret_access_key,
+                    bedrock_config.session_token,
+                );
+                resolved.bedrock_region = Some(region);
+            }
+            ProviderType::GoogleAI => {
+                let googleai_config = config
+                    .googleai
+                    .clone()
+                    .context("Google AI configuration missing for Google AI provider")?;
+                resolved.api_key = googleai_config.api_key;
+                resolved.base_url = googleai_config.base_url.unwrap_or_else(|| {
+                    crate::core::providers::googleai::DEFAULT_BASE_URL.to_string()
+                });
+            }
+            ProviderType::Mock => {
+                // Offline, answers from fixtures
+                let mock_config = config.mock.clone().unwrap_or_default();
+                resolved.mock_fixtures = mock_config
+                    .fixtures_dir
+                    .map(MockFixture::load_dir)
+                    .transpose()?
+                    .unwrap_or_default();
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    /// Validate API key format based on provider
+    ///
+    /// For OpenAI: checks that the API key starts with 'sk-' prefix
+    /// For other providers: checks that key is non-empty
+    pub fn validate_api_key(&self) -> bool {
+        match self.provider {
+            ProviderType::OpenAI => self
+                .api_keys
+                .iter()
+                .all(|key| !key.is_empty() && key.starts_with("sk-")),
+            ProviderType::OpenRouter | ProviderType::Anthropic | ProviderType::GoogleAI => {
+                !self.api_key.is_empty()
+            }
+            ProviderType::VertexAI => {
+                self.vertexai_credentials.is_some() && self.vertexai_project_id.is_some()
+            }
+            ProviderType::Ollama => true, // Local server, no API key required
+            ProviderType::Mock => true,   // Offline, no API key required
+            ProviderType::Bedrock => self.bedrock_credentials.is_some(),
+        }
+    }
+}
+
+/// Application configuration loaded from TOML files
+///
+/// All configuration values are loaded and validated at startup to ensure
+/// the application fails fast if misconfigured (JPL Rule 24).
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Default provider type, used for tiers without their own provider
+    pub provider: ProviderType,
+
+    /// Settings for every provider in use, keyed by type
+    pub providers: HashMap<ProviderType, ProviderConfig>,
+
+    /// Optional Anthropic API key for client validation
+    pub anthropic_api_key: Option<String>,
 
     /// Server host address
     pub host: String,
@@ -222,6 +632,79 @@ pub struct Config {
 
     /// Model for haiku requests
     pub small_model: String,
+
+    /// Provider serving opus requests
+    pub big_provider: ProviderType,
+
+    /// Provider serving sonnet requests
+    pub middle_provider: ProviderType,
+
+    /// Provider serving haiku requests
+    pub small_provider: ProviderType,
+
+    /// Upstream model prefixes whose `<think>` tags become thinking blocks
+    pub think_tag_models: Vec<String>,
+
+    /// Targets tried in order when the opus provider fails
+    pub big_fallbacks: Vec<ModelRoute>,
+
+    /// Targets tried in order when the sonnet provider fails
+    pub middle_fallbacks: Vec<ModelRoute>,
+
+    /// Targets tried in order when the haiku provider fails
+    pub small_fallbacks: Vec<ModelRoute>,
+
+    /// Circuit breaker thresholds
+    pub circuit_breaker: CircuitBreakerConfig,
+
+    /// Record or replay upstream exchanges
+    pub cassette_mode: Option<CassetteMode>,
+
+    /// Directory holding recorded exchanges
+    pub cassette_dir: String,
+}
+
+/// Combine `api_key` and `api_keys` into one list, or `None` if both are empty
+fn key_list(api_key: Option<String>, api_keys: Vec<String>) -> Option<Vec<String>> {
+    let keys: Vec<String> = api_key.into_iter().chain(api_keys).collect();
+    (!keys.is_empty()).then_some(keys)
+}
+
+/// Parse a key selection strategy from the configuration
+fn parse_key_selection(name: Option<String>) -> Result<KeySelection> {
+    match name {
+        Some(name) => KeySelection::from_str(&name).with_context(|| {
+            format!(
+                "Invalid key_selection value '{}'. Must be one of: round_robin, least_in_flight",
+                name
+            )
+        }),
+        None => Ok(KeySelection::default()),
+    }
+}
+
+/// Parse a cassette mode from the configuration
+fn parse_cassette_mode(name: Option<String>) -> Result<Option<CassetteMode>> {
+    name.map(|name| {
+        CassetteMode::from_str(&name).with_context(|| {
+            format!(
+                "Invalid cassette mode '{}'. Must be one of: record, replay",
+                name
+            )
+        })
+    })
+    .transpose()
+}
+
+/// Parse a provider name from the configuration
+fn parse_provider(name: &str) -> Result<ProviderType> {
+    ProviderType::from_str(name).with_context(|| {
+        format!(
+            "Invalid provider value '{}'. Must be one of: openai, openrouter, vertexai, \
+             anthropic, ollama, bedrock, googleai, mock",
+            name
+        )
+    })
 }
 
 impl Config {
@@ -239,74 +722,51 @@ impl Config {
         let config: TomlConfig =
             toml::from_str(&content).context("Failed to parse TOML configuration")?;
 
-        let provider = ProviderType::from_str(&config.provider)
-            .context("Invalid provider value. Must be one of: openai, openrouter, vertexai")?;
+        let provider = parse_provider(&config.provider)?;
 
-        let (
-            openai_api_key,
-            openai_base_url,
-            azure_api_version,
-            openrouter_site_url,
-            openrouter_app_name,
-        ) = match provider {
-            ProviderType::OpenAI => {
-                let openai_config = config
-                    .openai
-                    .context("OpenAI configuration missing for OpenAI provider")?;
-                let azure_ver = openai_config.azure_api_version.clone();
-                (
-                    openai_config.api_key,
-                    openai_config
-                        .base_url
-                        .unwrap_or_else(|| "https://api.openai.com/v1".to_string()),
-                    azure_ver,
-                    None,
-                    None,
-                )
-            }
-            ProviderType::OpenRouter => {
-                let openrouter_config = config
-                    .openrouter
-                    .context("OpenRouter configuration missing for OpenRouter provider")?;
-                (
-                    openrouter_config.api_key,
-                    openrouter_config
-                        .base_url
-                        .unwrap_or_else(|| "https://openrouter.ai/api/v1".to_string()),
-                    None,
-                    openrouter_config.site_url,
-                    openrouter_config.app_name,
-                )
-            }
-            ProviderType::VertexAI => {
-                (String::new(), String::new(), None, None, None) // Not used for Vertex AI
-            }
+        // Tiers without an explicit provider use the default one
+        let tier_provider = |name: &Option<String>| -> Result<ProviderType> {
+            name.as_deref().map(parse_provider).unwrap_or(Ok(provider))
         };
-
-        let vertexai_config = if provider == ProviderType::VertexAI {
-            let config = config
-                .vertexai
-                .context("Vertex AI configuration missing for Vertex AI provider")?;
-            (
-                Some(config.project_id),
-                Some(config.location),
-                Some(config.access_token),
-            )
-        } else {
-            (None, None, None)
+        let big_provider = tier_provider(&config.models.big_provider)?;
+        let middle_provider = tier_provider(&config.models.middle_provider)?;
+        let small_provider = tier_provider(&config.models.small_provider)?;
+
+        let fallback_routes = |targets: &[FallbackTarget]| -> Result<Vec<ModelRoute>> {
+            targets
+                .iter()
+                .map(|target| {
+                    Ok(ModelRoute {
+                        provider: parse_provider(&target.provider)?,
+                        model: target.model.clone(),
+                    })
+                })
+                .collect()
         };
+        let big_fallbacks = fallback_routes(&config.fallbacks.big)?;
+        let middle_fallbacks = fallback_routes(&config.fallbacks.middle)?;
+        let small_fallbacks = fallback_routes(&config.fallbacks.small)?;
+
+        let fallback_providers = big_fallbacks
+            .iter()
+            .chain(&middle_fallbacks)
+            .chain(&small_fallbacks)
+            .map(|route| route.provider);
+
+        let mut providers = HashMap::new();
+        for provider_type in [provider, big_provider, middle_provider, small_provider]
+            .into_iter()
+            .chain(fallback_providers)
+        {
+            if let Entry::Vacant(entry) = providers.entry(provider_type) {
+                entry.insert(ProviderConfig::from_toml(&config, provider_type)?);
+            }
+        }
 
         Ok(Config {
             provider,
-            openai_api_key,
+            providers,
             anthropic_api_key: config.anthropic_api_key,
-            openai_base_url,
-            azure_api_version,
-            openrouter_site_url,
-            openrouter_app_name,
-            vertexai_project_id: vertexai_config.0,
-            vertexai_location: vertexai_config.1,
-            vertexai_access_token: vertexai_config.2,
             host: config.server.host,
             port: config.server.port,
             log_level: config.server.log_level,
@@ -320,9 +780,24 @@ impl Config {
             big_model: config.models.big_model,
             middle_model: config.models.middle_model,
             small_model: config.models.small_model,
+            big_provider,
+            middle_provider,
+            small_provider,
+            think_tag_models: config.models.think_tag_models,
+            big_fallbacks,
+            middle_fallbacks,
+            small_fallbacks,
+            circuit_breaker: config.circuit_breaker,
+            cassette_mode: parse_cassette_mode(config.cassette.mode)?,
+            cassette_dir: config.cassette.dir,
         })
     }
 
+    /// Settings for the default provider
+    pub fn default_provider_config(&self) -> &ProviderConfig {
+        &self.providers[&self.provider]
+    }
+
     /// Load configuration from environment and config file
     ///
     /// Looks for config.toml in current directory by default
@@ -332,20 +807,11 @@ impl Config {
         Self::from_file(config_path)
     }
 
-    /// Validate API key format based on provider
-    ///
-    /// For OpenAI: checks that the API key starts with 'sk-' prefix
-    /// For other providers: checks that key is non-empty
+    /// Validate the API keys of every configured provider
     pub fn validate_api_key(&self) -> bool {
-        match self.provider {
-            ProviderType::OpenAI => {
-                !self.openai_api_key.is_empty() && self.openai_api_key.starts_with("sk-")
-            }
-            ProviderType::OpenRouter => !self.openai_api_key.is_empty(),
-            ProviderType::VertexAI => {
-                self.vertexai_access_token.is_some() && self.vertexai_project_id.is_some()
-            }
-        }
+        self.providers
+            .values()
+            .all(|provider_config| provider_config.validate_api_key())
     }
 
     /// Validate client's Anthropic API key
@@ -408,8 +874,9 @@ mod tests {
         let file = create_test_config();
         let config = Config::from_file(file.path()).unwrap();
         assert_eq!(config.provider, ProviderType::OpenAI);
-        assert_eq!(config.openai_api_key, "sk-test123");
+        assert_eq!(config.default_provider_config().api_key, "sk-test123");
         assert_eq!(config.anthropic_api_key, Some("test-key".to_string()));
+        assert!(!config.default_provider_config().openai_use_responses_api);
     }
 
     #[test]
@@ -426,4 +893,304 @@ mod tests {
         assert!(config.validate_client_api_key("test-key"));
         assert!(!config.validate_client_api_key("wrong-key"));
     }
+
+    #[test]
+    fn test_load_anthropic_config() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"
+            provider = "anthropic"
+
+            [anthropic]
+            api_key = "sk-ant-upstream"
+
+            [models]
+            big_model = "claude-opus-4-1"
+            middle_model = "claude-sonnet-4-5"
+            small_model = "claude-3-5-haiku-latest"
+        "#
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let config = Config::from_file(file.path()).unwrap();
+        assert_eq!(config.provider, ProviderType::Anthropic);
+        assert_eq!(config.default_provider_config().api_key, "sk-ant-upstream");
+        assert_eq!(
+            config.default_provider_config().base_url,
+            "https://api.anthropic.com/v1"
+        );
+        assert!(config.validate_api_key());
+    }
+
+    #[test]
+    fn test_load_ollama_config() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"
+            provider = "ollama"
+
+            [ollama]
+            num_ctx = 32768
+            keep_alive = "30m"
+            options = {{ num_gpu = 99 }}
+
+            [models]
+            big_model = "qwen2.5-coder:32b"
+            middle_model = "qwen2.5-coder:32b"
+            small_model = "llama3.1:8b"
+        "#
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let config = Config::from_file(file.path()).unwrap();
+        assert_eq!(config.provider, ProviderType::Ollama);
+        assert_eq!(
+            config.default_provider_config().base_url,
+            "http://localhost:11434"
+        );
+        assert_eq!(config.default_provider_config().ollama_num_ctx, Some(32768));
+        assert_eq!(
+            config
+                .default_provider_config()
+                .ollama_keep_alive
+                .as_deref(),
+            Some("30m")
+        );
+        assert_eq!(
+            config.default_provider_config().ollama_options["num_gpu"],
+            99
+        );
+        assert!(config.validate_api_key());
+    }
+
+    #[test]
+    fn test_load_bedrock_config() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"
+            provider = "bedrock"
+
+            [bedrock]
+            region = "eu-west-1"
+            access_key_id = "AKIDEXAMPLE"
+            secret_access_key = "secret"
+
+            [models]
+            big_model = "anthropic.claude-3-opus-20240229-v1:0"
+            middle_model = "anthropic.claude-3-5-sonnet-20240620-v1:0"
+            small_model = "anthropic.claude-3-haiku-20240307-v1:0"
+        "#
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let config = Config::from_file(file.path()).unwrap();
+        assert_eq!(config.provider, ProviderType::Bedrock);
+        assert_eq!(
+            config.default_provider_config().base_url,
+            "https://bedrock-runtime.eu-west-1.amazonaws.com"
+        );
+        assert_eq!(
+            config.default_provider_config().bedrock_region.as_deref(),
+            Some("eu-west-1")
+        );
+        assert!(config.validate_api_key());
+    }
+
+    #[test]
+    fn test_load_googleai_config() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"
+            provider = "gemini"
+
+            [googleai]
+            api_key = "AIza-test"
+
+            [models]
+            big_model = "gemini-2.5-pro"
+            middle_model = "gemini-2.5-pro"
+            small_model = "gemini-2.5-flash"
+        "#
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let config = Config::from_file(file.path()).unwrap();
+        assert_eq!(config.provider, ProviderType::GoogleAI);
+        assert_eq!(config.default_provider_config().api_key, "AIza-test");
+        assert_eq!(
+            config.default_provider_config().base_url,
+            "https://generativelanguage.googleapis.com/v1beta"
+        );
+        assert!(config.validate_api_key());
+    }
+
+    #[test]
+    fn test_load_mock_config() {
+        let config = Config::from_file("config/mock.toml.example").unwrap();
+        assert_eq!(config.provider, ProviderType::Mock);
+        assert_eq!(config.default_provider_config().mock_fixtures.len(), 3);
+        assert!(config.validate_api_key());
+
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"
+            provider = "mock"
+
+            [mock]
+            fixtures_dir = "config/no-such-fixtures"
+
+            [models]
+            big_model = "mock-model"
+            middle_model = "mock-model"
+            small_model = "mock-model"
+        "#
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        assert!(Config::from_file(file.path()).is_err());
+    }
+
+    #[test]
+    fn test_load_cassette_config() {
+        let config = Config::from_file("config/mock.toml.example").unwrap();
+        assert_eq!(config.cassette_mode, None);
+
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"
+            provider = "mock"
+
+            [cassette]
+            mode = "replay"
+            dir = "tests/cassettes"
+
+            [models]
+            big_model = "mock-model"
+            middle_model = "mock-model"
+            small_model = "mock-model"
+        "#
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let config = Config::from_file(file.path()).unwrap();
+        assert_eq!(config.cassette_mode, Some(CassetteMode::Replay));
+        assert_eq!(config.cassette_dir, "tests/cassettes");
+    }
+
+    #[test]
+    fn test_load_per_tier_providers() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"
+            provider = "openrouter"
+
+            [openrouter]
+            api_key = "sk-or-test"
+
+            [models]
+            big_model = "anthropic/claude-opus-4"
+            middle_model = "anthropic/claude-sonnet-4"
+            small_model = "llama3.1:8b"
+            small_provider = "ollama"
+        "#
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let config = Config::from_file(file.path()).unwrap();
+        assert_eq!(config.big_provider, ProviderType::OpenRouter);
+        assert_eq!(config.middle_provider, ProviderType::OpenRouter);
+        assert_eq!(config.small_provider, ProviderType::Ollama);
+        assert_eq!(config.providers.len(), 2);
+        assert_eq!(
+            config.providers[&ProviderType::Ollama].base_url,
+            "http://localhost:11434"
+        );
+        assert!(config.validate_api_key());
+    }
+
+    #[test]
+    fn test_load_fallbacks() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"
+            provider = "anthropic"
+
+            [anthropic]
+            api_key = "sk-ant-upstream"
+
+            [openrouter]
+            api_key = "sk-or-test"
+
+            [models]
+            big_model = "claude-opus-4-1"
+            middle_model = "claude-sonnet-4-5"
+            small_model = "claude-3-5-haiku-latest"
+
+            [fallbacks]
+            middle = [
+                {{ provider = "openrouter", model = "anthropic/claude-sonnet-4.5" }},
+                {{ provider = "ollama", model = "qwen2.5-coder:32b" }},
+            ]
+        "#
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let config = Config::from_file(file.path()).unwrap();
+        assert!(config.big_fallbacks.is_empty());
+        assert_eq!(config.middle_fallbacks.len(), 2);
+        assert_eq!(
+            config.middle_fallbacks[0].provider,
+            ProviderType::OpenRouter
+        );
+        assert_eq!(config.middle_fallbacks[1].model, "qwen2.5-coder:32b");
+        assert!(config.providers.contains_key(&ProviderType::Ollama));
+        assert!(config.circuit_breaker.enabled);
+        assert_eq!(config.circuit_breaker.min_requests, 5);
+    }
+
+    #[test]
+    fn test_load_key_pool() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"
+            provider = "openai"
+
+            [openai]
+            api_key = "sk-first"
+            api_keys = ["sk-second", "sk-third"]
+            key_selection = "least_in_flight"
+
+            [models]
+            big_model = "gpt-4o"
+            middle_model = "gpt-4o"
+            small_model = "gpt-4o-mini"
+        "#
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let config = Config::from_file(file.path()).unwrap();
+        let provider_config = config.default_provider_config();
+        assert_eq!(provider_config.api_key, "sk-first");
+        assert_eq!(provider_config.api_keys.len(), 3);
+        assert_eq!(provider_config.key_selection, KeySelection::LeastInFlight);
+        assert!(config.validate_api_key());
+    }
 }
@@ -32,6 +32,9 @@ pub mod content {
 
     /// Tool result content type
     pub const TOOL_RESULT: &str = "tool_result";
+
+    /// Thinking (reasoning) content type
+    pub const THINKING: &str = "thinking";
 }
 
 /// Tool type constants
@@ -51,6 +54,9 @@ pub mod stop {
     /// Tool use stop reason
     pub const TOOL_USE: &str = "tool_use";
 
+    /// Refusal stop reason (upstream content filter)
+    pub const REFUSAL: &str = "refusal";
+
     /// Error stop reason
     pub const ERROR: &str = "error";
 }
@@ -86,4 +92,10 @@ pub mod delta {
 
     /// Input JSON delta type
     pub const INPUT_JSON: &str = "input_json_delta";
+
+    /// Thinking delta type
+    pub const THINKING: &str = "thinking_delta";
+
+    /// Thinking signature delta type
+    pub const SIGNATURE: &str = "signature_delta";
 }
@@ -0,0 +1,438 @@
+//! Google Cloud OAuth access tokens
+//!
+//! Mints access tokens for Vertex AI from a service-account key (JWT bearer
+//! grant) or an `application_default_credentials.json` refresh token, and
+//! caches them until shortly before they expire.
+
+use crate::core::provider::ProviderError;
+use anyhow::{Context, Result, bail};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Default Google OAuth token endpoint
+pub const DEFAULT_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+
+/// OAuth scope required by Vertex AI
+const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+/// Lifetime requested for service-account assertions (the maximum Google allows)
+const ASSERTION_LIFETIME_SECS: i64 = 3600;
+
+/// Refresh tokens this long before they expire
+const REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+/// Service-account key file contents
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServiceAccountKey {
+    pub client_email: String,
+    pub private_key: String,
+    #[serde(default)]
+    pub private_key_id: Option<String>,
+    #[serde(default)]
+    pub token_uri: Option<String>,
+}
+
+/// `gcloud auth application-default login` credentials
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthorizedUserCredentials {
+    pub client_id: String,
+    pub client_secret: String,
+    pub refresh_token: String,
+}
+
+/// Source of Google Cloud access tokens
+#[derive(Debug, Clone)]
+pub enum GcpCredentials {
+    /// Pre-minted access token used as-is
+    StaticToken(String),
+    ServiceAccount(ServiceAccountKey),
+    AuthorizedUser(AuthorizedUserCredentials),
+}
+
+impl GcpCredentials {
+    /// Load a service-account key or application default credentials file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read credentials file {}", path.display()))?;
+        let value: serde_json::Value =
+            serde_json::from_str(&content).context("Failed to parse credentials file")?;
+
+        match value.get("type").and_then(|t| t.as_str()) {
+            Some("service_account") => Ok(GcpCredentials::ServiceAccount(
+                serde_json::from_value(value).context("Invalid service account key")?,
+            )),
+            Some("authorized_user") => Ok(GcpCredentials::AuthorizedUser(
+                serde_json::from_value(value).context("Invalid authorized user credentials")?,
+            )),
+            Some(other) => bail!("Unsupported credentials type: {}", other),
+            None => bail!("Credentials file has no \"type\" field"),
+        }
+    }
+
+    /// Locate application default credentials
+    ///
+    /// Checks `GOOGLE_APPLICATION_CREDENTIALS`, then the gcloud default location.
+    pub fn default_credentials_path() -> Option<String> {
+        if let Ok(path) = std::env::var("GOOGLE_APPLICATION_CREDENTIALS") {
+            return Some(path);
+        }
+
+        let config_dir = std::env::var("CLOUDSDK_CONFIG").ok().or_else(|| {
+            std::env::var("HOME")
+                .ok()
+                .map(|home| format!("{}/.config/gcloud", home))
+        })?;
+        let path = format!("{}/application_default_credentials.json", config_dir);
+        Path::new(&path).exists().then_some(path)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct JwtClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+struct CachedToken {
+    access_token: String,
+    expires_at: Instant,
+}
+
+/// Caching access token provider
+pub struct GcpTokenProvider {
+    client: Client,
+    credentials: GcpCredentials,
+    token_url: Option<String>,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+impl GcpTokenProvider {
+    /// Create a new token provider
+    ///
+    /// # Arguments
+    ///
+    /// * `credentials` - Where tokens come from
+    /// * `token_url` - Optional token endpoint override
+    ///   (default: the key's `token_uri`, then https://oauth2.googleapis.com/token)
+    pub fn new(credentials: GcpCredentials, token_url: Option<String>) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            client,
+            credentials,
+            token_url,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Get a valid access token, refreshing it if it is about to expire
+    pub async fn access_token(&self) -> Result<String, ProviderError> {
+        if let GcpCredentials::StaticToken(ref token) = self.credentials {
+            return Ok(token.clone());
+        }
+
+        // Holding the lock while refreshing keeps concurrent requests from
+        // minting a token each
+        let mut cached = self.cached.lock().await;
+        if let Some(ref token) = *cached
+            && token.expires_at > Instant::now() + REFRESH_MARGIN
+        {
+            return Ok(token.access_token.clone());
+        }
+
+        let response = self.fetch_token().await?;
+        let expires_in = Duration::from_secs(response.expires_in.unwrap_or(3600));
+        *cached = Some(CachedToken {
+            access_token: response.access_token.clone(),
+            expires_at: Instant::now() + expires_in,
+        });
+
+        Ok(response.access_token)
+    }
+
+    /// Token endpoint for the configured credentials
+    fn token_url(&self) -> &str {
+        if let Some(ref url) = self.token_url {
+            return url;
+        }
+
+        match self.credentials {
+            GcpCredentials::ServiceAccount(ref key) => {
+                key.token_uri.as_deref().unwrap_or(DEFAULT_TOKEN_URL)
+            }
+            _ => DEFAULT_TOKEN_URL,
+        }
+    }
+
+    /// Build a signed JWT bearer assertion for a service account
+    fn service_account_assertion(&self, key: &ServiceAccountKey) -> Result<String, ProviderError> {
+        let now = chrono::Utc::now().timestamp();
+        let claims = JwtClaims {
+            iss: &key.client_email,
+            scope: CLOUD_PLATFORM_SCOPE,
+            aud: self.token_url(),
+            iat: now,
+            exp: now + ASSERTION_LIFETIME_SECS,
+        };
+
+        let mut header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256);
+        header.kid = key.private_key_id.clone();
+
+        let encoding_key = jsonwebtoken::EncodingKey::from_rsa_pem(key.private_key.as_bytes())
+            .map_err(|e| {
+                ProviderError::Authentication(format!("Invalid service account private key: {}", e))
+            })?;
+
+        jsonwebtoken::encode(&header, &claims, &encoding_key)
+            .map_err(|e| ProviderError::Unexpected(format!("Failed to sign JWT: {}", e)))
+    }
+
+    /// Exchange the credentials for a new access token
+    async fn fetch_token(&self) -> Result<TokenResponse, ProviderError> {
+        let form: Vec<(&str, String)> = match self.credentials {
+            GcpCredentials::ServiceAccount(ref key) => vec![
+                (
+                    "grant_type",
+                    "urn:ietf:params:oauth:grant-type:jwt-bearer".to_string(),
+                ),
+                ("assertion", self.service_account_assertion(key)?),
+            ],
+            GcpCredentials::AuthorizedUser(ref user) => vec![
+                ("grant_type", "refresh_token".to_string()),
+                ("client_id", user.client_id.clone()),
+                ("client_secret", user.client_secret.clone()),
+                ("refresh_token", user.refresh_token.clone()),
+            ],
+            GcpCredentials::StaticToken(_) => unreachable!("static tokens are never fetched"),
+        };
+
+        let response = self
+            .client
+            .post(self.token_url())
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| ProviderError::Connection(format!("Token request failed: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            let message = format!(
+                "Failed to obtain Google Cloud access token ({}): {}",
+                status, error_text
+            );
+            // Only client errors mean the credentials themselves are bad
+            return Err(if status.as_u16() == 429 {
+                ProviderError::RateLimit(message)
+            } else if status.is_server_error() {
+                ProviderError::ApiError {
+                    status: status.as_u16(),
+                    message,
+                }
+            } else {
+                ProviderError::Authentication(message)
+            });
+        }
+
+        response.json().await.map_err(|e| {
+            ProviderError::Unexpected(format!("Failed to parse token response: {}", e))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Form, Json, Router, extract::State, routing::post};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Generate a throwaway RSA key so no private key lives in the tree
+    fn test_private_key() -> String {
+        use rsa::pkcs8::{EncodePrivateKey, LineEnding};
+
+        rsa::RsaPrivateKey::new(&mut rsa::rand_core::OsRng, 2048)
+            .unwrap()
+            .to_pkcs8_pem(LineEnding::LF)
+            .unwrap()
+            .to_string()
+    }
+
+    /// Form bodies received by the local token endpoint
+    type RecordedForms = Arc<std::sync::Mutex<Vec<HashMap<String, String>>>>;
+
+    /// Start a local token endpoint returning `expires_in` and counting calls
+    async fn spawn_token_server(expires_in: u64) -> (String, Arc<AtomicUsize>, RecordedForms) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let forms: RecordedForms = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let state = (calls.clone(), forms.clone());
+
+        let app = Router::new()
+            .route(
+                "/token",
+                post(
+                    move |State((calls, forms)): State<(Arc<AtomicUsize>, RecordedForms)>,
+                          Form(form): Form<HashMap<String, String>>| async move {
+                        let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                        forms.lock().unwrap().push(form);
+                        Json(serde_json::json!({
+                            "access_token": format!("token-{}", n),
+                            "expires_in": expires_in,
+                            "token_type": "Bearer"
+                        }))
+                    },
+                ),
+            )
+            .with_state(state);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        (format!("http://{}/token", addr), calls, forms)
+    }
+
+    #[tokio::test]
+    async fn test_authorized_user_token_is_cached() {
+        let (url, calls, forms) = spawn_token_server(3600).await;
+        let provider = GcpTokenProvider::new(
+            GcpCredentials::AuthorizedUser(AuthorizedUserCredentials {
+                client_id: "id".to_string(),
+                client_secret: "secret".to_string(),
+                refresh_token: "refresh".to_string(),
+            }),
+            Some(url),
+        );
+
+        assert_eq!(provider.access_token().await.unwrap(), "token-1");
+        assert_eq!(provider.access_token().await.unwrap(), "token-1");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(forms.lock().unwrap()[0]["grant_type"], "refresh_token");
+        assert_eq!(forms.lock().unwrap()[0]["refresh_token"], "refresh");
+    }
+
+    #[tokio::test]
+    async fn test_service_account_token_refreshes_before_expiry() {
+        // Tokens inside the refresh margin are minted again on every call
+        let (url, calls, forms) = spawn_token_server(60).await;
+        let provider = GcpTokenProvider::new(
+            GcpCredentials::ServiceAccount(ServiceAccountKey {
+                client_email: "proxy@project.iam.gserviceaccount.com".to_string(),
+                private_key: test_private_key(),
+                private_key_id: Some("key-1".to_string()),
+                token_uri: None,
+            }),
+            Some(url.clone()),
+        );
+
+        assert_eq!(provider.access_token().await.unwrap(), "token-1");
+        assert_eq!(provider.access_token().await.unwrap(), "token-2");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let form = forms.lock().unwrap()[0].clone();
+        assert_eq!(
+            form["grant_type"],
+            "urn:ietf:params:oauth:grant-type:jwt-bearer"
+        );
+        let header = jsonwebtoken::decode_header(&form["assertion"]).unwrap();
+        assert_eq!(header.kid.as_deref(), Some("key-1"));
+
+        use base64::Engine;
+        let payload = form["assertion"].split('.').nth(1).unwrap();
+        let claims: serde_json::Value = serde_json::from_slice(
+            &base64::engine::general_purpose::URL_SAFE_NO_PAD
+                .decode(payload)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(claims["iss"], "proxy@project.iam.gserviceaccount.com");
+        assert_eq!(claims["aud"], url);
+        assert_eq!(claims["scope"], CLOUD_PLATFORM_SCOPE);
+    }
+
+    #[tokio::test]
+    async fn test_token_endpoint_failures_are_classified() {
+        let app = Router::new()
+            .route(
+                "/unavailable",
+                post(|| async { (axum::http::StatusCode::SERVICE_UNAVAILABLE, "try later") }),
+            )
+            .route(
+                "/denied",
+                post(|| async { (axum::http::StatusCode::UNAUTHORIZED, "invalid_grant") }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        // Nothing listens on a port whose listener was already dropped
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed_addr = closed.local_addr().unwrap();
+        drop(closed);
+
+        let fetch = |url: String| async move {
+            GcpTokenProvider::new(
+                GcpCredentials::AuthorizedUser(AuthorizedUserCredentials {
+                    client_id: "id".to_string(),
+                    client_secret: "secret".to_string(),
+                    refresh_token: "refresh".to_string(),
+                }),
+                Some(url),
+            )
+            .access_token()
+            .await
+            .unwrap_err()
+        };
+
+        let err = fetch(format!("http://{}/unavailable", addr)).await;
+        assert!(matches!(err, ProviderError::ApiError { status: 503, .. }));
+        assert!(err.should_fallback());
+
+        let err = fetch(format!("http://{}/denied", addr)).await;
+        assert!(matches!(err, ProviderError::Authentication(_)));
+
+        let err = fetch(format!("http://{}/token", closed_addr)).await;
+        assert!(matches!(err, ProviderError::Connection(_)));
+    }
+
+    #[test]
+    fn test_credentials_from_file() {
+        use std::io::Write;
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"{{"type": "authorized_user", "client_id": "id", "client_secret": "s", "refresh_token": "r"}}"#
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let credentials = GcpCredentials::from_file(file.path()).unwrap();
+        assert!(matches!(credentials, GcpCredentials::AuthorizedUser(_)));
+    }
+}
@@ -0,0 +1,238 @@
+//! Upstream API key pool
+//!
+//! Spreads requests across several API keys for the same upstream and takes
+//! keys out of rotation for a while after they are rate limited (429) or
+//! rejected (401).
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// How long a rate-limited key is ejected when no `Retry-After` is sent
+const DEFAULT_RATE_LIMIT_EJECTION: Duration = Duration::from_secs(60);
+
+/// How long a rejected key is ejected
+const AUTH_FAILURE_EJECTION: Duration = Duration::from_secs(300);
+
+/// Strategy for picking the next key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeySelection {
+    /// Cycle through the keys in order
+    #[default]
+    RoundRobin,
+    /// Pick the key with the fewest requests in progress
+    LeastInFlight,
+}
+
+impl KeySelection {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "round_robin" | "round-robin" | "roundrobin" => Some(KeySelection::RoundRobin),
+            "least_in_flight" | "least-in-flight" | "least_inflight" => {
+                Some(KeySelection::LeastInFlight)
+            }
+            _ => None,
+        }
+    }
+}
+
+struct PooledKey {
+    key: String,
+    in_flight: AtomicUsize,
+    ejected_until: Mutex<Option<Instant>>,
+}
+
+impl PooledKey {
+    fn ejected_until(&self, now: Instant) -> Option<Instant> {
+        self.ejected_until
+            .lock()
+            .unwrap()
+            .filter(|until| *until > now)
+    }
+}
+
+/// A set of interchangeable API keys for one upstream
+pub struct KeyPool {
+    keys: Vec<PooledKey>,
+    selection: KeySelection,
+    next: AtomicUsize,
+}
+
+impl KeyPool {
+    /// Create a key pool
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - API keys; must not be empty
+    /// * `selection` - How the next key is chosen
+    pub fn new(keys: Vec<String>, selection: KeySelection) -> Arc<Self> {
+        assert!(!keys.is_empty(), "Key pool needs at least one key");
+
+        Arc::new(Self {
+            keys: keys
+                .into_iter()
+                .map(|key| PooledKey {
+                    key,
+                    in_flight: AtomicUsize::new(0),
+                    ejected_until: Mutex::new(None),
+                })
+                .collect(),
+            selection,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Take a key for one request
+    ///
+    /// Ejected keys are skipped; if every key is ejected, the one that
+    /// returns to rotation soonest is used. The key counts as in flight until
+    /// the lease is dropped.
+    pub fn acquire(self: &Arc<Self>) -> KeyLease {
+        let now = Instant::now();
+        let count = self.keys.len();
+        let available: Vec<usize> = (0..count)
+            .filter(|&i| self.keys[i].ejected_until(now).is_none())
+            .collect();
+
+        let index = if available.is_empty() {
+            (0..count)
+                .min_by_key(|&i| self.keys[i].ejected_until(now))
+                .unwrap_or(0)
+        } else {
+            match self.selection {
+                KeySelection::RoundRobin => {
+                    let start = self.next.fetch_add(1, Ordering::Relaxed);
+                    available[start % available.len()]
+                }
+                KeySelection::LeastInFlight => *available
+                    .iter()
+                    .min_by_key(|&&i| self.keys[i].in_flight.load(Ordering::Relaxed))
+                    .unwrap(),
+            }
+        };
+
+        self.keys[index].in_flight.fetch_add(1, Ordering::Relaxed);
+        KeyLease {
+            pool: self.clone(),
+            index,
+        }
+    }
+
+    /// Number of keys in the pool
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+/// Parse a `Retry-After` header given in seconds
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+/// A key checked out of a pool for one request
+pub struct KeyLease {
+    pool: Arc<KeyPool>,
+    index: usize,
+}
+
+impl KeyLease {
+    /// The API key to send
+    pub fn key(&self) -> &str {
+        &self.pool.keys[self.index].key
+    }
+
+    /// Take the key out of rotation for `duration`
+    pub fn eject(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+        let mut ejected_until = self.pool.keys[self.index].ejected_until.lock().unwrap();
+        if ejected_until.is_none_or(|current| current < until) {
+            *ejected_until = Some(until);
+        }
+    }
+
+    /// Record the upstream response status, ejecting the key on 429 or 401
+    pub fn report(&self, status: u16, headers: &HeaderMap) {
+        match status {
+            429 => {
+                let duration = parse_retry_after(headers).unwrap_or(DEFAULT_RATE_LIMIT_EJECTION);
+                if self.pool.len() > 1 {
+                    tracing::warn!("API key rate limited; ejecting it for {:?}", duration);
+                }
+                self.eject(duration);
+            }
+            401 => {
+                if self.pool.len() > 1 {
+                    tracing::warn!(
+                        "API key rejected; ejecting it for {:?}",
+                        AUTH_FAILURE_EJECTION
+                    );
+                }
+                self.eject(AUTH_FAILURE_EJECTION);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Drop for KeyLease {
+    fn drop(&mut self) {
+        self.pool.keys[self.index]
+            .in_flight
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> Vec<String> {
+        vec!["a".to_string(), "b".to_string(), "c".to_string()]
+    }
+
+    #[test]
+    fn test_round_robin_skips_ejected_keys() {
+        let pool = KeyPool::new(keys(), KeySelection::RoundRobin);
+        let picked: Vec<String> = (0..3).map(|_| pool.acquire().key().to_string()).collect();
+        assert_eq!(picked, ["a", "b", "c"]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "120".parse().unwrap());
+        let lease = pool.acquire();
+        assert_eq!(lease.key(), "a");
+        lease.report(429, &headers);
+        drop(lease);
+
+        for _ in 0..4 {
+            assert_ne!(pool.acquire().key(), "a");
+        }
+    }
+
+    #[test]
+    fn test_least_in_flight_and_all_ejected() {
+        let pool = KeyPool::new(keys(), KeySelection::LeastInFlight);
+        let first = pool.acquire();
+        let second = pool.acquire();
+        assert_ne!(first.key(), second.key());
+        let third = pool.acquire();
+        assert_eq!(third.key(), "c");
+        drop(second);
+        assert_eq!(pool.acquire().key(), "b");
+
+        // With every key ejected, the one back soonest is still used
+        first.eject(Duration::from_secs(30));
+        third.eject(Duration::from_secs(60));
+        pool.acquire().report(401, &HeaderMap::new());
+        assert_eq!(pool.acquire().key(), "a");
+    }
+}
@@ -3,10 +3,18 @@
 //! This module contains configuration, constants, logging, and client
 //! functionality.
 
+pub mod aws;
+pub mod cancellation;
+pub mod cassette;
+pub mod circuit_breaker;
+pub mod client;
 pub mod config;
 pub mod constants;
+pub mod gcp_auth;
+pub mod key_pool;
 pub mod logging;
 pub mod model_manager;
-pub mod client;
 pub mod provider;
 pub mod providers;
+pub mod registry;
+pub mod retry;
@@ -1,9 +1,18 @@
 //! Model mapping between Claude and OpenAI model names
 //!
 //! This module handles the conversion of Claude model names to their
-//! corresponding OpenAI model equivalents based on configuration.
+//! corresponding OpenAI model equivalents based on configuration, and picks
+//! the provider that serves each model tier.
 
 use crate::core::config::Config;
+use crate::core::provider::ProviderType;
+
+/// Where a Claude model is sent: the provider and its upstream model name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelRoute {
+    pub provider: ProviderType,
+    pub model: String,
+}
 
 /// Manages model name mapping from Claude to OpenAI
 pub struct ModelManager {
@@ -16,23 +25,26 @@ impl ModelManager {
         Self { config }
     }
 
-    /// Map Claude model names to OpenAI model names
+    /// Resolve a Claude model name to a provider and upstream model
     ///
-    /// Maps Claude model names (opus, sonnet, haiku) to configured OpenAI
-    /// models. If the model name is already an OpenAI model or other supported
-    /// model (ARK, Doubao, DeepSeek), returns it as-is.
+    /// Claude model names (opus, sonnet, haiku) go to the configured model
+    /// and provider for their tier. OpenAI and other supported models (ARK,
+    /// Doubao, DeepSeek) are passed through to the default provider.
     ///
     /// # Arguments
     ///
-    /// * `claude_model` - The Claude model name to map
-    ///
-    /// # Returns
+    /// * `claude_model` - The Claude model name to resolve
+    pub fn resolve(&self, claude_model: &str) -> ModelRoute {
+        self.resolve_with_fallbacks(claude_model).swap_remove(0)
+    }
+
+    /// Resolve a Claude model to its route followed by the tier's fallbacks
     ///
-    /// The corresponding OpenAI model name
-    pub fn map_claude_model_to_openai(&self, claude_model: &str) -> String {
+    /// Passthrough models have no fallbacks.
+    pub fn resolve_with_fallbacks(&self, claude_model: &str) -> Vec<ModelRoute> {
         // If it's already an OpenAI model, return as-is
         if claude_model.starts_with("gpt-") || claude_model.starts_with("o1-") {
-            return claude_model.to_string();
+            return vec![self.passthrough(claude_model)];
         }
 
         // If it's other supported models (ARK/Doubao/DeepSeek), return as-is
@@ -40,41 +52,84 @@ impl ModelManager {
             || claude_model.starts_with("doubao-")
             || claude_model.starts_with("deepseek-")
         {
-            return claude_model.to_string();
+            return vec![self.passthrough(claude_model)];
         }
 
         // Map based on model naming patterns
         let model_lower = claude_model.to_lowercase();
-        if model_lower.contains("haiku") {
-            self.config.small_model.clone()
+        let (model, provider, fallbacks) = if model_lower.contains("haiku") {
+            (
+                &self.config.small_model,
+                self.config.small_provider,
+                &self.config.small_fallbacks,
+            )
         } else if model_lower.contains("sonnet") {
-            self.config.middle_model.clone()
-        } else if model_lower.contains("opus") {
-            self.config.big_model.clone()
+            (
+                &self.config.middle_model,
+                self.config.middle_provider,
+                &self.config.middle_fallbacks,
+            )
         } else {
-            // Default to big model for unknown models
-            self.config.big_model.clone()
+            // Opus, and the default for unknown models
+            (
+                &self.config.big_model,
+                self.config.big_provider,
+                &self.config.big_fallbacks,
+            )
+        };
+
+        let route = ModelRoute {
+            provider,
+            model: model.clone(),
+        };
+        std::iter::once(route)
+            .chain(fallbacks.iter().cloned())
+            .collect()
+    }
+
+    /// Route a model name unchanged to the default provider
+    fn passthrough(&self, model: &str) -> ModelRoute {
+        ModelRoute {
+            provider: self.config.provider,
+            model: model.to_string(),
         }
     }
+
+    /// Map Claude model names to OpenAI model names
+    ///
+    /// Maps Claude model names (opus, sonnet, haiku) to configured OpenAI
+    /// models. If the model name is already an OpenAI model or other supported
+    /// model (ARK, Doubao, DeepSeek), returns it as-is.
+    ///
+    /// # Arguments
+    ///
+    /// * `claude_model` - The Claude model name to map
+    ///
+    /// # Returns
+    ///
+    /// The corresponding OpenAI model name
+    pub fn map_claude_model_to_openai(&self, claude_model: &str) -> String {
+        self.resolve(claude_model).model
+    }
+
+    /// Whether an upstream model inlines its reasoning in `<think>` tags
+    pub fn uses_think_tags(&self, upstream_model: &str) -> bool {
+        self.config
+            .think_tag_models
+            .iter()
+            .any(|prefix| upstream_model.starts_with(prefix.as_str()))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::provider::ProviderType;
 
     fn create_test_config() -> Config {
         Config {
             provider: ProviderType::OpenAI,
-            openai_api_key: "sk-test".to_string(),
+            providers: Default::default(),
             anthropic_api_key: None,
-            openai_base_url: "https://api.openai.com/v1".to_string(),
-            azure_api_version: None,
-            openrouter_site_url: None,
-            openrouter_app_name: None,
-            vertexai_project_id: None,
-            vertexai_location: None,
-            vertexai_access_token: None,
             host: "0.0.0.0".to_string(),
             port: 8082,
             log_level: "INFO".to_string(),
@@ -88,6 +143,16 @@ mod tests {
             big_model: "gpt-4o".to_string(),
             middle_model: "gpt-4o".to_string(),
             small_model: "gpt-4o-mini".to_string(),
+            big_provider: ProviderType::OpenAI,
+            middle_provider: ProviderType::OpenAI,
+            small_provider: ProviderType::OpenAI,
+            think_tag_models: Vec::new(),
+            big_fallbacks: Vec::new(),
+            middle_fallbacks: Vec::new(),
+            small_fallbacks: Vec::new(),
+            circuit_breaker: Default::default(),
+            cassette_mode: None,
+            cassette_dir: "cassettes".to_string(),
         }
     }
 
@@ -126,4 +191,50 @@ mod tests {
             "gpt-4-turbo"
         );
     }
+
+    #[test]
+    fn test_resolve_tier_provider() {
+        let mut config = create_test_config();
+        config.small_model = "llama3.1:8b".to_string();
+        config.small_provider = ProviderType::Ollama;
+        let manager = ModelManager::new(config);
+
+        assert_eq!(
+            manager.resolve("claude-3-5-haiku-20241022"),
+            ModelRoute {
+                provider: ProviderType::Ollama,
+                model: "llama3.1:8b".to_string(),
+            }
+        );
+        assert_eq!(
+            manager.resolve("claude-opus-4-1").provider,
+            ProviderType::OpenAI
+        );
+    }
+
+    #[test]
+    fn test_resolve_with_fallbacks() {
+        let mut config = create_test_config();
+        config.middle_fallbacks = vec![ModelRoute {
+            provider: ProviderType::OpenRouter,
+            model: "openai/gpt-4o".to_string(),
+        }];
+        let manager = ModelManager::new(config);
+
+        let routes = manager.resolve_with_fallbacks("claude-sonnet-4-5");
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[1].provider, ProviderType::OpenRouter);
+        assert_eq!(manager.resolve_with_fallbacks("claude-3-haiku").len(), 1);
+        assert_eq!(manager.resolve_with_fallbacks("gpt-4-turbo").len(), 1);
+    }
+
+    #[test]
+    fn test_uses_think_tags() {
+        let mut config = create_test_config();
+        config.think_tag_models = vec!["deepseek-r1".to_string()];
+        let manager = ModelManager::new(config);
+
+        assert!(manager.uses_think_tags("deepseek-r1-distill-qwen-32b"));
+        assert!(!manager.uses_think_tags("gpt-4o"));
+    }
 }
@@ -1,11 +1,12 @@
 //! Provider abstraction layer for different LLM API providers
 //!
-//! This module defines a common trait for different providers (OpenAI, OpenRouter, Vertex AI)
-//! and provides factory methods for creating provider instances.
+//! This module defines a common trait for different providers (OpenAI, OpenRouter, Vertex AI,
+//! Anthropic, Ollama, Bedrock, Google AI, Mock) and provides factory methods for creating provider instances.
 
 use crate::models::openai::{OpenAIChatCompletionRequest, OpenAIChatCompletionResponse};
 use async_trait::async_trait;
 use futures::stream::Stream;
+use serde_json::Value;
 use std::pin::Pin;
 use thiserror::Error;
 
@@ -24,6 +25,19 @@ pub enum ProviderError {
     #[error("API error (status {status}): {message}")]
     ApiError { status: u16, message: String },
 
+    /// Error body from an Anthropic-compatible upstream, relayed unchanged
+    #[error("Upstream error (status {status}): {body}")]
+    Relayed { status: u16, body: Value },
+
+    #[error("Connection failed: {0}")]
+    Connection(String),
+
+    #[error("Unsupported request: {0}")]
+    Unsupported(String),
+
+    #[error("Circuit open for {0}")]
+    CircuitOpen(String),
+
     #[error("Request cancelled by client")]
     Cancelled,
 
@@ -31,6 +45,77 @@ pub enum ProviderError {
     Unexpected(String),
 }
 
+impl ProviderError {
+    /// Classify an error from sending an HTTP request
+    ///
+    /// Connect failures and timeouts become `Connection`; anything else is
+    /// unexpected.
+    pub fn from_send_error(e: reqwest::Error) -> Self {
+        if e.is_connect() || e.is_timeout() {
+            ProviderError::Connection(e.to_string())
+        } else {
+            ProviderError::Unexpected(e.to_string())
+        }
+    }
+
+    /// Whether the same request may succeed against a fallback provider
+    pub fn should_fallback(&self) -> bool {
+        match self {
+            ProviderError::RateLimit(_)
+            | ProviderError::Connection(_)
+            | ProviderError::CircuitOpen(_)
+            | ProviderError::Unsupported(_) => true,
+            ProviderError::ApiError { status, .. } => *status >= 500,
+            ProviderError::Relayed { status, .. } => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+/// Features a provider supports for a given model
+///
+/// Requests needing a missing feature are rejected with an
+/// `invalid_request_error` (or sent to a fallback) instead of having the
+/// feature silently dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProviderCapabilities {
+    /// Function/tool calling
+    pub tools: bool,
+    /// Image inputs
+    pub vision: bool,
+    /// Streaming responses
+    pub streaming: bool,
+    /// A separate system role; without it the system prompt is sent as a user turn
+    pub system_role: bool,
+    /// Maximum number of stop sequences
+    pub max_stop_sequences: Option<usize>,
+    /// Context window in tokens
+    pub max_context_tokens: Option<u32>,
+    /// Maximum output tokens per response
+    pub max_output_tokens: Option<u32>,
+    /// Thinking from earlier turns can be sent back; it is stripped otherwise
+    pub reasoning_input: bool,
+    /// Every `tool_choice` (required, a named tool, none) is enforced
+    /// upstream; replies are checked against it otherwise
+    pub tool_choice: bool,
+}
+
+impl Default for ProviderCapabilities {
+    fn default() -> Self {
+        Self {
+            tools: true,
+            vision: true,
+            streaming: true,
+            system_role: true,
+            max_stop_sequences: None,
+            max_context_tokens: None,
+            max_output_tokens: None,
+            reasoning_input: false,
+            tool_choice: true,
+        }
+    }
+}
+
 /// Trait for LLM API providers
 #[async_trait]
 pub trait Provider: Send + Sync {
@@ -48,6 +133,44 @@ pub trait Provider: Send + Sync {
         request_id: Option<String>,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<String, ProviderError>> + Send>>, ProviderError>;
 
+    /// Features supported when serving `model`
+    fn capabilities(&self, _model: &str) -> ProviderCapabilities {
+        ProviderCapabilities::default()
+    }
+
+    /// Whether requests for `model` should bypass OpenAI conversion and be
+    /// sent in Anthropic Messages format
+    fn accepts_claude_format(&self, _model: &str) -> bool {
+        false
+    }
+
+    /// Send non-streaming Anthropic Messages request without conversion
+    async fn create_claude_message(
+        &self,
+        _request: &Value,
+        _request_id: Option<String>,
+    ) -> Result<Value, ProviderError> {
+        Err(ProviderError::BadRequest(format!(
+            "{} does not accept Anthropic Messages requests",
+            self.provider_name()
+        )))
+    }
+
+    /// Send streaming Anthropic Messages request without conversion
+    ///
+    /// Yields the upstream SSE lines verbatim.
+    async fn create_claude_message_stream(
+        &self,
+        _request: Value,
+        _request_id: Option<String>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String, ProviderError>> + Send>>, ProviderError>
+    {
+        Err(ProviderError::BadRequest(format!(
+            "{} does not accept Anthropic Messages requests",
+            self.provider_name()
+        )))
+    }
+
     /// Cancel an active request by request_id
     async fn cancel_request(&self, request_id: &str) -> bool;
 
@@ -56,11 +179,16 @@ pub trait Provider: Send + Sync {
 }
 
 /// Supported provider types
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ProviderType {
     OpenAI,
     OpenRouter,
     VertexAI,
+    Anthropic,
+    Ollama,
+    Bedrock,
+    GoogleAI,
+    Mock,
 }
 
 impl ProviderType {
@@ -69,6 +197,11 @@ impl ProviderType {
             "openai" => Some(ProviderType::OpenAI),
             "openrouter" => Some(ProviderType::OpenRouter),
             "vertexai" | "vertex-ai" | "vertex_ai" => Some(ProviderType::VertexAI),
+            "anthropic" | "claude" => Some(ProviderType::Anthropic),
+            "ollama" => Some(ProviderType::Ollama),
+            "bedrock" | "aws-bedrock" | "aws_bedrock" => Some(ProviderType::Bedrock),
+            "googleai" | "google-ai" | "google_ai" | "gemini" => Some(ProviderType::GoogleAI),
+            "mock" => Some(ProviderType::Mock),
             _ => None,
         }
     }
@@ -0,0 +1,308 @@
+//! Anthropic provider implementation
+//!
+//! Forwards Anthropic Messages API requests to an Anthropic-compatible upstream
+//! without converting them, so features such as extended thinking, prompt
+//! caching and citations survive the round trip.
+
+use crate::core::cancellation::ActiveRequests;
+use crate::core::provider::{Provider, ProviderError};
+use crate::models::openai::{OpenAIChatCompletionRequest, OpenAIChatCompletionResponse};
+use async_trait::async_trait;
+use futures::StreamExt;
+use futures::stream::Stream;
+use reqwest::Client;
+use serde_json::Value;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default Anthropic API base URL
+pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com/v1";
+
+/// Default value for the `anthropic-version` header
+pub const DEFAULT_ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Anthropic provider (native Messages API passthrough)
+pub struct AnthropicProvider {
+    client: Client,
+    api_key: String,
+    base_url: String,
+    anthropic_version: String,
+    active_requests: Arc<ActiveRequests>,
+}
+
+impl AnthropicProvider {
+    /// Name reported for this provider
+    pub const NAME: &'static str = "Anthropic";
+
+    /// Create a new Anthropic provider
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - Anthropic API key
+    /// * `base_url` - Anthropic API base URL (default: https://api.anthropic.com/v1)
+    /// * `timeout` - Request timeout in seconds
+    /// * `anthropic_version` - Optional `anthropic-version` header value
+    pub fn new(
+        api_key: String,
+        base_url: Option<String>,
+        timeout: u64,
+        anthropic_version: Option<String>,
+    ) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            client,
+            api_key,
+            base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            anthropic_version: anthropic_version
+                .unwrap_or_else(|| DEFAULT_ANTHROPIC_VERSION.to_string()),
+            active_requests: Arc::default(),
+        }
+    }
+
+    /// Classify Anthropic errors and provide helpful messages
+    fn classify_error(error_detail: &str) -> String {
+        let error_lower = error_detail.to_lowercase();
+
+        if error_lower.contains("authentication_error") || error_lower.contains("invalid x-api-key")
+        {
+            return "Invalid API key. Please check your Anthropic API key configuration."
+                .to_string();
+        }
+
+        if error_lower.contains("rate_limit_error") {
+            return "Rate limit exceeded. Please wait and try again.".to_string();
+        }
+
+        if error_lower.contains("overloaded_error") {
+            return "Anthropic API is overloaded. Please try again later.".to_string();
+        }
+
+        if error_lower.contains("not_found_error") && error_lower.contains("model") {
+            return "Model not found. Please check your model configuration.".to_string();
+        }
+
+        error_detail.to_string()
+    }
+
+    /// Internal method to send a Messages API request
+    ///
+    /// Client betas in the body's `anthropic_beta` are sent as the
+    /// `anthropic-beta` header instead.
+    async fn send_request(&self, mut request: Value) -> Result<reqwest::Response, ProviderError> {
+        let url = format!("{}/messages", self.base_url);
+
+        let mut builder = self
+            .client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", &self.anthropic_version);
+
+        if let Some(betas) = request
+            .as_object_mut()
+            .and_then(|body| body.remove("anthropic_beta"))
+        {
+            let betas: Vec<&str> = betas
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|beta| beta.as_str())
+                .collect();
+            if !betas.is_empty() {
+                builder = builder.header("anthropic-beta", betas.join(","));
+            }
+        }
+
+        let response = builder
+            .json(&request)
+            .send()
+            .await
+            .map_err(ProviderError::from_send_error)?;
+
+        let status = response.status();
+
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+
+            // Errors in the Messages API format go back to the client as is
+            if let Ok(body) = serde_json::from_str::<Value>(&error_text)
+                && body.get("type").and_then(|t| t.as_str()) == Some("error")
+            {
+                return Err(ProviderError::Relayed {
+                    status: status.as_u16(),
+                    body,
+                });
+            }
+
+            let classified_error = Self::classify_error(&error_text);
+
+            return Err(match status.as_u16() {
+                401 | 403 => ProviderError::Authentication(classified_error),
+                429 => ProviderError::RateLimit(classified_error),
+                400 | 404 | 413 => ProviderError::BadRequest(classified_error),
+                _ => ProviderError::ApiError {
+                    status: status.as_u16(),
+                    message: classified_error,
+                },
+            });
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl Provider for AnthropicProvider {
+    async fn create_chat_completion(
+        &self,
+        _request: &OpenAIChatCompletionRequest,
+        _request_id: Option<String>,
+    ) -> Result<OpenAIChatCompletionResponse, ProviderError> {
+        Err(ProviderError::BadRequest(
+            "Anthropic provider only accepts Anthropic Messages requests".to_string(),
+        ))
+    }
+
+    async fn create_chat_completion_stream(
+        &self,
+        _request: OpenAIChatCompletionRequest,
+        _request_id: Option<String>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String, ProviderError>> + Send>>, ProviderError>
+    {
+        Err(ProviderError::BadRequest(
+            "Anthropic provider only accepts Anthropic Messages requests".to_string(),
+        ))
+    }
+
+    fn accepts_claude_format(&self, _model: &str) -> bool {
+        true
+    }
+
+    async fn create_claude_message(
+        &self,
+        request: &Value,
+        request_id: Option<String>,
+    ) -> Result<Value, ProviderError> {
+        let cancel = self.active_requests.register(request_id);
+
+        let mut request = request.clone();
+        request["stream"] = Value::Bool(false);
+
+        cancel
+            .run(async {
+                self.send_request(request)
+                    .await?
+                    .json::<Value>()
+                    .await
+                    .map_err(|e| {
+                        ProviderError::Unexpected(format!("Failed to parse response: {}", e))
+                    })
+            })
+            .await
+    }
+
+    async fn create_claude_message_stream(
+        &self,
+        mut request: Value,
+        request_id: Option<String>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String, ProviderError>> + Send>>, ProviderError>
+    {
+        let cancel = self.active_requests.register(request_id);
+
+        request["stream"] = Value::Bool(true);
+
+        let response = cancel.run(self.send_request(request)).await?;
+
+        use futures::TryStreamExt;
+        use tokio::io::AsyncBufReadExt;
+        use tokio_stream::wrappers::LinesStream;
+
+        let byte_stream = response.bytes_stream();
+        let byte_stream = byte_stream.map_err(std::io::Error::other);
+
+        let reader = tokio_util::io::StreamReader::new(byte_stream);
+        let buf_reader = tokio::io::BufReader::new(reader);
+        let lines = buf_reader.lines();
+        let line_stream = LinesStream::new(lines);
+
+        let stream = line_stream.map(|result: Result<String, std::io::Error>| {
+            result.map_err(|e| ProviderError::Unexpected(e.to_string()))
+        });
+
+        Ok(cancel.wrap_stream(Box::pin(stream)))
+    }
+
+    async fn cancel_request(&self, request_id: &str) -> bool {
+        self.active_requests.cancel(request_id)
+    }
+
+    fn provider_name(&self) -> &str {
+        Self::NAME
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::{Json, Router, routing::post};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_betas_sent_as_header_and_errors_relayed() {
+        let app = Router::new().route(
+            "/messages",
+            post(|headers: HeaderMap, Json(body): Json<Value>| async move {
+                let beta = headers
+                    .get("anthropic-beta")
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                if body.get("anthropic_beta").is_some() {
+                    return (StatusCode::BAD_REQUEST, Json(json!({"unexpected": "body"})));
+                }
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    Json(json!({
+                        "type": "error",
+                        "error": {"type": "rate_limit_error", "message": beta}
+                    })),
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let provider =
+            AnthropicProvider::new("key".to_string(), Some(format!("http://{}", addr)), 5, None);
+        let request = json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 10,
+            "messages": [{"role": "user", "content": "hi"}],
+            "anthropic_beta": ["interleaved-thinking-2025-05-14", "context-1m-2025-08-07"]
+        });
+
+        match provider.create_claude_message(&request, None).await {
+            Err(ProviderError::Relayed { status, body }) => {
+                assert_eq!(status, 429);
+                assert_eq!(body["error"]["type"], "rate_limit_error");
+                assert_eq!(
+                    body["error"]["message"],
+                    "interleaved-thinking-2025-05-14,context-1m-2025-08-07"
+                );
+            }
+            other => panic!("expected a relayed error, got {:?}", other),
+        }
+    }
+}
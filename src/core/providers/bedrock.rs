@@ -0,0 +1,854 @@
+//! AWS Bedrock provider implementation
+//!
+//! Converts OpenAI-format requests to the Bedrock Converse API, signs them with
+//! SigV4 and decodes the ConverseStream event-stream into OpenAI-style chunks.
+
+use crate::core::aws::{AwsCredentials, EventStreamDecoder, sign_request, uri_encode};
+use crate::core::cancellation::ActiveRequests;
+use crate::core::provider::{Provider, ProviderCapabilities, ProviderError};
+use crate::models::openai::{
+    OpenAIChatCompletionRequest, OpenAIChatCompletionResponse, OpenAIChoice, OpenAIDelta,
+    OpenAIFunction, OpenAIFunctionDelta, OpenAIMessage, OpenAIStreamingChunk, OpenAIToolCall,
+    OpenAIToolCallDelta, OpenAIUsage,
+};
+use async_trait::async_trait;
+use futures::StreamExt;
+use futures::stream::Stream;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default AWS region
+pub const DEFAULT_REGION: &str = "us-east-1";
+
+/// Bedrock provider using the Converse API
+pub struct BedrockProvider {
+    client: Client,
+    region: String,
+    base_url: String,
+    credentials: AwsCredentials,
+    active_requests: Arc<ActiveRequests>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConverseResponse {
+    output: ConverseOutput,
+    #[serde(default)]
+    stop_reason: Option<String>,
+    #[serde(default)]
+    usage: Option<ConverseUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConverseOutput {
+    #[serde(default)]
+    message: Option<ConverseMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConverseMessage {
+    #[serde(default)]
+    content: Vec<Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConverseUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+    #[serde(default)]
+    total_tokens: u32,
+}
+
+impl ConverseUsage {
+    fn to_openai(&self) -> OpenAIUsage {
+        OpenAIUsage {
+            prompt_tokens: self.input_tokens,
+            completion_tokens: self.output_tokens,
+            total_tokens: self.total_tokens,
+        }
+    }
+}
+
+impl BedrockProvider {
+    /// Name reported for this provider
+    pub const NAME: &'static str = "AWS Bedrock";
+
+    /// Capabilities shared by every Converse model
+    pub fn model_capabilities() -> ProviderCapabilities {
+        // Converse accepts at most four stop sequences and has no "none"
+        // tool choice
+        ProviderCapabilities {
+            max_stop_sequences: Some(4),
+            tool_choice: false,
+            ..ProviderCapabilities::default()
+        }
+    }
+
+    /// Create a new Bedrock provider
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - AWS region hosting the models
+    /// * `base_url` - Optional endpoint override
+    ///   (default: https://bedrock-runtime.{region}.amazonaws.com)
+    /// * `credentials` - AWS credentials used for SigV4 signing
+    /// * `timeout` - Request timeout in seconds
+    pub fn new(
+        region: String,
+        base_url: Option<String>,
+        credentials: AwsCredentials,
+        timeout: u64,
+    ) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        let base_url = base_url
+            .unwrap_or_else(|| Self::default_base_url(&region))
+            .trim_end_matches('/')
+            .to_string();
+
+        Self {
+            client,
+            region,
+            base_url,
+            credentials,
+            active_requests: Arc::default(),
+        }
+    }
+
+    /// Default Bedrock runtime endpoint for a region
+    pub fn default_base_url(region: &str) -> String {
+        format!("https://bedrock-runtime.{}.amazonaws.com", region)
+    }
+
+    /// Convert an OpenAI content value to Converse content blocks
+    fn convert_content(content: &Value) -> Vec<Value> {
+        let mut blocks = Vec::new();
+
+        match content {
+            Value::String(s) => {
+                if !s.is_empty() {
+                    blocks.push(json!({"text": s}));
+                }
+            }
+            Value::Array(parts) => {
+                for part in parts {
+                    match part.get("type").and_then(|t| t.as_str()) {
+                        Some("text") => {
+                            if let Some(text) = part.get("text").and_then(|t| t.as_str())
+                                && !text.is_empty()
+                            {
+                                blocks.push(json!({"text": text}));
+                            }
+                        }
+                        Some("image_url") => {
+                            let url = part
+                                .get("image_url")
+                                .and_then(|i| i.get("url"))
+                                .and_then(|u| u.as_str())
+                                .unwrap_or_default();
+                            if let Some((media_type, data)) = url
+                                .strip_prefix("data:")
+                                .and_then(|rest| rest.split_once(";base64,"))
+                            {
+                                let format = media_type.strip_prefix("image/").unwrap_or("png");
+                                blocks.push(json!({
+                                    "image": {
+                                        "format": if format == "jpg" { "jpeg" } else { format },
+                                        "source": {"bytes": data}
+                                    }
+                                }));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Value::Null => {}
+            other => blocks.push(json!({"text": other.to_string()})),
+        }
+
+        blocks
+    }
+
+    /// Convert OpenAI request to Bedrock Converse format
+    fn convert_request_to_converse(request: &OpenAIChatCompletionRequest) -> Value {
+        let mut system = Vec::new();
+        let mut messages: Vec<Value> = Vec::new();
+
+        for msg in &request.messages {
+            let (role, blocks) = match msg.role.as_str() {
+                "system" => {
+                    if let Some(ref content) = msg.content {
+                        system.extend(Self::convert_content(content));
+                    }
+                    continue;
+                }
+                "assistant" => {
+                    let mut blocks = msg
+                        .content
+                        .as_ref()
+                        .map(Self::convert_content)
+                        .unwrap_or_default();
+                    for tool_call in msg.tool_calls.iter().flatten() {
+                        blocks.push(json!({
+                            "toolUse": {
+                                "toolUseId": tool_call.id,
+                                "name": tool_call.function.name,
+                                "input": serde_json::from_str::<Value>(&tool_call.function.arguments)
+                                    .unwrap_or_else(|_| json!({}))
+                            }
+                        }));
+                    }
+                    ("assistant", blocks)
+                }
+                "tool" => {
+                    let text = match msg.content {
+                        Some(Value::String(ref s)) => s.clone(),
+                        Some(ref other) => other.to_string(),
+                        None => String::new(),
+                    };
+                    let block = json!({
+                        "toolResult": {
+                            "toolUseId": msg.tool_call_id.clone().unwrap_or_default(),
+                            "content": [{"text": text}]
+                        }
+                    });
+                    ("user", vec![block])
+                }
+                _ => (
+                    "user",
+                    msg.content
+                        .as_ref()
+                        .map(Self::convert_content)
+                        .unwrap_or_default(),
+                ),
+            };
+
+            if blocks.is_empty() {
+                continue;
+            }
+
+            // Converse requires alternating roles, so merge consecutive turns
+            if let Some(last) = messages.last_mut()
+                && last["role"] == role
+                && let Some(content) = last["content"].as_array_mut()
+            {
+                content.extend(blocks);
+                continue;
+            }
+            messages.push(json!({"role": role, "content": blocks}));
+        }
+
+        let mut converse_request = json!({ "messages": messages });
+
+        if !system.is_empty() {
+            converse_request["system"] = Value::Array(system);
+        }
+
+        let mut inference_config = serde_json::Map::new();
+        if let Some(max_tokens) = request.max_tokens {
+            inference_config.insert("maxTokens".to_string(), json!(max_tokens));
+        }
+        if let Some(temperature) = request.temperature {
+            inference_config.insert("temperature".to_string(), json!(temperature));
+        }
+        if let Some(top_p) = request.top_p {
+            inference_config.insert("topP".to_string(), json!(top_p));
+        }
+        if let Some(ref stop) = request.stop {
+            inference_config.insert("stopSequences".to_string(), json!(stop));
+        }
+        if !inference_config.is_empty() {
+            converse_request["inferenceConfig"] = Value::Object(inference_config);
+        }
+
+        if let Some(ref tools) = request.tools {
+            let tool_specs: Vec<Value> = tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "toolSpec": {
+                            "name": tool.function.name,
+                            "description": tool.function.description.clone()
+                                .unwrap_or_else(|| tool.function.name.clone()),
+                            "inputSchema": {"json": tool.function.parameters}
+                        }
+                    })
+                })
+                .collect();
+
+            let mut tool_config = json!({ "tools": tool_specs });
+            match request.tool_choice {
+                Some(Value::String(ref choice)) if choice == "required" => {
+                    tool_config["toolChoice"] = json!({"any": {}});
+                }
+                Some(Value::Object(ref choice)) => {
+                    if let Some(name) = choice
+                        .get("function")
+                        .and_then(|f| f.get("name"))
+                        .and_then(|n| n.as_str())
+                    {
+                        tool_config["toolChoice"] = json!({"tool": {"name": name}});
+                    }
+                }
+                _ => {}
+            }
+            converse_request["toolConfig"] = tool_config;
+        }
+
+        converse_request
+    }
+
+    /// Map a Converse stop reason to an OpenAI finish reason
+    fn map_stop_reason(stop_reason: Option<&str>) -> String {
+        match stop_reason {
+            Some("tool_use") => "tool_calls",
+            Some("max_tokens") => "length",
+            Some("guardrail_intervened") | Some("content_filtered") => "content_filter",
+            _ => "stop",
+        }
+        .to_string()
+    }
+
+    /// Convert a Converse response to OpenAI format
+    fn convert_response_from_converse(
+        response: ConverseResponse,
+        model: &str,
+    ) -> OpenAIChatCompletionResponse {
+        let mut text_parts = Vec::new();
+        let mut tool_calls = Vec::new();
+
+        for block in response
+            .output
+            .message
+            .map(|m| m.content)
+            .unwrap_or_default()
+        {
+            if let Some(text) = block.get("text").and_then(|t| t.as_str()) {
+                text_parts.push(text.to_string());
+            } else if let Some(tool_use) = block.get("toolUse") {
+                tool_calls.push(OpenAIToolCall {
+                    id: tool_use["toolUseId"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    call_type: "function".to_string(),
+                    function: OpenAIFunction {
+                        name: tool_use["name"].as_str().unwrap_or_default().to_string(),
+                        arguments: tool_use
+                            .get("input")
+                            .map(|input| input.to_string())
+                            .unwrap_or_else(|| "{}".to_string()),
+                    },
+                });
+            }
+        }
+
+        OpenAIChatCompletionResponse {
+            id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+            object: "chat.completion".to_string(),
+            created: chrono::Utc::now().timestamp(),
+            model: model.to_string(),
+            choices: vec![OpenAIChoice {
+                index: 0,
+                message: OpenAIMessage {
+                    role: "assistant".to_string(),
+                    content: Some(Value::String(text_parts.join(""))),
+                    tool_calls: if tool_calls.is_empty() {
+                        None
+                    } else {
+                        Some(tool_calls)
+                    },
+                    tool_call_id: None,
+                    reasoning_content: None,
+                    reasoning: None,
+                    reasoning_details: None,
+                },
+                finish_reason: Some(Self::map_stop_reason(response.stop_reason.as_deref())),
+            }],
+            usage: response.usage.unwrap_or_default().to_openai(),
+        }
+    }
+
+    /// Classify Bedrock errors
+    fn classify_error(error_detail: &str) -> String {
+        let error_lower = error_detail.to_lowercase();
+
+        if error_lower.contains("security token") || error_lower.contains("signature") {
+            return "Invalid AWS credentials. Please check your Bedrock access keys.".to_string();
+        }
+
+        if error_lower.contains("access denied") || error_lower.contains("don't have access") {
+            return "Access denied. Enable model access in the Bedrock console and check IAM \
+                    permissions."
+                .to_string();
+        }
+
+        if error_lower.contains("throttl") || error_lower.contains("too many requests") {
+            return "Rate limit exceeded. Please wait and try again.".to_string();
+        }
+
+        if error_lower.contains("model identifier is invalid")
+            || error_lower.contains("could not resolve the foundation model")
+        {
+            return "Model not found. Please check your Bedrock model IDs.".to_string();
+        }
+
+        error_detail.to_string()
+    }
+
+    /// Map an HTTP status and body to a provider error
+    fn error_from_status(status: u16, error_text: &str) -> ProviderError {
+        let classified_error = Self::classify_error(error_text);
+        match status {
+            401 | 403 => ProviderError::Authentication(classified_error),
+            429 => ProviderError::RateLimit(classified_error),
+            400 | 404 => ProviderError::BadRequest(classified_error),
+            _ => ProviderError::ApiError {
+                status,
+                message: classified_error,
+            },
+        }
+    }
+
+    /// Internal method to sign and send a Converse request
+    async fn send_request(
+        &self,
+        request: &OpenAIChatCompletionRequest,
+        stream: bool,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let action = if stream {
+            "converse-stream"
+        } else {
+            "converse"
+        };
+        let url = format!(
+            "{}/model/{}/{}",
+            self.base_url,
+            uri_encode(&request.model, true),
+            action
+        );
+        let url = reqwest::Url::parse(&url)
+            .map_err(|e| ProviderError::Unexpected(format!("Invalid Bedrock URL: {}", e)))?;
+
+        let body = serde_json::to_vec(&Self::convert_request_to_converse(request))
+            .map_err(|e| ProviderError::Unexpected(e.to_string()))?;
+
+        let auth_headers = sign_request(
+            &self.credentials,
+            &self.region,
+            "bedrock",
+            "POST",
+            &url,
+            &[("content-type", "application/json")],
+            &body,
+            chrono::Utc::now(),
+        );
+
+        let mut req_builder = self
+            .client
+            .post(url)
+            .header("Content-Type", "application/json");
+        for (name, value) in auth_headers {
+            req_builder = req_builder.header(name, value);
+        }
+
+        let response = req_builder
+            .body(body)
+            .send()
+            .await
+            .map_err(ProviderError::from_send_error)?;
+
+        let status = response.status();
+
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(Self::error_from_status(status.as_u16(), &error_text));
+        }
+
+        Ok(response)
+    }
+
+    /// Internal method to send completion request
+    async fn send_completion_request(
+        &self,
+        request: &OpenAIChatCompletionRequest,
+    ) -> Result<OpenAIChatCompletionResponse, ProviderError> {
+        let response = self.send_request(request, false).await?;
+
+        let converse_response: ConverseResponse = response
+            .json()
+            .await
+            .map_err(|e| ProviderError::Unexpected(format!("Failed to parse response: {}", e)))?;
+
+        Ok(Self::convert_response_from_converse(
+            converse_response,
+            &request.model,
+        ))
+    }
+}
+
+/// Translates ConverseStream events into OpenAI SSE chunk lines
+struct ConverseStreamTranslator {
+    chunk_id: String,
+    model: String,
+    /// Converse content block index -> OpenAI tool call index
+    tool_indices: HashMap<u64, u32>,
+    stop_reason: Option<String>,
+    finished: bool,
+}
+
+impl ConverseStreamTranslator {
+    fn new(model: &str) -> Self {
+        Self {
+            chunk_id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+            model: model.to_string(),
+            tool_indices: HashMap::new(),
+            stop_reason: None,
+            finished: false,
+        }
+    }
+
+    fn chunk(&self, delta: OpenAIDelta) -> String {
+        OpenAIStreamingChunk::new(&self.chunk_id, &self.model, delta, None).to_sse_line()
+    }
+
+    fn finish(&mut self, usage: Option<OpenAIUsage>) -> Vec<String> {
+        if self.finished {
+            return Vec::new();
+        }
+        self.finished = true;
+
+        let mut final_chunk = OpenAIStreamingChunk::new(
+            &self.chunk_id,
+            &self.model,
+            OpenAIDelta::default(),
+            Some(BedrockProvider::map_stop_reason(
+                self.stop_reason.as_deref(),
+            )),
+        );
+        final_chunk.usage = usage;
+        vec![final_chunk.to_sse_line(), "data: [DONE]".to_string()]
+    }
+
+    /// Translate one event into zero or more SSE lines
+    fn translate(&mut self, event_type: &str, payload: &Value) -> Vec<String> {
+        match event_type {
+            "messageStart" => vec![self.chunk(OpenAIDelta {
+                role: Some("assistant".to_string()),
+                ..Default::default()
+            })],
+            "contentBlockStart" => {
+                let block_index = payload["contentBlockIndex"].as_u64().unwrap_or(0);
+                let Some(tool_use) = payload.get("start").and_then(|s| s.get("toolUse")) else {
+                    return Vec::new();
+                };
+                let tool_index = self.tool_indices.len() as u32;
+                self.tool_indices.insert(block_index, tool_index);
+                vec![self.chunk(OpenAIDelta {
+                    tool_calls: Some(vec![OpenAIToolCallDelta {
+                        index: tool_index,
+                        id: tool_use["toolUseId"].as_str().map(|s| s.to_string()),
+                        call_type: Some("function".to_string()),
+                        function: Some(OpenAIFunctionDelta {
+                            name: tool_use["name"].as_str().map(|s| s.to_string()),
+                            arguments: Some(String::new()),
+                        }),
+                    }]),
+                    ..Default::default()
+                })]
+            }
+            "contentBlockDelta" => {
+                let block_index = payload["contentBlockIndex"].as_u64().unwrap_or(0);
+                let delta = &payload["delta"];
+                if let Some(text) = delta.get("text").and_then(|t| t.as_str()) {
+                    vec![self.chunk(OpenAIDelta {
+                        content: Some(text.to_string()),
+                        ..Default::default()
+                    })]
+                } else if let Some(input) = delta
+                    .get("toolUse")
+                    .and_then(|t| t.get("input"))
+                    .and_then(|i| i.as_str())
+                {
+                    let tool_index = self.tool_indices.get(&block_index).copied().unwrap_or(0);
+                    vec![self.chunk(OpenAIDelta {
+                        tool_calls: Some(vec![OpenAIToolCallDelta {
+                            index: tool_index,
+                            id: None,
+                            call_type: None,
+                            function: Some(OpenAIFunctionDelta {
+                                name: None,
+                                arguments: Some(input.to_string()),
+                            }),
+                        }]),
+                        ..Default::default()
+                    })]
+                } else {
+                    Vec::new()
+                }
+            }
+            "messageStop" => {
+                self.stop_reason = payload["stopReason"].as_str().map(|s| s.to_string());
+                Vec::new()
+            }
+            // Usage arrives after messageStop, so the finish chunk is sent here
+            "metadata" => {
+                let usage = payload
+                    .get("usage")
+                    .and_then(|u| serde_json::from_value::<ConverseUsage>(u.clone()).ok())
+                    .map(|u| u.to_openai());
+                self.finish(usage)
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for BedrockProvider {
+    async fn create_chat_completion(
+        &self,
+        request: &OpenAIChatCompletionRequest,
+        request_id: Option<String>,
+    ) -> Result<OpenAIChatCompletionResponse, ProviderError> {
+        let cancel = self.active_requests.register(request_id);
+        cancel.run(self.send_completion_request(request)).await
+    }
+
+    async fn create_chat_completion_stream(
+        &self,
+        request: OpenAIChatCompletionRequest,
+        request_id: Option<String>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String, ProviderError>> + Send>>, ProviderError>
+    {
+        let cancel = self.active_requests.register(request_id);
+
+        let response = cancel.run(self.send_request(&request, true)).await?;
+        let byte_stream = response.bytes_stream();
+        let mut translator = ConverseStreamTranslator::new(&request.model);
+
+        // Decode binary event-stream frames into OpenAI SSE chunk lines
+        let stream = async_stream::stream! {
+            tokio::pin!(byte_stream);
+            let mut decoder = EventStreamDecoder::new();
+
+            while let Some(bytes_result) = byte_stream.next().await {
+                let bytes = match bytes_result {
+                    Ok(b) => b,
+                    Err(e) => {
+                        yield Err(ProviderError::Unexpected(e.to_string()));
+                        return;
+                    }
+                };
+                decoder.push(&bytes);
+
+                loop {
+                    let message = match decoder.next_message() {
+                        Ok(Some(message)) => message,
+                        Ok(None) => break,
+                        Err(e) => {
+                            yield Err(ProviderError::Unexpected(e));
+                            return;
+                        }
+                    };
+
+                    let payload: Value = serde_json::from_slice(&message.payload).unwrap_or(Value::Null);
+
+                    if message.header(":message-type") == Some("exception") {
+                        let exception_type = message.header(":exception-type").unwrap_or("exception");
+                        let error_text = payload["message"].as_str().unwrap_or(exception_type);
+                        let status = match exception_type {
+                            "throttlingException" => 429,
+                            "validationException" => 400,
+                            "serviceUnavailableException" => 503,
+                            _ => 500,
+                        };
+                        yield Err(BedrockProvider::error_from_status(status, error_text));
+                        return;
+                    }
+
+                    let event_type = message.header(":event-type").unwrap_or_default().to_string();
+                    for line in translator.translate(&event_type, &payload) {
+                        yield Ok(line);
+                    }
+                }
+            }
+
+            // Emit the finish chunk if the stream ended cleanly without metadata
+            for line in translator.finish(None) {
+                yield Ok(line);
+            }
+        };
+
+        Ok(cancel.wrap_stream(Box::pin(stream)))
+    }
+
+    fn capabilities(&self, _model: &str) -> ProviderCapabilities {
+        Self::model_capabilities()
+    }
+
+    async fn cancel_request(&self, request_id: &str) -> bool {
+        self.active_requests.cancel(request_id)
+    }
+
+    fn provider_name(&self) -> &str {
+        Self::NAME
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_request_merges_tool_results() {
+        let request: OpenAIChatCompletionRequest = serde_json::from_value(json!({
+            "model": "anthropic.claude-3-5-sonnet-20240620-v1:0",
+            "max_tokens": 1024,
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "List files"},
+                {"role": "assistant", "tool_calls": [
+                    {"id": "t1", "type": "function", "function": {"name": "ls", "arguments": "{}"}},
+                    {"id": "t2", "type": "function", "function": {"name": "pwd", "arguments": "{}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "t1", "content": "a.txt"},
+                {"role": "tool", "tool_call_id": "t2", "content": "/tmp"}
+            ],
+            "tools": [{"type": "function", "function": {
+                "name": "ls", "parameters": {"type": "object"}
+            }}],
+            "tool_choice": "required"
+        }))
+        .unwrap();
+
+        let converse = BedrockProvider::convert_request_to_converse(&request);
+
+        assert_eq!(converse["system"][0]["text"], "Be brief.");
+        assert_eq!(converse["messages"].as_array().unwrap().len(), 3);
+        assert_eq!(
+            converse["messages"][1]["content"][1]["toolUse"]["toolUseId"],
+            "t2"
+        );
+        assert_eq!(
+            converse["messages"][2]["content"].as_array().unwrap().len(),
+            2
+        );
+        assert_eq!(converse["inferenceConfig"]["maxTokens"], 1024);
+        assert_eq!(converse["toolConfig"]["toolChoice"], json!({"any": {}}));
+    }
+
+    #[test]
+    fn test_stream_translator_emits_usage_with_finish() {
+        let mut translator = ConverseStreamTranslator::new("model");
+        let mut lines = Vec::new();
+        lines.extend(translator.translate("messageStart", &json!({"role": "assistant"})));
+        lines.extend(translator.translate(
+            "contentBlockStart",
+            &json!({"contentBlockIndex": 1, "start": {"toolUse": {"toolUseId": "t1", "name": "ls"}}}),
+        ));
+        lines.extend(translator.translate(
+            "contentBlockDelta",
+            &json!({"contentBlockIndex": 1, "delta": {"toolUse": {"input": "{\"a\":1}"}}}),
+        ));
+        lines.extend(translator.translate("messageStop", &json!({"stopReason": "tool_use"})));
+        lines.extend(translator.translate(
+            "metadata",
+            &json!({"usage": {"inputTokens": 10, "outputTokens": 5, "totalTokens": 15}}),
+        ));
+
+        let last_chunk: Value = serde_json::from_str(&lines[3][6..]).unwrap();
+        assert_eq!(last_chunk["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(last_chunk["usage"]["prompt_tokens"], 10);
+        assert_eq!(lines[4], "data: [DONE]");
+        assert!(translator.finish(None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stream_ends_at_exception_frame() {
+        use crate::core::aws::encode_message;
+        use futures::StreamExt;
+
+        let mut body = encode_message(
+            &[(":message-type", "event"), (":event-type", "messageStart")],
+            br#"{"role":"assistant"}"#,
+        );
+        body.extend(encode_message(
+            &[
+                (":message-type", "event"),
+                (":event-type", "contentBlockDelta"),
+            ],
+            br#"{"contentBlockIndex":0,"delta":{"text":"Hel"}}"#,
+        ));
+        body.extend(encode_message(
+            &[
+                (":message-type", "exception"),
+                (":exception-type", "throttlingException"),
+            ],
+            br#"{"message":"Too many requests"}"#,
+        ));
+
+        let app = axum::Router::new().fallback(move || {
+            let body = body.clone();
+            async move {
+                (
+                    [("content-type", "application/vnd.amazon.eventstream")],
+                    body,
+                )
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let provider = BedrockProvider::new(
+            "us-east-1".to_string(),
+            Some(format!("http://{}", addr)),
+            AwsCredentials {
+                access_key_id: "AKIDEXAMPLE".to_string(),
+                secret_access_key: "secret".to_string(),
+                session_token: None,
+            },
+            5,
+        );
+        let request: OpenAIChatCompletionRequest = serde_json::from_value(json!({
+            "model": "anthropic.claude-3-5-sonnet-20240620-v1:0",
+            "messages": [{"role": "user", "content": "hi"}]
+        }))
+        .unwrap();
+
+        let items: Vec<_> = provider
+            .create_chat_completion_stream(request, None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        // Nothing, not even a finish chunk or [DONE], follows the error
+        assert!(matches!(
+            items.last(),
+            Some(Err(ProviderError::RateLimit(_)))
+        ));
+        let lines: Vec<&String> = items.iter().filter_map(|item| item.as_ref().ok()).collect();
+        assert!(lines.iter().any(|line| line.contains("Hel")));
+        assert!(!lines.iter().any(|line| line.contains("[DONE]")));
+        assert!(!lines.iter().any(|line| line.contains("finish_reason\":\"")));
+    }
+}
@@ -0,0 +1,1190 @@
+//! Shared Gemini API types and conversion
+//!
+//! Vertex AI and Google AI Studio expose the same `generateContent` request and
+//! response format; this module converts between it and the OpenAI format.
+
+use crate::core::provider::{ProviderCapabilities, ProviderError};
+use crate::models::openai::{
+    OpenAIChatCompletionRequest, OpenAIChatCompletionResponse, OpenAIChoice, OpenAIDelta,
+    OpenAIFunction, OpenAIFunctionDelta, OpenAIMessage, OpenAIStreamingChunk, OpenAIToolCall,
+    OpenAIToolCallDelta, OpenAIUsage,
+};
+use futures::StreamExt;
+use futures::stream::Stream;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::pin::Pin;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiRequest {
+    pub contents: Vec<GeminiContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_instruction: Option<GeminiContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<GeminiTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_config: Option<GeminiToolConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generation_config: Option<GeminiGenerationConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safety_settings: Option<Vec<GeminiSafetySetting>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GeminiContent {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub role: String,
+    #[serde(default)]
+    pub parts: Vec<GeminiPart>,
+}
+
+/// A content part; exactly one field is set
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiPart {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inline_data: Option<GeminiBlob>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function_call: Option<GeminiFunctionCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function_response: Option<GeminiFunctionResponse>,
+}
+
+/// Inline base64 media
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiBlob {
+    pub mime_type: String,
+    pub data: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GeminiFunctionCall {
+    pub name: String,
+    #[serde(default)]
+    pub args: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GeminiFunctionResponse {
+    pub name: String,
+    pub response: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiTool {
+    pub function_declarations: Vec<GeminiFunctionDeclaration>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GeminiFunctionDeclaration {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiToolConfig {
+    pub function_calling_config: GeminiFunctionCallingConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiFunctionCallingConfig {
+    /// AUTO, ANY or NONE
+    pub mode: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_function_names: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiGenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_config: Option<GeminiThinkingConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiThinkingConfig {
+    pub thinking_budget: u32,
+}
+
+/// Thinking budget for a Gemini model, if the model can think
+///
+/// Gemini 2.5 Pro accepts up to 32768 thinking tokens, the Flash models
+/// up to 24576.
+fn thinking_budget(model: &str, budget: u32) -> Option<u32> {
+    if !(model.contains("gemini-2.5") || model.contains("gemini-3")) {
+        return None;
+    }
+    let max_budget = if model.contains("pro") {
+        32_768
+    } else {
+        24_576
+    };
+    Some(budget.min(max_budget))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeminiSafetySetting {
+    pub category: String,
+    pub threshold: String,
+}
+
+/// Build safety settings from a category -> threshold table
+///
+/// Sorted by category so requests are deterministic; `None` when empty.
+pub fn safety_settings_from_thresholds(
+    thresholds: &HashMap<String, String>,
+) -> Option<Vec<GeminiSafetySetting>> {
+    if thresholds.is_empty() {
+        return None;
+    }
+
+    let mut settings: Vec<GeminiSafetySetting> = thresholds
+        .iter()
+        .map(|(category, threshold)| GeminiSafetySetting {
+            category: category.clone(),
+            threshold: threshold.clone(),
+        })
+        .collect();
+    settings.sort_by(|a, b| a.category.cmp(&b.category));
+    Some(settings)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiSafetyRating {
+    pub category: String,
+    #[serde(default)]
+    pub probability: String,
+    #[serde(default)]
+    pub blocked: bool,
+}
+
+/// Why the prompt itself was rejected
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiPromptFeedback {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_reason: Option<String>,
+    #[serde(default)]
+    pub safety_ratings: Vec<GeminiSafetyRating>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiResponse {
+    #[serde(default)]
+    pub candidates: Vec<GeminiCandidate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_feedback: Option<GeminiPromptFeedback>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_metadata: Option<GeminiUsageMetadata>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiCandidate {
+    #[serde(default)]
+    pub content: Option<GeminiContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+    #[serde(default)]
+    pub safety_ratings: Vec<GeminiSafetyRating>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiUsageMetadata {
+    #[serde(default)]
+    pub prompt_token_count: u32,
+    #[serde(default)]
+    pub candidates_token_count: u32,
+    #[serde(default)]
+    pub total_token_count: u32,
+}
+
+impl GeminiUsageMetadata {
+    fn to_openai(&self) -> OpenAIUsage {
+        OpenAIUsage {
+            prompt_tokens: self.prompt_token_count,
+            completion_tokens: self.candidates_token_count,
+            total_tokens: self.total_token_count,
+        }
+    }
+}
+
+/// JSON Schema keywords understood by Gemini's OpenAPI schema subset
+const SUPPORTED_SCHEMA_KEYS: &[&str] = &[
+    "type",
+    "format",
+    "title",
+    "description",
+    "nullable",
+    "enum",
+    "properties",
+    "required",
+    "items",
+    "minItems",
+    "maxItems",
+    "minimum",
+    "maximum",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "anyOf",
+];
+
+/// Reduce a JSON Schema to the subset Gemini accepts
+///
+/// Unsupported keywords such as `$schema` and `additionalProperties` are
+/// rejected by the API, so they are dropped. `"type": ["string", "null"]`
+/// becomes `"type": "string", "nullable": true`, a union of several types
+/// becomes an `anyOf` with one schema per type, and `const` becomes `enum`.
+pub fn sanitize_schema(schema: &Value) -> Value {
+    let Value::Object(object) = schema else {
+        return schema.clone();
+    };
+
+    let mut sanitized = serde_json::Map::new();
+    for (key, value) in object {
+        match key.as_str() {
+            "type" => match value {
+                Value::Array(types) => {
+                    let non_null: Vec<&Value> = types
+                        .iter()
+                        .filter(|t| t.as_str() != Some("null"))
+                        .collect();
+                    match non_null.as_slice() {
+                        [] => {}
+                        [single] => {
+                            sanitized.insert("type".to_string(), (*single).clone());
+                        }
+                        union => {
+                            let variants = union.iter().map(|t| json!({"type": t})).collect();
+                            sanitized.insert("anyOf".to_string(), Value::Array(variants));
+                        }
+                    }
+                    if non_null.len() < types.len() {
+                        sanitized.insert("nullable".to_string(), Value::Bool(true));
+                    }
+                }
+                _ => {
+                    sanitized.insert(key.clone(), value.clone());
+                }
+            },
+            "const" => {
+                sanitized.insert("enum".to_string(), Value::Array(vec![value.clone()]));
+            }
+            "properties" => {
+                if let Value::Object(properties) = value {
+                    let properties = properties
+                        .iter()
+                        .map(|(name, property)| (name.clone(), sanitize_schema(property)))
+                        .collect();
+                    sanitized.insert(key.clone(), Value::Object(properties));
+                }
+            }
+            "items" => {
+                sanitized.insert(key.clone(), sanitize_schema(value));
+            }
+            "anyOf" => {
+                if let Value::Array(variants) = value {
+                    sanitized.insert(
+                        key.clone(),
+                        Value::Array(variants.iter().map(sanitize_schema).collect()),
+                    );
+                }
+            }
+            _ if SUPPORTED_SCHEMA_KEYS.contains(&key.as_str()) => {
+                sanitized.insert(key.clone(), value.clone());
+            }
+            _ => {}
+        }
+    }
+
+    Value::Object(sanitized)
+}
+
+/// Convert OpenAI tool definitions to Gemini function declarations
+fn convert_tools(request: &OpenAIChatCompletionRequest) -> Option<Vec<GeminiTool>> {
+    let tools = request.tools.as_ref()?;
+    if tools.is_empty() {
+        return None;
+    }
+
+    let function_declarations = tools
+        .iter()
+        .map(|tool| {
+            let parameters = sanitize_schema(
+                &serde_json::to_value(&tool.function.parameters).unwrap_or(Value::Null),
+            );
+            // Gemini rejects object schemas without properties
+            let has_properties = parameters
+                .get("properties")
+                .and_then(|p| p.as_object())
+                .is_some_and(|p| !p.is_empty());
+
+            GeminiFunctionDeclaration {
+                name: tool.function.name.clone(),
+                description: tool.function.description.clone(),
+                parameters: has_properties.then_some(parameters),
+            }
+        })
+        .collect();
+
+    Some(vec![GeminiTool {
+        function_declarations,
+    }])
+}
+
+/// Convert OpenAI `tool_choice` to a Gemini tool config
+fn convert_tool_choice(tool_choice: &Value) -> Option<GeminiToolConfig> {
+    let (mode, allowed_function_names) = match tool_choice {
+        Value::String(choice) => match choice.as_str() {
+            "auto" => ("AUTO", None),
+            "required" => ("ANY", None),
+            "none" => ("NONE", None),
+            _ => return None,
+        },
+        Value::Object(choice) => {
+            let name = choice
+                .get("function")
+                .and_then(|f| f.get("name"))
+                .and_then(|n| n.as_str())?;
+            ("ANY", Some(vec![name.to_string()]))
+        }
+        _ => return None,
+    };
+
+    Some(GeminiToolConfig {
+        function_calling_config: GeminiFunctionCallingConfig {
+            mode: mode.to_string(),
+            allowed_function_names,
+        },
+    })
+}
+
+/// Flatten message content to text
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(s) => s.clone(),
+        Value::Array(arr) => {
+            // Handle array of content blocks
+            arr.iter()
+                .filter_map(|v| v.get("text").and_then(|t| t.as_str()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        _ => content.to_string(),
+    }
+}
+
+/// Convert OpenAI message content to Gemini parts
+///
+/// Text blocks become text parts and `image_url` data URIs become
+/// `inlineData` parts. Remote image URLs cannot be inlined and are skipped.
+fn convert_content_parts(content: &Value) -> Vec<GeminiPart> {
+    let text_part = |text: &str| GeminiPart {
+        text: Some(text.to_string()),
+        ..Default::default()
+    };
+
+    match content {
+        Value::String(s) if s.is_empty() => Vec::new(),
+        Value::String(s) => vec![text_part(s)],
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|block| match block.get("type").and_then(|t| t.as_str()) {
+                Some("text") => block
+                    .get("text")
+                    .and_then(|t| t.as_str())
+                    .filter(|t| !t.is_empty())
+                    .map(text_part),
+                Some("image_url") => {
+                    let url = block.get("image_url")?.get("url")?.as_str()?;
+                    let (mime_type, data) = url.strip_prefix("data:")?.split_once(";base64,")?;
+                    Some(GeminiPart {
+                        inline_data: Some(GeminiBlob {
+                            mime_type: mime_type.to_string(),
+                            data: data.to_string(),
+                        }),
+                        ..Default::default()
+                    })
+                }
+                _ => None,
+            })
+            .collect(),
+        Value::Null => Vec::new(),
+        other => vec![text_part(&other.to_string())],
+    }
+}
+
+/// Append parts to the conversation, merging consecutive turns of the same role
+///
+/// Gemini requires user and model turns to alternate, and all function
+/// responses for one model turn must arrive in a single user turn.
+fn push_content(contents: &mut Vec<GeminiContent>, role: &str, parts: Vec<GeminiPart>) {
+    if parts.is_empty() {
+        return;
+    }
+
+    match contents.last_mut() {
+        Some(last) if last.role == role => last.parts.extend(parts),
+        _ => contents.push(GeminiContent {
+            role: role.to_string(),
+            parts,
+        }),
+    }
+}
+
+/// Convert OpenAI request to Gemini format
+pub fn convert_request(request: &OpenAIChatCompletionRequest) -> GeminiRequest {
+    let mut contents: Vec<GeminiContent> = Vec::new();
+    let mut system_parts = Vec::new();
+    // functionResponse parts are matched to calls by name, not id
+    let mut tool_names: HashMap<String, String> = HashMap::new();
+
+    for msg in &request.messages {
+        match msg.role.as_str() {
+            "system" | "developer" => {
+                if let Some(ref content) = msg.content {
+                    system_parts.extend(convert_content_parts(content));
+                }
+            }
+            "assistant" => {
+                let mut parts = msg
+                    .content
+                    .as_ref()
+                    .map(convert_content_parts)
+                    .unwrap_or_default();
+                for tool_call in msg.tool_calls.iter().flatten() {
+                    tool_names.insert(tool_call.id.clone(), tool_call.function.name.clone());
+                    parts.push(GeminiPart {
+                        function_call: Some(GeminiFunctionCall {
+                            name: tool_call.function.name.clone(),
+                            args: serde_json::from_str(&tool_call.function.arguments)
+                                .unwrap_or_else(|_| json!({})),
+                        }),
+                        ..Default::default()
+                    });
+                }
+                push_content(&mut contents, "model", parts);
+            }
+            "tool" => {
+                let output = msg.content.as_ref().map(content_text).unwrap_or_default();
+                // The response must be an object; wrap plain text results
+                let response = match serde_json::from_str::<Value>(&output) {
+                    Ok(value @ Value::Object(_)) => value,
+                    _ => json!({ "content": output }),
+                };
+                let part = GeminiPart {
+                    function_response: Some(GeminiFunctionResponse {
+                        name: msg
+                            .tool_call_id
+                            .as_ref()
+                            .and_then(|id| tool_names.get(id))
+                            .cloned()
+                            .unwrap_or_default(),
+                        response,
+                    }),
+                    ..Default::default()
+                };
+                push_content(&mut contents, "user", vec![part]);
+            }
+            _ => {
+                let parts = msg
+                    .content
+                    .as_ref()
+                    .map(convert_content_parts)
+                    .unwrap_or_default();
+                push_content(&mut contents, "user", parts);
+            }
+        }
+    }
+
+    let system_instruction = if system_parts.is_empty() {
+        None
+    } else {
+        Some(GeminiContent {
+            role: String::new(),
+            parts: system_parts,
+        })
+    };
+
+    let generation_config = Some(GeminiGenerationConfig {
+        temperature: request.temperature,
+        top_p: request.top_p,
+        max_output_tokens: request.max_tokens,
+        thinking_config: request
+            .reasoning
+            .as_ref()
+            .and_then(|reasoning| reasoning.max_tokens)
+            .and_then(|budget| thinking_budget(&request.model, budget))
+            .map(|thinking_budget| GeminiThinkingConfig { thinking_budget }),
+    });
+
+    let tools = convert_tools(request);
+    let tool_config = if tools.is_some() {
+        request.tool_choice.as_ref().and_then(convert_tool_choice)
+    } else {
+        None
+    };
+
+    GeminiRequest {
+        contents,
+        system_instruction,
+        tools,
+        tool_config,
+        generation_config,
+        safety_settings: None,
+    }
+}
+
+/// Map a Gemini finish reason to an OpenAI finish reason
+fn map_finish_reason(finish_reason: Option<&str>) -> &'static str {
+    match finish_reason {
+        Some("MAX_TOKENS") => "length",
+        Some(reason) if is_blocked_reason(reason) => "content_filter",
+        _ => "stop",
+    }
+}
+
+/// Whether a finish or block reason means content was withheld
+fn is_blocked_reason(reason: &str) -> bool {
+    matches!(
+        reason,
+        "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" | "IMAGE_SAFETY"
+    )
+}
+
+/// Explain a blocked response, listing the safety ratings that caused it
+fn blocked_message(reason: &str, ratings: &[GeminiSafetyRating]) -> String {
+    let mut message = format!("[Response blocked by Gemini: {}]", reason);
+
+    if !ratings.is_empty() {
+        let ratings = ratings
+            .iter()
+            .map(|r| {
+                let blocked = if r.blocked { ", blocked" } else { "" };
+                format!("{} ({}{})", r.category, r.probability, blocked)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        message.push_str(&format!("\nSafety ratings: {}", ratings));
+    }
+
+    message
+}
+
+/// Block reason and ratings for a response that was withheld, if any
+///
+/// Covers both blocked candidates and prompts rejected before generation.
+fn block_details(response: &GeminiResponse) -> Option<(String, &[GeminiSafetyRating])> {
+    if let Some(candidate) = response.candidates.first() {
+        return candidate
+            .finish_reason
+            .as_deref()
+            .filter(|reason| is_blocked_reason(reason))
+            .map(|reason| (reason.to_string(), candidate.safety_ratings.as_slice()));
+    }
+
+    let feedback = response.prompt_feedback.as_ref()?;
+    let reason = feedback.block_reason.as_ref()?;
+    Some((
+        format!("prompt {}", reason),
+        feedback.safety_ratings.as_slice(),
+    ))
+}
+
+/// Convert Gemini response to OpenAI format
+pub fn convert_response(response: GeminiResponse, model: &str) -> OpenAIChatCompletionResponse {
+    let blocked =
+        block_details(&response).map(|(reason, ratings)| blocked_message(&reason, ratings));
+
+    let choice = if let Some(candidate) = response.candidates.first() {
+        let parts = candidate
+            .content
+            .as_ref()
+            .map(|c| c.parts.as_slice())
+            .unwrap_or_default();
+
+        let content = parts
+            .iter()
+            .filter_map(|p| p.text.clone())
+            .chain(blocked)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let tool_calls: Vec<OpenAIToolCall> = parts
+            .iter()
+            .filter_map(|p| p.function_call.as_ref())
+            .map(|call| OpenAIToolCall {
+                id: format!("call_{}", uuid::Uuid::new_v4().simple()),
+                call_type: "function".to_string(),
+                function: OpenAIFunction {
+                    name: call.name.clone(),
+                    arguments: call.args.to_string(),
+                },
+            })
+            .collect();
+
+        let finish_reason = if tool_calls.is_empty() {
+            map_finish_reason(candidate.finish_reason.as_deref())
+        } else {
+            "tool_calls"
+        }
+        .to_string();
+
+        OpenAIChoice {
+            index: 0,
+            message: OpenAIMessage {
+                role: "assistant".to_string(),
+                content: Some(Value::String(content)),
+                tool_calls: if tool_calls.is_empty() {
+                    None
+                } else {
+                    Some(tool_calls)
+                },
+                tool_call_id: None,
+                reasoning_content: None,
+                reasoning: None,
+                reasoning_details: None,
+            },
+            finish_reason: Some(finish_reason),
+        }
+    } else {
+        OpenAIChoice {
+            index: 0,
+            finish_reason: Some(
+                if blocked.is_some() {
+                    "content_filter"
+                } else {
+                    "stop"
+                }
+                .to_string(),
+            ),
+            message: OpenAIMessage {
+                role: "assistant".to_string(),
+                content: Some(Value::String(blocked.unwrap_or_default())),
+                tool_calls: None,
+                tool_call_id: None,
+                reasoning_content: None,
+                reasoning: None,
+                reasoning_details: None,
+            },
+        }
+    };
+
+    let usage = response
+        .usage_metadata
+        .map(|u| u.to_openai())
+        .unwrap_or(OpenAIUsage {
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            total_tokens: 0,
+        });
+
+    OpenAIChatCompletionResponse {
+        id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+        object: "chat.completion".to_string(),
+        created: chrono::Utc::now().timestamp(),
+        model: model.to_string(),
+        choices: vec![choice],
+        usage,
+    }
+}
+
+/// Features of a Gemini model
+///
+/// Gemini accepts at most five stop sequences; 2.5 models have a larger
+/// output limit, and 1.5 Pro a 2M token context window.
+pub fn capabilities(model: &str) -> ProviderCapabilities {
+    let max_context_tokens = if model.starts_with("gemini-1.5-pro") {
+        2_097_152
+    } else {
+        1_048_576
+    };
+    let max_output_tokens = if model.starts_with("gemini-2.5") {
+        65_536
+    } else {
+        8_192
+    };
+
+    ProviderCapabilities {
+        max_stop_sequences: Some(5),
+        max_context_tokens: Some(max_context_tokens),
+        max_output_tokens: Some(max_output_tokens),
+        ..ProviderCapabilities::default()
+    }
+}
+
+/// Map an HTTP error status to a provider error
+///
+/// `classified_error` is the provider-specific, human-readable error message.
+pub fn error_from_status(status: u16, classified_error: String) -> ProviderError {
+    match status {
+        401 | 403 => ProviderError::Authentication(classified_error),
+        429 => ProviderError::RateLimit(classified_error),
+        400 | 404 => ProviderError::BadRequest(classified_error),
+        _ => ProviderError::ApiError {
+            status,
+            message: classified_error,
+        },
+    }
+}
+
+/// Translates Gemini `alt=sse` chunks into OpenAI SSE chunk lines
+pub struct GeminiStreamTranslator {
+    chunk_id: String,
+    model: String,
+    started: bool,
+    tool_call_count: u32,
+    finish_reason: Option<String>,
+    blocked_message: Option<String>,
+    usage: Option<OpenAIUsage>,
+}
+
+impl GeminiStreamTranslator {
+    pub fn new(model: &str) -> Self {
+        Self {
+            chunk_id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+            model: model.to_string(),
+            started: false,
+            tool_call_count: 0,
+            finish_reason: None,
+            blocked_message: None,
+            usage: None,
+        }
+    }
+
+    fn chunk(&self, delta: OpenAIDelta) -> String {
+        OpenAIStreamingChunk::new(&self.chunk_id, &self.model, delta, None).to_sse_line()
+    }
+
+    /// Translate one SSE line into zero or more OpenAI chunk lines
+    pub fn translate_line(&mut self, line: &str) -> Result<Vec<String>, ProviderError> {
+        let Some(data) = line.strip_prefix("data:") else {
+            return Ok(Vec::new());
+        };
+        let value: Value = match serde_json::from_str(data.trim()) {
+            Ok(value) => value,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        if let Some(error) = value.get("error") {
+            let message = error["message"].as_str().unwrap_or("Unknown error");
+            return Err(error_from_status(
+                error["code"].as_u64().unwrap_or(500) as u16,
+                message.to_string(),
+            ));
+        }
+
+        let response: GeminiResponse = serde_json::from_value(value)
+            .map_err(|e| ProviderError::Unexpected(format!("Failed to parse chunk: {}", e)))?;
+
+        let mut lines = Vec::new();
+
+        if !self.started {
+            self.started = true;
+            lines.push(self.chunk(OpenAIDelta {
+                role: Some("assistant".to_string()),
+                ..Default::default()
+            }));
+        }
+
+        // Later chunks carry cumulative usage, so keep the latest
+        if let Some(ref usage) = response.usage_metadata {
+            self.usage = Some(usage.to_openai());
+        }
+
+        if let Some((reason, ratings)) = block_details(&response) {
+            self.finish_reason = Some(reason.clone());
+            self.blocked_message = Some(blocked_message(&reason, ratings));
+        }
+
+        let Some(candidate) = response.candidates.into_iter().next() else {
+            return Ok(lines);
+        };
+
+        for part in candidate.content.map(|c| c.parts).unwrap_or_default() {
+            if let Some(text) = part.text {
+                if !text.is_empty() {
+                    lines.push(self.chunk(OpenAIDelta {
+                        content: Some(text),
+                        ..Default::default()
+                    }));
+                }
+            } else if let Some(call) = part.function_call {
+                // Gemini sends each function call whole
+                lines.push(self.chunk(OpenAIDelta {
+                    tool_calls: Some(vec![OpenAIToolCallDelta {
+                        index: self.tool_call_count,
+                        id: Some(format!("call_{}", uuid::Uuid::new_v4().simple())),
+                        call_type: Some("function".to_string()),
+                        function: Some(OpenAIFunctionDelta {
+                            name: Some(call.name),
+                            arguments: Some(call.args.to_string()),
+                        }),
+                    }]),
+                    ..Default::default()
+                }));
+                self.tool_call_count += 1;
+            }
+        }
+
+        if let Some(finish_reason) = candidate.finish_reason
+            && self.blocked_message.is_none()
+        {
+            self.finish_reason = Some(finish_reason);
+        }
+
+        Ok(lines)
+    }
+
+    /// Emit the final chunk with finish reason and usage
+    pub fn finish(&mut self) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        let finish_reason = if let Some(message) = self.blocked_message.take() {
+            lines.push(self.chunk(OpenAIDelta {
+                content: Some(message),
+                ..Default::default()
+            }));
+            "content_filter"
+        } else if self.tool_call_count > 0 {
+            "tool_calls"
+        } else {
+            map_finish_reason(self.finish_reason.as_deref())
+        };
+
+        let mut final_chunk = OpenAIStreamingChunk::new(
+            &self.chunk_id,
+            &self.model,
+            OpenAIDelta::default(),
+            Some(finish_reason.to_string()),
+        );
+        final_chunk.usage = self.usage.take();
+        lines.push(final_chunk.to_sse_line());
+        lines.push("data: [DONE]".to_string());
+        lines
+    }
+}
+
+/// Translate a `streamGenerateContent?alt=sse` response into OpenAI chunk lines
+pub fn translate_stream(
+    response: reqwest::Response,
+    model: &str,
+) -> Pin<Box<dyn Stream<Item = Result<String, ProviderError>> + Send>> {
+    use futures::TryStreamExt;
+    use tokio::io::AsyncBufReadExt;
+    use tokio_stream::wrappers::LinesStream;
+
+    let byte_stream = response.bytes_stream();
+    let byte_stream = byte_stream.map_err(std::io::Error::other);
+
+    let reader = tokio_util::io::StreamReader::new(byte_stream);
+    let buf_reader = tokio::io::BufReader::new(reader);
+    let lines = buf_reader.lines();
+    let line_stream = LinesStream::new(lines);
+    let mut translator = GeminiStreamTranslator::new(model);
+
+    let stream = async_stream::stream! {
+        tokio::pin!(line_stream);
+
+        while let Some(line_result) = line_stream.next().await {
+            let line = match line_result {
+                Ok(line) => line,
+                Err(e) => {
+                    yield Err(ProviderError::Unexpected(e.to_string()));
+                    return;
+                }
+            };
+
+            match translator.translate_line(&line) {
+                Ok(translated) => {
+                    for chunk in translated {
+                        yield Ok(chunk);
+                    }
+                }
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            }
+        }
+
+        for chunk in translator.finish() {
+            yield Ok(chunk);
+        }
+    };
+
+    Box::pin(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gemini_wire_format_is_camel_case() {
+        let request: OpenAIChatCompletionRequest = serde_json::from_value(json!({
+            "model": "gemini-2.5-pro",
+            "max_tokens": 512,
+            "messages": [{"role": "user", "content": "Hi"}],
+            "reasoning": {"max_tokens": 40000}
+        }))
+        .unwrap();
+
+        let body = serde_json::to_value(convert_request(&request)).unwrap();
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 512);
+        assert_eq!(
+            body["generationConfig"]["thinkingConfig"]["thinkingBudget"],
+            32768
+        );
+
+        let response: GeminiResponse = serde_json::from_value(json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [{"text": "Hello"}]},
+                "finishReason": "MAX_TOKENS"
+            }],
+            "usageMetadata": {"promptTokenCount": 3, "candidatesTokenCount": 1, "totalTokenCount": 4}
+        }))
+        .unwrap();
+
+        let converted = convert_response(response, "gemini-2.5-pro");
+        assert_eq!(
+            converted.choices[0].finish_reason.as_deref(),
+            Some("length")
+        );
+        assert_eq!(converted.usage.total_tokens, 4);
+    }
+
+    #[test]
+    fn test_function_calling_round_trip() {
+        let request: OpenAIChatCompletionRequest = serde_json::from_value(json!({
+            "model": "gemini-2.5-pro",
+            "messages": [
+                {"role": "user", "content": "Read both files"},
+                {"role": "assistant", "tool_calls": [
+                    {"id": "call_a", "type": "function", "function": {"name": "read", "arguments": "{\"path\":\"a\"}"}},
+                    {"id": "call_b", "type": "function", "function": {"name": "stat", "arguments": "{}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_a", "content": "alpha"},
+                {"role": "tool", "tool_call_id": "call_b", "content": "{\"size\": 3}"}
+            ],
+            "tools": [{"type": "function", "function": {
+                "name": "read",
+                "parameters": {
+                    "$schema": "http://json-schema.org/draft-07/schema#",
+                    "type": "object",
+                    "additionalProperties": false,
+                    "properties": {"path": {"type": ["string", "null"]}},
+                    "required": ["path"]
+                }
+            }}],
+            "tool_choice": {"type": "function", "function": {"name": "read"}}
+        }))
+        .unwrap();
+
+        let body = serde_json::to_value(convert_request(&request)).unwrap();
+
+        assert_eq!(body["contents"].as_array().unwrap().len(), 3);
+        assert_eq!(
+            body["contents"][1]["parts"][0]["functionCall"]["args"]["path"],
+            "a"
+        );
+        let responses = &body["contents"][2]["parts"];
+        assert_eq!(responses[0]["functionResponse"]["name"], "read");
+        assert_eq!(
+            responses[0]["functionResponse"]["response"]["content"],
+            "alpha"
+        );
+        assert_eq!(responses[1]["functionResponse"]["response"]["size"], 3);
+
+        let declaration = &body["tools"][0]["functionDeclarations"][0];
+        assert!(declaration["parameters"].get("$schema").is_none());
+        assert!(
+            declaration["parameters"]
+                .get("additionalProperties")
+                .is_none()
+        );
+        assert_eq!(
+            declaration["parameters"]["properties"]["path"]["type"],
+            "string"
+        );
+        assert_eq!(
+            declaration["parameters"]["properties"]["path"]["nullable"],
+            true
+        );
+        assert_eq!(
+            body["toolConfig"]["functionCallingConfig"],
+            json!({"mode": "ANY", "allowedFunctionNames": ["read"]})
+        );
+
+        let response: GeminiResponse = serde_json::from_value(json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"functionCall": {"name": "read", "args": {"path": "b"}}}
+                ]},
+                "finishReason": "STOP"
+            }]
+        }))
+        .unwrap();
+
+        let converted = convert_response(response, "gemini-2.5-pro");
+        let tool_calls = converted.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(
+            converted.choices[0].finish_reason.as_deref(),
+            Some("tool_calls")
+        );
+        assert_eq!(tool_calls[0].function.name, "read");
+        assert_eq!(tool_calls[0].function.arguments, r#"{"path":"b"}"#);
+    }
+
+    #[test]
+    fn test_sanitize_schema_type_unions() {
+        assert_eq!(
+            sanitize_schema(&json!({"type": ["string", "null"]})),
+            json!({"type": "string", "nullable": true})
+        );
+        assert_eq!(
+            sanitize_schema(&json!({"type": ["string", "integer"]})),
+            json!({"anyOf": [{"type": "string"}, {"type": "integer"}]})
+        );
+        assert_eq!(
+            sanitize_schema(&json!({"type": ["string", "integer", "null"]})),
+            json!({
+                "anyOf": [{"type": "string"}, {"type": "integer"}],
+                "nullable": true
+            })
+        );
+    }
+
+    #[test]
+    fn test_stream_translator_chunks() {
+        let mut translator = GeminiStreamTranslator::new("gemini-2.5-flash");
+        let mut lines = Vec::new();
+        for line in [
+            r#"data: {"candidates": [{"content": {"role": "model", "parts": [{"text": "Hel"}]}}]}"#,
+            "",
+            r#"data: {"candidates": [{"content": {"role": "model", "parts": [{"text": "lo"}]}, "finishReason": "STOP"}], "usageMetadata": {"promptTokenCount": 5, "candidatesTokenCount": 2, "totalTokenCount": 7}}"#,
+        ] {
+            lines.extend(translator.translate_line(line).unwrap());
+        }
+        lines.extend(translator.finish());
+
+        assert_eq!(lines.len(), 5);
+        let text: Value = serde_json::from_str(&lines[2][6..]).unwrap();
+        assert_eq!(text["choices"][0]["delta"]["content"], "lo");
+        let finish: Value = serde_json::from_str(&lines[3][6..]).unwrap();
+        assert_eq!(finish["choices"][0]["finish_reason"], "stop");
+        assert_eq!(finish["usage"]["completion_tokens"], 2);
+        assert_eq!(lines[4], "data: [DONE]");
+
+        let error = translator
+            .translate_line(r#"data: {"error": {"code": 429, "message": "Resource exhausted"}}"#);
+        assert!(matches!(error, Err(ProviderError::RateLimit(_))));
+    }
+
+    #[test]
+    fn test_system_instruction_and_inline_images() {
+        let request: OpenAIChatCompletionRequest = serde_json::from_value(json!({
+            "model": "gemini-2.5-pro",
+            "messages": [
+                {"role": "system", "content": "You are terse."},
+                {"role": "user", "content": "First"},
+                {"role": "user", "content": [
+                    {"type": "text", "text": "What is this?"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0KGgo="}}
+                ]},
+                {"role": "assistant", "content": "A logo."}
+            ]
+        }))
+        .unwrap();
+
+        let body = serde_json::to_value(convert_request(&request)).unwrap();
+
+        assert_eq!(
+            body["systemInstruction"]["parts"][0]["text"],
+            "You are terse."
+        );
+        assert!(body["systemInstruction"].get("role").is_none());
+        let contents = body["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 2);
+        assert_eq!(contents[0]["parts"].as_array().unwrap().len(), 3);
+        assert_eq!(
+            contents[0]["parts"][2]["inlineData"],
+            json!({"mimeType": "image/png", "data": "iVBORw0KGgo="})
+        );
+        assert_eq!(contents[1]["role"], "model");
+    }
+
+    #[test]
+    fn test_safety_blocks_become_content_filter() {
+        let thresholds = HashMap::from([
+            (
+                "HARM_CATEGORY_HATE_SPEECH".to_string(),
+                "BLOCK_NONE".to_string(),
+            ),
+            (
+                "HARM_CATEGORY_DANGEROUS_CONTENT".to_string(),
+                "BLOCK_ONLY_HIGH".to_string(),
+            ),
+        ]);
+        let settings = safety_settings_from_thresholds(&thresholds).unwrap();
+        assert_eq!(settings[0].category, "HARM_CATEGORY_DANGEROUS_CONTENT");
+        assert!(safety_settings_from_thresholds(&HashMap::new()).is_none());
+
+        let response: GeminiResponse = serde_json::from_value(json!({
+            "candidates": [{
+                "finishReason": "SAFETY",
+                "safetyRatings": [
+                    {"category": "HARM_CATEGORY_DANGEROUS_CONTENT", "probability": "HIGH", "blocked": true}
+                ]
+            }]
+        }))
+        .unwrap();
+        let converted = convert_response(response, "gemini-2.5-pro");
+        assert_eq!(
+            converted.choices[0].finish_reason.as_deref(),
+            Some("content_filter")
+        );
+        let text = converted.choices[0].message.content.as_ref().unwrap();
+        assert!(
+            text.as_str()
+                .unwrap()
+                .contains("HARM_CATEGORY_DANGEROUS_CONTENT (HIGH, blocked)")
+        );
+
+        // Prompts rejected before generation have no candidates
+        let mut translator = GeminiStreamTranslator::new("gemini-2.5-pro");
+        let mut lines = translator
+            .translate_line(r#"data: {"promptFeedback": {"blockReason": "BLOCKLIST"}}"#)
+            .unwrap();
+        lines.extend(translator.finish());
+        let message: Value = serde_json::from_str(&lines[1][6..]).unwrap();
+        assert_eq!(
+            message["choices"][0]["delta"]["content"],
+            "[Response blocked by Gemini: prompt BLOCKLIST]"
+        );
+        let finish: Value = serde_json::from_str(&lines[2][6..]).unwrap();
+        assert_eq!(finish["choices"][0]["finish_reason"], "content_filter");
+    }
+}
@@ -0,0 +1,181 @@
+//! Google AI Studio provider implementation
+//!
+//! Talks to the Gemini API at `generativelanguage.googleapis.com` with an API
+//! key. Request and response conversion is shared with Vertex AI in `gemini.rs`.
+
+use crate::core::cancellation::ActiveRequests;
+use crate::core::provider::{Provider, ProviderCapabilities, ProviderError};
+use crate::core::providers::gemini::{self, GeminiResponse};
+use crate::models::openai::{OpenAIChatCompletionRequest, OpenAIChatCompletionResponse};
+use async_trait::async_trait;
+use futures::stream::Stream;
+use reqwest::Client;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default Gemini API base URL
+pub const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Google AI Studio provider for Gemini models
+pub struct GoogleAIProvider {
+    client: Client,
+    api_key: String,
+    base_url: String,
+    active_requests: Arc<ActiveRequests>,
+}
+
+impl GoogleAIProvider {
+    /// Name reported for this provider
+    pub const NAME: &'static str = "Google AI Studio";
+
+    /// Create a new Google AI Studio provider
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - Gemini API key from AI Studio
+    /// * `base_url` - Gemini API base URL
+    ///   (default: https://generativelanguage.googleapis.com/v1beta)
+    /// * `timeout` - Request timeout in seconds
+    pub fn new(api_key: String, base_url: Option<String>, timeout: u64) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            client,
+            api_key,
+            base_url: base_url
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            active_requests: Arc::default(),
+        }
+    }
+
+    /// Get the endpoint URL for the model
+    fn get_endpoint_url(&self, model: &str, stream: bool) -> String {
+        // alt=sse streams one JSON chunk per `data:` line instead of a JSON array
+        let method = if stream {
+            "streamGenerateContent?alt=sse"
+        } else {
+            "generateContent"
+        };
+
+        // Accept both "gemini-2.5-pro" and "models/gemini-2.5-pro"
+        let model = model.strip_prefix("models/").unwrap_or(model);
+
+        format!("{}/models/{}:{}", self.base_url, model, method)
+    }
+
+    /// Classify Google AI Studio errors
+    fn classify_error(error_detail: &str) -> String {
+        let error_lower = error_detail.to_lowercase();
+
+        if error_lower.contains("api key not valid") || error_lower.contains("api_key_invalid") {
+            return "Invalid API key. Please check your Google AI Studio API key.".to_string();
+        }
+
+        if error_lower.contains("resource_exhausted") || error_lower.contains("quota") {
+            return "Rate limit or quota exceeded. Please check your AI Studio quota.".to_string();
+        }
+
+        if error_lower.contains("not found") && error_lower.contains("model") {
+            return "Model not found. Please check your model configuration.".to_string();
+        }
+
+        if error_lower.contains("user location is not supported") {
+            return "The Gemini API is not available in your region.".to_string();
+        }
+
+        error_detail.to_string()
+    }
+
+    /// Internal method to send a generateContent request
+    async fn send_request(
+        &self,
+        request: &OpenAIChatCompletionRequest,
+        stream: bool,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let url = self.get_endpoint_url(&request.model, stream);
+        let gemini_request = gemini::convert_request(request);
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .header("x-goog-api-key", &self.api_key)
+            .json(&gemini_request)
+            .send()
+            .await
+            .map_err(ProviderError::from_send_error)?;
+
+        let status = response.status();
+
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(gemini::error_from_status(
+                status.as_u16(),
+                Self::classify_error(&error_text),
+            ));
+        }
+
+        Ok(response)
+    }
+
+    /// Internal method to send completion request
+    async fn send_completion_request(
+        &self,
+        request: &OpenAIChatCompletionRequest,
+    ) -> Result<OpenAIChatCompletionResponse, ProviderError> {
+        let response = self.send_request(request, false).await?;
+
+        let gemini_response: GeminiResponse = response
+            .json()
+            .await
+            .map_err(|e| ProviderError::Unexpected(format!("Failed to parse response: {}", e)))?;
+
+        Ok(gemini::convert_response(gemini_response, &request.model))
+    }
+}
+
+#[async_trait]
+impl Provider for GoogleAIProvider {
+    async fn create_chat_completion(
+        &self,
+        request: &OpenAIChatCompletionRequest,
+        request_id: Option<String>,
+    ) -> Result<OpenAIChatCompletionResponse, ProviderError> {
+        let cancel = self.active_requests.register(request_id);
+        cancel.run(self.send_completion_request(request)).await
+    }
+
+    async fn create_chat_completion_stream(
+        &self,
+        request: OpenAIChatCompletionRequest,
+        request_id: Option<String>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String, ProviderError>> + Send>>, ProviderError>
+    {
+        let cancel = self.active_requests.register(request_id);
+
+        let response = cancel.run(self.send_request(&request, true)).await?;
+
+        Ok(cancel.wrap_stream(gemini::translate_stream(response, &request.model)))
+    }
+
+    fn capabilities(&self, model: &str) -> ProviderCapabilities {
+        gemini::capabilities(model)
+    }
+
+    async fn cancel_request(&self, request_id: &str) -> bool {
+        self.active_requests.cancel(request_id)
+    }
+
+    fn provider_name(&self) -> &str {
+        Self::NAME
+    }
+}
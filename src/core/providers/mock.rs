@@ -0,0 +1,692 @@
+//! Offline mock provider
+//!
+//! Serves scripted replies from JSON fixture files so the proxy can be used
+//! without any upstream, e.g. in CI or offline. Each fixture file holds one
+//! fixture:
+//!
+//! ```json
+//! {
+//!   "match": { "model": "mock-model", "contains": "list files", "after_tool_result": false },
+//!   "responses": [
+//!     { "tool_calls": [{ "name": "Bash", "arguments": { "command": "ls" } }] },
+//!     { "text": "Here are your files.", "stream": { "chunk_chars": 4, "chunk_delay_ms": 30 } },
+//!     { "error": { "status": 529, "message": "Overloaded", "after_chunks": 2 } }
+//!   ]
+//! }
+//! ```
+//!
+//! Fixtures are tried in file name order and the first whose `match` fits the
+//! request is used; each use serves its next response, repeating the last one.
+//! Requests no fixture matches get an echo of the last user message.
+
+use crate::core::cancellation::ActiveRequests;
+use crate::core::provider::{Provider, ProviderError};
+use crate::models::openai::{
+    OpenAIChatCompletionRequest, OpenAIChatCompletionResponse, OpenAIChoice, OpenAIDelta,
+    OpenAIFunction, OpenAIFunctionDelta, OpenAIMessage, OpenAIStreamingChunk, OpenAIToolCall,
+    OpenAIToolCallDelta, OpenAIUsage,
+};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::stream::Stream;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Which requests a fixture answers; unset fields match anything
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MockMatch {
+    /// Exact upstream model name
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Substring of the last user message
+    #[serde(default)]
+    pub contains: Option<String>,
+    /// Whether the last message is a tool result
+    #[serde(default)]
+    pub after_tool_result: Option<bool>,
+}
+
+/// A tool call the mock model makes
+#[derive(Debug, Clone, Deserialize)]
+pub struct MockToolCall {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+/// Token counts to report; estimated from the text when unset
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MockUsage {
+    #[serde(default)]
+    pub prompt_tokens: Option<u32>,
+    #[serde(default)]
+    pub completion_tokens: Option<u32>,
+}
+
+/// An upstream failure to simulate
+#[derive(Debug, Clone, Deserialize)]
+pub struct MockError {
+    pub status: u16,
+    #[serde(default)]
+    pub message: String,
+    /// Fail a stream after this many chunks instead of before the response
+    #[serde(default)]
+    pub after_chunks: Option<usize>,
+}
+
+/// How a streamed reply is split up and paced
+#[derive(Debug, Clone, Deserialize)]
+pub struct MockStreamTiming {
+    #[serde(default = "default_chunk_chars")]
+    pub chunk_chars: usize,
+    #[serde(default)]
+    pub first_chunk_delay_ms: u64,
+    #[serde(default = "default_chunk_delay_ms")]
+    pub chunk_delay_ms: u64,
+}
+
+impl Default for MockStreamTiming {
+    fn default() -> Self {
+        Self {
+            chunk_chars: default_chunk_chars(),
+            first_chunk_delay_ms: 0,
+            chunk_delay_ms: default_chunk_delay_ms(),
+        }
+    }
+}
+
+fn default_chunk_chars() -> usize {
+    16
+}
+
+fn default_chunk_delay_ms() -> u64 {
+    20
+}
+
+/// One scripted reply
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MockResponse {
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<MockToolCall>,
+    /// Defaults to "tool_calls" when the reply calls tools, "stop" otherwise
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+    #[serde(default)]
+    pub usage: MockUsage,
+    #[serde(default)]
+    pub error: Option<MockError>,
+    #[serde(default)]
+    pub stream: MockStreamTiming,
+}
+
+/// A fixture file: a matcher and the replies it serves in turn
+#[derive(Debug, Clone, Deserialize)]
+pub struct MockFixture {
+    #[serde(default, rename = "match")]
+    pub matcher: MockMatch,
+    pub responses: Vec<MockResponse>,
+}
+
+impl MockFixture {
+    /// Load every `*.json` fixture in `dir`, sorted by file name
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the directory cannot be read or a fixture is invalid.
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> Result<Vec<MockFixture>> {
+        let dir = dir.as_ref();
+        let mut paths: Vec<_> = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read mock fixtures directory {}", dir.display()))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+
+        paths
+            .iter()
+            .map(|path| {
+                let content = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read mock fixture {}", path.display()))?;
+                let fixture: MockFixture = serde_json::from_str(&content)
+                    .with_context(|| format!("Invalid mock fixture {}", path.display()))?;
+                anyhow::ensure!(
+                    !fixture.responses.is_empty(),
+                    "Mock fixture {} has no responses",
+                    path.display()
+                );
+                Ok(fixture)
+            })
+            .collect()
+    }
+
+    fn matches(&self, request: &OpenAIChatCompletionRequest) -> bool {
+        if let Some(ref model) = self.matcher.model
+            && *model != request.model
+        {
+            return false;
+        }
+
+        if let Some(ref needle) = self.matcher.contains
+            && !last_user_text(request).contains(needle.as_str())
+        {
+            return false;
+        }
+
+        if let Some(after_tool_result) = self.matcher.after_tool_result {
+            let last_is_tool = request
+                .messages
+                .last()
+                .is_some_and(|message| message.role == "tool");
+            if last_is_tool != after_tool_result {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Plain text of a message's content
+fn content_text(content: &Option<Value>) -> String {
+    match content {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Text of the last user message
+fn last_user_text(request: &OpenAIChatCompletionRequest) -> String {
+    request
+        .messages
+        .iter()
+        .rev()
+        .find(|message| message.role == "user")
+        .map(|message| content_text(&message.content))
+        .unwrap_or_default()
+}
+
+/// Rough token estimate, about 4 characters per token
+fn estimate_tokens(chars: usize) -> u32 {
+    (chars / 4).max(1) as u32
+}
+
+/// Split text into pieces of at most `size` characters
+fn split_chars(text: &str, size: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .chunks(size.max(1))
+        .map(|chunk| chunk.iter().collect())
+        .collect()
+}
+
+/// Map a simulated status to a provider error
+fn error_from_mock(error: &MockError) -> ProviderError {
+    let message = if error.message.is_empty() {
+        format!("Mock upstream error {}", error.status)
+    } else {
+        error.message.clone()
+    };
+
+    match error.status {
+        401 => ProviderError::Authentication(message),
+        429 => ProviderError::RateLimit(message),
+        400 => ProviderError::BadRequest(message),
+        status => ProviderError::ApiError { status, message },
+    }
+}
+
+/// Provider that answers from fixtures without any network access
+pub struct MockProvider {
+    fixtures: Vec<MockFixture>,
+    /// Replies served so far by each fixture
+    served: Vec<AtomicUsize>,
+    active_requests: Arc<ActiveRequests>,
+}
+
+impl MockProvider {
+    /// Name reported for this provider
+    pub const NAME: &'static str = "Mock";
+
+    /// Create a mock provider
+    ///
+    /// # Arguments
+    ///
+    /// * `fixtures` - Scripted fixtures, tried in order
+    pub fn new(fixtures: Vec<MockFixture>) -> Self {
+        Self {
+            served: fixtures.iter().map(|_| AtomicUsize::new(0)).collect(),
+            fixtures,
+            active_requests: Arc::default(),
+        }
+    }
+
+    /// Pick the reply for a request
+    ///
+    /// A fixture's replies are served in turn, repeating the last one once
+    /// the script runs out.
+    fn respond(&self, request: &OpenAIChatCompletionRequest) -> MockResponse {
+        match self
+            .fixtures
+            .iter()
+            .position(|fixture| fixture.matches(request))
+        {
+            Some(index) => {
+                let fixture = &self.fixtures[index];
+                let served = self.served[index].fetch_add(1, Ordering::Relaxed);
+                fixture.responses[served.min(fixture.responses.len() - 1)].clone()
+            }
+            None => MockResponse {
+                text: Some(format!("Mock response to: {}", last_user_text(request))),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn usage(request: &OpenAIChatCompletionRequest, response: &MockResponse) -> OpenAIUsage {
+        let prompt_chars: usize = request
+            .messages
+            .iter()
+            .map(|message| content_text(&message.content).len())
+            .sum();
+        let completion_chars = response.text.as_deref().map_or(0, str::len)
+            + response
+                .tool_calls
+                .iter()
+                .map(|call| call.name.len() + call.arguments.to_string().len())
+                .sum::<usize>();
+
+        let prompt_tokens = response
+            .usage
+            .prompt_tokens
+            .unwrap_or_else(|| estimate_tokens(prompt_chars));
+        let completion_tokens = response
+            .usage
+            .completion_tokens
+            .unwrap_or_else(|| estimate_tokens(completion_chars));
+
+        OpenAIUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+
+    fn finish_reason(response: &MockResponse) -> String {
+        response.finish_reason.clone().unwrap_or_else(|| {
+            if response.tool_calls.is_empty() {
+                "stop".to_string()
+            } else {
+                "tool_calls".to_string()
+            }
+        })
+    }
+
+    fn tool_calls(response: &MockResponse) -> Vec<OpenAIToolCall> {
+        response
+            .tool_calls
+            .iter()
+            .map(|call| OpenAIToolCall {
+                id: call
+                    .id
+                    .clone()
+                    .unwrap_or_else(|| format!("call_{}", uuid::Uuid::new_v4().simple())),
+                call_type: "function".to_string(),
+                function: OpenAIFunction {
+                    name: call.name.clone(),
+                    arguments: call.arguments.to_string(),
+                },
+            })
+            .collect()
+    }
+
+    /// Build the SSE lines for a streamed reply, in order
+    fn stream_lines(request: &OpenAIChatCompletionRequest, response: &MockResponse) -> Vec<String> {
+        let chunk_id = format!("chatcmpl-{}", uuid::Uuid::new_v4());
+        let model = &request.model;
+        let chunk_chars = response.stream.chunk_chars;
+        let mut lines = vec![
+            OpenAIStreamingChunk::new(
+                &chunk_id,
+                model,
+                OpenAIDelta {
+                    role: Some("assistant".to_string()),
+                    ..Default::default()
+                },
+                None,
+            )
+            .to_sse_line(),
+        ];
+
+        if let Some(ref text) = response.text {
+            for piece in split_chars(text, chunk_chars) {
+                lines.push(
+                    OpenAIStreamingChunk::new(
+                        &chunk_id,
+                        model,
+                        OpenAIDelta {
+                            content: Some(piece),
+                            ..Default::default()
+                        },
+                        None,
+                    )
+                    .to_sse_line(),
+                );
+            }
+        }
+
+        for (index, tool_call) in Self::tool_calls(response).into_iter().enumerate() {
+            let tool_chunk = |id, call_type, name, arguments| {
+                OpenAIStreamingChunk::new(
+                    &chunk_id,
+                    model,
+                    OpenAIDelta {
+                        tool_calls: Some(vec![OpenAIToolCallDelta {
+                            index: index as u32,
+                            id,
+                            call_type,
+                            function: Some(OpenAIFunctionDelta { name, arguments }),
+                        }]),
+                        ..Default::default()
+                    },
+                    None,
+                )
+                .to_sse_line()
+            };
+
+            lines.push(tool_chunk(
+                Some(tool_call.id),
+                Some(tool_call.call_type),
+                Some(tool_call.function.name),
+                Some(String::new()),
+            ));
+            for piece in split_chars(&tool_call.function.arguments, chunk_chars) {
+                lines.push(tool_chunk(None, None, None, Some(piece)));
+            }
+        }
+
+        // Usage rides on the finish chunk, where the response converter reads it
+        let mut final_chunk = OpenAIStreamingChunk::new(
+            &chunk_id,
+            model,
+            OpenAIDelta::default(),
+            Some(Self::finish_reason(response)),
+        );
+        final_chunk.usage = Some(Self::usage(request, response));
+        lines.push(final_chunk.to_sse_line());
+        lines.push("data: [DONE]".to_string());
+
+        lines
+    }
+}
+
+#[async_trait]
+impl Provider for MockProvider {
+    async fn create_chat_completion(
+        &self,
+        request: &OpenAIChatCompletionRequest,
+        _request_id: Option<String>,
+    ) -> Result<OpenAIChatCompletionResponse, ProviderError> {
+        let response = self.respond(request);
+
+        if let Some(ref error) = response.error {
+            return Err(error_from_mock(error));
+        }
+
+        let tool_calls = Self::tool_calls(&response);
+        Ok(OpenAIChatCompletionResponse {
+            id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+            object: "chat.completion".to_string(),
+            created: chrono::Utc::now().timestamp(),
+            model: request.model.clone(),
+            choices: vec![OpenAIChoice {
+                index: 0,
+                message: OpenAIMessage {
+                    role: "assistant".to_string(),
+                    content: response.text.clone().map(Value::String),
+                    tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+                    tool_call_id: None,
+                    reasoning_content: None,
+                    reasoning: None,
+                    reasoning_details: None,
+                },
+                finish_reason: Some(Self::finish_reason(&response)),
+            }],
+            usage: Self::usage(request, &response),
+        })
+    }
+
+    async fn create_chat_completion_stream(
+        &self,
+        request: OpenAIChatCompletionRequest,
+        request_id: Option<String>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String, ProviderError>> + Send>>, ProviderError>
+    {
+        let cancel = self.active_requests.register(request_id);
+        let response = self.respond(&request);
+
+        let fail_after = match response.error {
+            Some(ref error) => match error.after_chunks {
+                Some(after_chunks) => Some((after_chunks, error_from_mock(error))),
+                None => return Err(error_from_mock(error)),
+            },
+            None => None,
+        };
+
+        let lines = Self::stream_lines(&request, &response);
+        let timing = response.stream.clone();
+
+        let stream = async_stream::stream! {
+            let mut fail_after = fail_after;
+
+            for (sent, line) in lines.into_iter().enumerate() {
+                if let Some((after_chunks, _)) = fail_after
+                    && sent == after_chunks
+                {
+                    if let Some((_, error)) = fail_after.take() {
+                        yield Err(error);
+                    }
+                    break;
+                }
+
+                let delay = if sent == 0 {
+                    timing.first_chunk_delay_ms
+                } else {
+                    timing.chunk_delay_ms
+                };
+                if delay > 0 {
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                }
+
+                yield Ok(line);
+            }
+        };
+
+        Ok(cancel.wrap_stream(Box::pin(stream)))
+    }
+
+    async fn cancel_request(&self, request_id: &str) -> bool {
+        self.active_requests.cancel(request_id)
+    }
+
+    fn provider_name(&self) -> &str {
+        Self::NAME
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use serde_json::json;
+
+    fn request(body: Value) -> OpenAIChatCompletionRequest {
+        serde_json::from_value(body).unwrap()
+    }
+
+    fn fixture(body: Value) -> MockFixture {
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_scripted_responses_and_echo() {
+        let provider = MockProvider::new(vec![fixture(json!({
+            "match": {"contains": "list files"},
+            "responses": [
+                {"tool_calls": [{"id": "call_1", "name": "Bash", "arguments": {"command": "ls"}}]},
+                {"text": "Done.", "usage": {"prompt_tokens": 12, "completion_tokens": 3}}
+            ]
+        }))]);
+        let matching = request(json!({
+            "model": "mock-model",
+            "messages": [{"role": "user", "content": "please list files"}]
+        }));
+
+        let first = provider
+            .create_chat_completion(&matching, None)
+            .await
+            .unwrap();
+        let tool_call = &first.choices[0].message.tool_calls.as_ref().unwrap()[0];
+        assert_eq!(tool_call.function.name, "Bash");
+        assert_eq!(tool_call.function.arguments, r#"{"command":"ls"}"#);
+        assert_eq!(
+            first.choices[0].finish_reason.as_deref(),
+            Some("tool_calls")
+        );
+
+        for _ in 0..2 {
+            let next = provider
+                .create_chat_completion(&matching, None)
+                .await
+                .unwrap();
+            assert_eq!(next.choices[0].message.content, Some(json!("Done.")));
+            assert_eq!(next.usage.total_tokens, 15);
+        }
+
+        let other = request(json!({
+            "model": "mock-model",
+            "messages": [{"role": "user", "content": "hello"}]
+        }));
+        let echo = provider.create_chat_completion(&other, None).await.unwrap();
+        assert_eq!(
+            echo.choices[0].message.content,
+            Some(json!("Mock response to: hello"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_chunks_and_injected_error() {
+        let provider = MockProvider::new(vec![
+            fixture(json!({
+                "match": {"model": "mock-stream"},
+                "responses": [{"text": "abcdefgh", "stream": {"chunk_chars": 3, "chunk_delay_ms": 0}}]
+            })),
+            fixture(json!({
+                "match": {"model": "mock-fail"},
+                "responses": [{"text": "abcdefgh", "error": {"status": 529, "after_chunks": 2}}]
+            })),
+        ]);
+        let messages = json!([{"role": "user", "content": "hi"}]);
+
+        let stream = provider
+            .create_chat_completion_stream(
+                request(json!({"model": "mock-stream", "messages": messages})),
+                None,
+            )
+            .await
+            .unwrap();
+        let lines: Vec<String> = stream.map(|line| line.unwrap()).collect().await;
+        let text: String = lines
+            .iter()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .filter_map(|data| serde_json::from_str::<Value>(data).ok())
+            .filter_map(|chunk| {
+                chunk["choices"][0]["delta"]["content"]
+                    .as_str()
+                    .map(String::from)
+            })
+            .collect();
+        assert_eq!(text, "abcdefgh");
+        assert!(lines[lines.len() - 2].contains("\"finish_reason\":\"stop\""));
+        assert!(lines[lines.len() - 2].contains("\"usage\""));
+        assert_eq!(lines.last().unwrap(), "data: [DONE]");
+
+        let stream = provider
+            .create_chat_completion_stream(
+                request(json!({"model": "mock-fail", "messages": messages})),
+                None,
+            )
+            .await
+            .unwrap();
+        let items: Vec<_> = stream.collect().await;
+        assert_eq!(items.len(), 3);
+        assert!(matches!(
+            items[2],
+            Err(ProviderError::ApiError { status: 529, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_streamed_usage_reaches_claude_client() {
+        use crate::conversion::response_converter::convert_openai_streaming_to_claude_with_cancellation;
+
+        let provider: Arc<dyn Provider> = Arc::new(MockProvider::new(vec![fixture(json!({
+            "responses": [{
+                "text": "Done.",
+                "usage": {"prompt_tokens": 12, "completion_tokens": 3},
+                "stream": {"chunk_delay_ms": 0}
+            }]
+        }))]));
+        let stream = provider
+            .create_chat_completion_stream(
+                request(json!({
+                    "model": "mock-model",
+                    "messages": [{"role": "user", "content": "hi"}],
+                    "stream": true
+                })),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let events: Vec<String> = convert_openai_streaming_to_claude_with_cancellation(
+            stream,
+            "claude".to_string(),
+            false,
+            provider.clone(),
+            "req-1".to_string(),
+        )
+        .await
+        .map(|event| event.unwrap())
+        .collect()
+        .await;
+
+        let message_delta = events
+            .iter()
+            .find(|event| event.starts_with("event: message_delta"))
+            .unwrap();
+        let data: Value = serde_json::from_str(
+            message_delta
+                .lines()
+                .nth(1)
+                .unwrap()
+                .trim_start_matches("data: "),
+        )
+        .unwrap();
+        assert_eq!(data["usage"]["input_tokens"], 12);
+        assert_eq!(data["usage"]["output_tokens"], 3);
+    }
+}
@@ -1,9 +1,21 @@
 //! Provider implementations
 
+pub mod anthropic;
+pub mod bedrock;
+pub mod gemini;
+pub mod googleai;
+pub mod mock;
+pub mod ollama;
 pub mod openai;
+pub mod openai_responses;
 pub mod openrouter;
 pub mod vertexai;
 
+pub use anthropic::AnthropicProvider;
+pub use bedrock::BedrockProvider;
+pub use googleai::GoogleAIProvider;
+pub use mock::MockProvider;
+pub use ollama::OllamaProvider;
 pub use openai::OpenAIProvider;
 pub use openrouter::OpenRouterProvider;
 pub use vertexai::VertexAIProvider;
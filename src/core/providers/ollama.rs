@@ -0,0 +1,609 @@
+//! Ollama provider implementation
+//!
+//! Talks to Ollama's native `/api/chat` endpoint (also served by llama.cpp
+//! compatible front-ends) and translates its NDJSON stream into OpenAI-style
+//! chunks for the response converter.
+
+use crate::core::cancellation::ActiveRequests;
+use crate::core::provider::{Provider, ProviderCapabilities, ProviderError};
+use crate::models::openai::{
+    OpenAIChatCompletionRequest, OpenAIChatCompletionResponse, OpenAIChoice, OpenAIDelta,
+    OpenAIFunction, OpenAIFunctionDelta, OpenAIMessage, OpenAIStreamingChunk, OpenAITool,
+    OpenAIToolCall, OpenAIToolCallDelta, OpenAIUsage,
+};
+use async_trait::async_trait;
+use futures::StreamExt;
+use futures::stream::Stream;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default Ollama server URL
+pub const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+/// Ollama provider for locally hosted models
+pub struct OllamaProvider {
+    client: Client,
+    base_url: String,
+    num_ctx: Option<u32>,
+    keep_alive: Option<String>,
+    options: HashMap<String, Value>,
+    active_requests: Arc<ActiveRequests>,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaChatRequest {
+    model: String,
+    messages: Vec<OllamaMessage>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<OpenAITool>>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    options: HashMap<String, Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OllamaMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    images: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OllamaToolCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunction,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaFunction {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaChatResponse {
+    #[serde(default)]
+    message: Option<OllamaMessage>,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    done_reason: Option<String>,
+    #[serde(default)]
+    prompt_eval_count: Option<u32>,
+    #[serde(default)]
+    eval_count: Option<u32>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl OllamaProvider {
+    /// Name reported for this provider
+    pub const NAME: &'static str = "Ollama";
+
+    /// Capabilities shared by every Ollama model
+    pub fn model_capabilities() -> ProviderCapabilities {
+        // Ollama has no tool_choice parameter
+        ProviderCapabilities {
+            tool_choice: false,
+            ..ProviderCapabilities::default()
+        }
+    }
+
+    /// Create a new Ollama provider
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - Ollama server URL (default: http://localhost:11434)
+    /// * `timeout` - Request timeout in seconds
+    /// * `num_ctx` - Optional context window size passed as `options.num_ctx`
+    /// * `keep_alive` - Optional duration to keep the model loaded (e.g. "10m")
+    /// * `options` - Additional model options merged into every request
+    pub fn new(
+        base_url: Option<String>,
+        timeout: u64,
+        num_ctx: Option<u32>,
+        keep_alive: Option<String>,
+        options: HashMap<String, Value>,
+    ) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            client,
+            base_url: base_url
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            num_ctx,
+            keep_alive,
+            options,
+            active_requests: Arc::default(),
+        }
+    }
+
+    /// Convert OpenAI request to Ollama chat format
+    fn convert_request_to_ollama(
+        &self,
+        request: &OpenAIChatCompletionRequest,
+        stream: bool,
+    ) -> OllamaChatRequest {
+        // Ollama has no tool call ids, so remember names for tool result messages
+        let mut tool_names: HashMap<String, String> = HashMap::new();
+        let mut messages = Vec::new();
+
+        for msg in &request.messages {
+            let mut ollama_msg = OllamaMessage {
+                role: msg.role.clone(),
+                ..Default::default()
+            };
+
+            match &msg.content {
+                Some(Value::String(s)) => ollama_msg.content = s.clone(),
+                Some(Value::Array(parts)) => {
+                    let mut text_parts = Vec::new();
+                    for part in parts {
+                        match part.get("type").and_then(|t| t.as_str()) {
+                            Some("text") => {
+                                if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                                    text_parts.push(text.to_string());
+                                }
+                            }
+                            Some("image_url") => {
+                                let url = part
+                                    .get("image_url")
+                                    .and_then(|i| i.get("url"))
+                                    .and_then(|u| u.as_str())
+                                    .unwrap_or_default();
+                                // Ollama expects bare base64 without the data URI prefix
+                                if let Some((_, data)) = url.split_once(";base64,") {
+                                    ollama_msg.images.push(data.to_string());
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    ollama_msg.content = text_parts.join("\n");
+                }
+                Some(other) => ollama_msg.content = other.to_string(),
+                None => {}
+            }
+
+            if let Some(ref tool_calls) = msg.tool_calls {
+                for tool_call in tool_calls {
+                    tool_names.insert(tool_call.id.clone(), tool_call.function.name.clone());
+                    ollama_msg.tool_calls.push(OllamaToolCall {
+                        function: OllamaFunction {
+                            name: tool_call.function.name.clone(),
+                            arguments: serde_json::from_str(&tool_call.function.arguments)
+                                .unwrap_or_else(|_| Value::Object(Default::default())),
+                        },
+                    });
+                }
+            }
+
+            if let Some(ref tool_call_id) = msg.tool_call_id {
+                ollama_msg.tool_name = tool_names.get(tool_call_id).cloned();
+            }
+
+            messages.push(ollama_msg);
+        }
+
+        let mut options = self.options.clone();
+        if let Some(num_ctx) = self.num_ctx {
+            options.insert("num_ctx".to_string(), Value::from(num_ctx));
+        }
+        if let Some(temperature) = request.temperature {
+            options.insert("temperature".to_string(), Value::from(temperature));
+        }
+        if let Some(top_p) = request.top_p {
+            options.insert("top_p".to_string(), Value::from(top_p));
+        }
+        if let Some(max_tokens) = request.max_tokens {
+            options.insert("num_predict".to_string(), Value::from(max_tokens));
+        }
+        if let Some(ref stop) = request.stop {
+            options.insert("stop".to_string(), Value::from(stop.clone()));
+        }
+
+        OllamaChatRequest {
+            model: request.model.clone(),
+            messages,
+            stream,
+            tools: request.tools.clone(),
+            options,
+            keep_alive: self.keep_alive.clone(),
+        }
+    }
+
+    /// Convert Ollama tool calls to OpenAI tool calls with generated ids
+    fn convert_tool_calls(tool_calls: &[OllamaToolCall]) -> Vec<OpenAIToolCall> {
+        tool_calls
+            .iter()
+            .map(|tool_call| OpenAIToolCall {
+                id: format!("call_{}", uuid::Uuid::new_v4().simple()),
+                call_type: "function".to_string(),
+                function: OpenAIFunction {
+                    name: tool_call.function.name.clone(),
+                    arguments: tool_call.function.arguments.to_string(),
+                },
+            })
+            .collect()
+    }
+
+    /// Map Ollama's done_reason to an OpenAI finish reason
+    fn map_finish_reason(done_reason: Option<&str>, has_tool_calls: bool) -> String {
+        if has_tool_calls {
+            return "tool_calls".to_string();
+        }
+        match done_reason {
+            Some("length") => "length",
+            _ => "stop",
+        }
+        .to_string()
+    }
+
+    /// Build usage statistics from Ollama's eval counters
+    fn usage_from(response: &OllamaChatResponse) -> OpenAIUsage {
+        let prompt_tokens = response.prompt_eval_count.unwrap_or(0);
+        let completion_tokens = response.eval_count.unwrap_or(0);
+        OpenAIUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+
+    /// Convert a complete Ollama response to OpenAI format
+    fn convert_response_from_ollama(
+        response: OllamaChatResponse,
+        model: &str,
+    ) -> OpenAIChatCompletionResponse {
+        let usage = Self::usage_from(&response);
+        let message = response.message.unwrap_or_default();
+        let tool_calls = Self::convert_tool_calls(&message.tool_calls);
+        let finish_reason =
+            Self::map_finish_reason(response.done_reason.as_deref(), !tool_calls.is_empty());
+
+        OpenAIChatCompletionResponse {
+            id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+            object: "chat.completion".to_string(),
+            created: chrono::Utc::now().timestamp(),
+            model: model.to_string(),
+            choices: vec![OpenAIChoice {
+                index: 0,
+                message: OpenAIMessage {
+                    role: "assistant".to_string(),
+                    content: Some(Value::String(message.content)),
+                    tool_calls: if tool_calls.is_empty() {
+                        None
+                    } else {
+                        Some(tool_calls)
+                    },
+                    tool_call_id: None,
+                    reasoning_content: None,
+                    reasoning: None,
+                    reasoning_details: None,
+                },
+                finish_reason: Some(finish_reason),
+            }],
+            usage,
+        }
+    }
+
+    /// Classify Ollama errors
+    fn classify_error(error_detail: &str) -> String {
+        let error_lower = error_detail.to_lowercase();
+
+        if error_lower.contains("not found") && error_lower.contains("model") {
+            return "Model not found. Run `ollama pull <model>` or check your model configuration."
+                .to_string();
+        }
+
+        if error_lower.contains("does not support tools") {
+            return "This model does not support tool calling. Choose a tool-capable model."
+                .to_string();
+        }
+
+        error_detail.to_string()
+    }
+
+    /// Internal method to send a chat request
+    async fn send_request(
+        &self,
+        ollama_request: &OllamaChatRequest,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let url = format!("{}/api/chat", self.base_url);
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .json(ollama_request)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_connect() {
+                    ProviderError::Connection(format!(
+                        "Could not connect to Ollama at {}. Is `ollama serve` running?",
+                        self.base_url
+                    ))
+                } else {
+                    ProviderError::from_send_error(e)
+                }
+            })?;
+
+        let status = response.status();
+
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            let classified_error = Self::classify_error(&error_text);
+
+            return Err(match status.as_u16() {
+                400 | 404 => ProviderError::BadRequest(classified_error),
+                _ => ProviderError::ApiError {
+                    status: status.as_u16(),
+                    message: classified_error,
+                },
+            });
+        }
+
+        Ok(response)
+    }
+
+    /// Internal method to send completion request
+    async fn send_completion_request(
+        &self,
+        request: &OpenAIChatCompletionRequest,
+    ) -> Result<OpenAIChatCompletionResponse, ProviderError> {
+        let ollama_request = self.convert_request_to_ollama(request, false);
+        let response = self.send_request(&ollama_request).await?;
+
+        let ollama_response: OllamaChatResponse = response
+            .json()
+            .await
+            .map_err(|e| ProviderError::Unexpected(format!("Failed to parse response: {}", e)))?;
+
+        if let Some(error) = ollama_response.error {
+            return Err(ProviderError::Unexpected(Self::classify_error(&error)));
+        }
+
+        Ok(Self::convert_response_from_ollama(
+            ollama_response,
+            &request.model,
+        ))
+    }
+}
+
+#[async_trait]
+impl Provider for OllamaProvider {
+    async fn create_chat_completion(
+        &self,
+        request: &OpenAIChatCompletionRequest,
+        request_id: Option<String>,
+    ) -> Result<OpenAIChatCompletionResponse, ProviderError> {
+        let cancel = self.active_requests.register(request_id);
+        cancel.run(self.send_completion_request(request)).await
+    }
+
+    async fn create_chat_completion_stream(
+        &self,
+        request: OpenAIChatCompletionRequest,
+        request_id: Option<String>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String, ProviderError>> + Send>>, ProviderError>
+    {
+        let cancel = self.active_requests.register(request_id);
+
+        let ollama_request = self.convert_request_to_ollama(&request, true);
+        let response = cancel.run(self.send_request(&ollama_request)).await?;
+
+        use futures::TryStreamExt;
+        use tokio::io::AsyncBufReadExt;
+        use tokio_stream::wrappers::LinesStream;
+
+        let byte_stream = response.bytes_stream();
+        let byte_stream = byte_stream.map_err(std::io::Error::other);
+
+        let reader = tokio_util::io::StreamReader::new(byte_stream);
+        let buf_reader = tokio::io::BufReader::new(reader);
+        let lines = buf_reader.lines();
+        let line_stream = LinesStream::new(lines);
+
+        let chunk_id = format!("chatcmpl-{}", uuid::Uuid::new_v4());
+        let model = request.model.clone();
+
+        // Translate NDJSON objects into OpenAI SSE chunk lines
+        let stream = async_stream::stream! {
+            tokio::pin!(line_stream);
+
+            let mut tool_call_index = 0u32;
+            let mut role_sent = false;
+
+            while let Some(line_result) = line_stream.next().await {
+                let line = match line_result {
+                    Ok(l) => l,
+                    Err(e) => {
+                        yield Err(ProviderError::Unexpected(e.to_string()));
+                        break;
+                    }
+                };
+
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let chunk: OllamaChatResponse = match serde_json::from_str(&line) {
+                    Ok(c) => c,
+                    Err(e) => {
+                        tracing::warn!("Failed to parse Ollama chunk: {}, error: {}", line, e);
+                        continue;
+                    }
+                };
+
+                if let Some(error) = chunk.error {
+                    yield Err(ProviderError::Unexpected(Self::classify_error(&error)));
+                    break;
+                }
+
+                if let Some(ref message) = chunk.message {
+                    let mut delta = OpenAIDelta::default();
+                    if !role_sent {
+                        delta.role = Some("assistant".to_string());
+                        role_sent = true;
+                    }
+                    if !message.content.is_empty() {
+                        delta.content = Some(message.content.clone());
+                    }
+                    if !message.tool_calls.is_empty() {
+                        let tool_calls = Self::convert_tool_calls(&message.tool_calls);
+                        delta.tool_calls = Some(
+                            tool_calls
+                                .into_iter()
+                                .map(|tool_call| {
+                                    let tc_delta = OpenAIToolCallDelta {
+                                        index: tool_call_index,
+                                        id: Some(tool_call.id),
+                                        call_type: Some(tool_call.call_type),
+                                        function: Some(OpenAIFunctionDelta {
+                                            name: Some(tool_call.function.name),
+                                            arguments: Some(tool_call.function.arguments),
+                                        }),
+                                    };
+                                    tool_call_index += 1;
+                                    tc_delta
+                                })
+                                .collect(),
+                        );
+                    }
+
+                    if delta.role.is_some() || delta.content.is_some() || delta.tool_calls.is_some() {
+                        yield Ok(OpenAIStreamingChunk::new(&chunk_id, &model, delta, None).to_sse_line());
+                    }
+                }
+
+                if chunk.done {
+                    let finish_reason =
+                        Self::map_finish_reason(chunk.done_reason.as_deref(), tool_call_index > 0);
+                    let mut final_chunk = OpenAIStreamingChunk::new(
+                        &chunk_id,
+                        &model,
+                        OpenAIDelta::default(),
+                        Some(finish_reason),
+                    );
+                    final_chunk.usage = Some(Self::usage_from(&chunk));
+                    yield Ok(final_chunk.to_sse_line());
+                    yield Ok("data: [DONE]".to_string());
+                    break;
+                }
+            }
+        };
+
+        Ok(cancel.wrap_stream(Box::pin(stream)))
+    }
+
+    fn capabilities(&self, _model: &str) -> ProviderCapabilities {
+        Self::model_capabilities()
+    }
+
+    async fn cancel_request(&self, request_id: &str) -> bool {
+        self.active_requests.cancel(request_id)
+    }
+
+    fn provider_name(&self) -> &str {
+        Self::NAME
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider() -> OllamaProvider {
+        OllamaProvider::new(
+            None,
+            90,
+            Some(8192),
+            Some("10m".to_string()),
+            HashMap::new(),
+        )
+    }
+
+    #[test]
+    fn test_convert_request_options_and_tools() {
+        let request: OpenAIChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "model": "llama3.1",
+            "max_tokens": 256,
+            "temperature": 0.5,
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": "What is this?"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}}
+                ]},
+                {"role": "assistant", "tool_calls": [{
+                    "id": "call_1", "type": "function",
+                    "function": {"name": "read_file", "arguments": "{\"path\":\"a.txt\"}"}
+                }]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "hello"}
+            ]
+        }))
+        .unwrap();
+
+        let ollama_request = provider().convert_request_to_ollama(&request, true);
+        let body = serde_json::to_value(&ollama_request).unwrap();
+
+        assert_eq!(body["options"]["num_ctx"], 8192);
+        assert_eq!(body["options"]["num_predict"], 256);
+        assert_eq!(body["keep_alive"], "10m");
+        assert_eq!(body["messages"][0]["images"][0], "AAAA");
+        assert_eq!(
+            body["messages"][1]["tool_calls"][0]["function"]["arguments"]["path"],
+            "a.txt"
+        );
+        assert_eq!(body["messages"][2]["tool_name"], "read_file");
+    }
+
+    #[test]
+    fn test_convert_response_with_tool_calls() {
+        let response: OllamaChatResponse = serde_json::from_value(serde_json::json!({
+            "model": "llama3.1",
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{"function": {"name": "ls", "arguments": {"dir": "."}}}]
+            },
+            "done": true,
+            "done_reason": "stop",
+            "prompt_eval_count": 12,
+            "eval_count": 7
+        }))
+        .unwrap();
+
+        let converted = OllamaProvider::convert_response_from_ollama(response, "llama3.1");
+        let choice = &converted.choices[0];
+        assert_eq!(choice.finish_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(
+            choice.message.tool_calls.as_ref().unwrap()[0]
+                .function
+                .arguments,
+            "{\"dir\":\".\"}"
+        );
+        assert_eq!(converted.usage.total_tokens, 19);
+    }
+}
@@ -1,43 +1,74 @@
 //! OpenAI provider implementation
 
-use crate::core::provider::{Provider, ProviderError};
+use crate::core::cancellation::ActiveRequests;
+use crate::core::key_pool::{KeyLease, KeyPool};
+use crate::core::provider::{Provider, ProviderCapabilities, ProviderError};
+use crate::core::providers::openai_responses::{self, ResponsesStreamTranslator};
+use crate::core::retry::RetryPolicy;
 use crate::models::openai::{
     OpenAIChatCompletionRequest, OpenAIChatCompletionResponse, OpenAIStreamOptions,
 };
 use async_trait::async_trait;
 use futures::StreamExt;
 use futures::stream::Stream;
-use reqwest::Client;
-use std::collections::HashMap;
+use reqwest::{Client, RequestBuilder};
+use serde_json::Value;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{Mutex, Notify};
 use tracing::{error, warn};
 
 /// OpenAI provider (supports OpenAI and Azure OpenAI)
 pub struct OpenAIProvider {
     client: Client,
-    api_key: String,
+    key_pool: Arc<KeyPool>,
     base_url: String,
     api_version: Option<String>,
-    active_requests: Arc<Mutex<HashMap<String, Arc<Notify>>>>,
+    use_responses_api: bool,
+    retry_policy: RetryPolicy,
+    active_requests: Arc<ActiveRequests>,
 }
 
 impl OpenAIProvider {
+    /// Name reported for the OpenAI API
+    pub const NAME: &'static str = "OpenAI";
+
+    /// Name reported for an Azure OpenAI deployment
+    pub const AZURE_NAME: &'static str = "Azure OpenAI";
+
+    /// Capabilities of `model`, sent through the Responses API or not
+    pub fn model_capabilities(model: &str, use_responses_api: bool) -> ProviderCapabilities {
+        // The original o1 previews take neither system messages, tools nor images
+        let o1_preview = model.starts_with("o1-mini") || model.starts_with("o1-preview");
+
+        ProviderCapabilities {
+            tools: !o1_preview,
+            vision: !o1_preview,
+            system_role: !o1_preview,
+            max_stop_sequences: Some(4),
+            // Reasoning items round-trip through the Responses API only
+            reasoning_input: use_responses_api && openai_responses::is_reasoning_model(model),
+            ..ProviderCapabilities::default()
+        }
+    }
+
     /// Create a new OpenAI provider
     ///
     /// # Arguments
     ///
-    /// * `api_key` - OpenAI API key
+    /// * `key_pool` - OpenAI API keys
     /// * `base_url` - OpenAI API base URL or Azure endpoint
     /// * `timeout` - Request timeout in seconds
     /// * `api_version` - Optional Azure API version (enables Azure mode)
+    /// * `use_responses_api` - Send requests to `/responses` instead of `/chat/completions`
+    /// * `max_retries` - Retries for rate-limited or failed requests
     pub fn new(
-        api_key: String,
+        key_pool: Arc<KeyPool>,
         base_url: String,
         timeout: u64,
         api_version: Option<String>,
+        use_responses_api: bool,
+        max_retries: u32,
     ) -> Self {
         let client = Client::builder()
             .timeout(Duration::from_secs(timeout))
@@ -46,10 +77,12 @@ impl OpenAIProvider {
 
         Self {
             client,
-            api_key,
+            key_pool,
             base_url,
             api_version,
-            active_requests: Arc::new(Mutex::new(HashMap::new())),
+            use_responses_api,
+            retry_policy: RetryPolicy::new(max_retries),
+            active_requests: Arc::default(),
         }
     }
 
@@ -85,12 +118,116 @@ impl OpenAIProvider {
         error_detail.to_string()
     }
 
+    /// Attach an API key in Azure (`api-key`) or OpenAI (bearer) style
+    fn authorize(&self, req_builder: RequestBuilder, api_key: &str) -> RequestBuilder {
+        if self.api_version.is_some() {
+            req_builder.header("api-key", api_key)
+        } else {
+            req_builder.bearer_auth(api_key)
+        }
+    }
+
+    /// Post a JSON body with a pooled key, retrying with a fresh key on failure
+    async fn post_json<T: serde::Serialize + ?Sized>(
+        &self,
+        url: &str,
+        body: &T,
+    ) -> Result<(reqwest::Response, KeyLease), ProviderError> {
+        self.retry_policy
+            .send(|| {
+                let lease = self.key_pool.acquire();
+                let req_builder = self
+                    .authorize(
+                        self.client
+                            .post(url)
+                            .header("Content-Type", "application/json"),
+                        lease.key(),
+                    )
+                    .json(body);
+
+                async move {
+                    let response = req_builder.send().await?;
+                    if !response.status().is_success() {
+                        lease.report(response.status().as_u16(), response.headers());
+                    }
+                    Ok((response, lease))
+                }
+            })
+            .await
+    }
+
+    /// Get the Responses API endpoint URL
+    fn responses_url(&self) -> String {
+        match self.api_version {
+            Some(ref api_version) => format!(
+                "{}/openai/responses?api-version={}",
+                self.base_url, api_version
+            ),
+            None => format!("{}/responses", self.base_url),
+        }
+    }
+
+    /// Internal method to send a Responses API request
+    async fn send_responses_request(
+        &self,
+        body: &Value,
+    ) -> Result<(reqwest::Response, KeyLease), ProviderError> {
+        let (response, lease) = self.post_json(&self.responses_url(), body).await?;
+
+        let status = response.status();
+
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            let classified_error = Self::classify_error(&error_text);
+
+            return Err(match status.as_u16() {
+                401 => ProviderError::Authentication(classified_error),
+                429 => ProviderError::RateLimit(classified_error),
+                400 => ProviderError::BadRequest(classified_error),
+                _ => ProviderError::ApiError {
+                    status: status.as_u16(),
+                    message: classified_error,
+                },
+            });
+        }
+
+        Ok((response, lease))
+    }
+
+    /// Translate the thinking budget into `reasoning_effort`
+    ///
+    /// Only reasoning models accept the parameter. `reasoning` is not part of
+    /// the OpenAI API, so it is always removed.
+    fn apply_reasoning_effort(request: &mut OpenAIChatCompletionRequest) {
+        if let Some(reasoning) = request.reasoning.take()
+            && let Some(budget) = reasoning.max_tokens
+            && openai_responses::is_reasoning_model(&request.model)
+        {
+            request.reasoning_effort =
+                Some(openai_responses::reasoning_effort_for_budget(budget).to_string());
+        }
+    }
+
     /// Internal method to send completion request
     async fn send_completion_request(
         &self,
         request: &OpenAIChatCompletionRequest,
-        _cancel_notify: Option<Arc<Notify>>,
     ) -> Result<OpenAIChatCompletionResponse, ProviderError> {
+        if self.use_responses_api {
+            let body = openai_responses::convert_request(request);
+            let (response, _lease) = self.send_responses_request(&body).await?;
+            let response: Value = response.json().await.map_err(|e| {
+                ProviderError::Unexpected(format!("Failed to parse response: {}", e))
+            })?;
+            return Ok(openai_responses::convert_response(
+                &response,
+                &request.model,
+            ));
+        }
+
         let url = if self.api_version.is_some() {
             format!(
                 "{}/openai/deployments/{}/chat/completions?api-version={}",
@@ -102,23 +239,7 @@ impl OpenAIProvider {
             format!("{}/chat/completions", self.base_url)
         };
 
-        let mut req_builder = self
-            .client
-            .post(&url)
-            .header("Content-Type", "application/json");
-
-        if self.api_version.is_some() {
-            req_builder = req_builder.header("api-key", &self.api_key);
-        } else {
-            req_builder = req_builder.bearer_auth(&self.api_key);
-        }
-
-        let req_builder = req_builder.json(request);
-
-        let response = req_builder
-            .send()
-            .await
-            .map_err(|e| ProviderError::Unexpected(e.to_string()))?;
+        let (response, _lease) = self.post_json(&url, request).await?;
 
         let status = response.status();
 
@@ -152,7 +273,7 @@ impl OpenAIProvider {
     async fn send_stream_request(
         &self,
         request: &OpenAIChatCompletionRequest,
-    ) -> Result<reqwest::Response, ProviderError> {
+    ) -> Result<(reqwest::Response, KeyLease), ProviderError> {
         let url = if self.api_version.is_some() {
             format!(
                 "{}/openai/deployments/{}/chat/completions?api-version={}",
@@ -164,22 +285,7 @@ impl OpenAIProvider {
             format!("{}/chat/completions", self.base_url)
         };
 
-        let mut req_builder = self
-            .client
-            .post(&url)
-            .header("Content-Type", "application/json");
-
-        if self.api_version.is_some() {
-            req_builder = req_builder.header("api-key", &self.api_key);
-        } else {
-            req_builder = req_builder.bearer_auth(&self.api_key);
-        }
-
-        let response = req_builder
-            .json(request)
-            .send()
-            .await
-            .map_err(|e| ProviderError::Unexpected(e.to_string()))?;
+        let (response, lease) = self.post_json(&url, request).await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -200,7 +306,60 @@ impl OpenAIProvider {
             });
         }
 
-        Ok(response)
+        Ok((response, lease))
+    }
+
+    /// Internal method to stream a Responses API request as chat completion chunks
+    async fn send_responses_stream_request(
+        &self,
+        request: &OpenAIChatCompletionRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String, ProviderError>> + Send>>, ProviderError>
+    {
+        let body = openai_responses::convert_request(request);
+        let (response, lease) = self.send_responses_request(&body).await?;
+
+        use futures::TryStreamExt;
+        use tokio::io::AsyncBufReadExt;
+        use tokio_stream::wrappers::LinesStream;
+
+        let byte_stream = response.bytes_stream().map_err(std::io::Error::other);
+        let reader = tokio_util::io::StreamReader::new(byte_stream);
+        let lines = LinesStream::new(tokio::io::BufReader::new(reader).lines());
+        let mut translator = ResponsesStreamTranslator::new(&request.model);
+
+        let stream = async_stream::stream! {
+            // The key stays in flight until the stream ends
+            let _lease = lease;
+            tokio::pin!(lines);
+
+            while let Some(line_result) = lines.next().await {
+                let line = match line_result {
+                    Ok(line) => line,
+                    Err(e) => {
+                        yield Err(ProviderError::Unexpected(e.to_string()));
+                        break;
+                    }
+                };
+
+                match translator.translate_line(&line) {
+                    Ok(translated) => {
+                        for chunk in translated {
+                            yield Ok(chunk);
+                        }
+                    }
+                    Err(e) => {
+                        yield Err(e);
+                        break;
+                    }
+                }
+
+                if translator.is_finished() {
+                    break;
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
     }
 }
 
@@ -211,24 +370,10 @@ impl Provider for OpenAIProvider {
         request: &OpenAIChatCompletionRequest,
         request_id: Option<String>,
     ) -> Result<OpenAIChatCompletionResponse, ProviderError> {
-        let cancel_notify = if let Some(ref id) = request_id {
-            let notify = Arc::new(Notify::new());
-            self.active_requests
-                .lock()
-                .await
-                .insert(id.clone(), notify.clone());
-            Some(notify)
-        } else {
-            None
-        };
-
-        let result = self.send_completion_request(request, cancel_notify).await;
-
-        if let Some(id) = request_id {
-            self.active_requests.lock().await.remove(&id);
-        }
-
-        result
+        let cancel = self.active_requests.register(request_id);
+        let mut request = request.clone();
+        Self::apply_reasoning_effort(&mut request);
+        cancel.run(self.send_completion_request(&request)).await
     }
 
     async fn create_chat_completion_stream(
@@ -237,25 +382,25 @@ impl Provider for OpenAIProvider {
         request_id: Option<String>,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<String, ProviderError>> + Send>>, ProviderError>
     {
-        let cancel_notify = if let Some(ref id) = request_id {
-            let notify = Arc::new(Notify::new());
-            self.active_requests
-                .lock()
-                .await
-                .insert(id.clone(), notify.clone());
-            Some(notify)
-        } else {
-            None
-        };
+        let cancel = self.active_requests.register(request_id);
 
         request.stream = true;
+        Self::apply_reasoning_effort(&mut request);
+
+        if self.use_responses_api {
+            let stream = cancel
+                .run(self.send_responses_stream_request(&request))
+                .await?;
+            return Ok(cancel.wrap_stream(stream));
+        }
+
         if request.stream_options.is_none() {
             request.stream_options = Some(OpenAIStreamOptions {
                 include_usage: true,
             });
         }
 
-        let response = self.send_stream_request(&request).await?;
+        let (response, lease) = cancel.run(self.send_stream_request(&request)).await?;
 
         use futures::TryStreamExt;
         use tokio::io::AsyncBufReadExt;
@@ -270,27 +415,28 @@ impl Provider for OpenAIProvider {
         let lines = buf_reader.lines();
         let line_stream = LinesStream::new(lines);
 
-        let stream = line_stream.map(|result: Result<String, std::io::Error>| {
+        // The key stays in flight until the stream is dropped
+        let stream = line_stream.map(move |result: Result<String, std::io::Error>| {
+            let _ = &lease;
             result.map_err(|e| ProviderError::Unexpected(e.to_string()))
         });
 
-        Ok(Box::pin(stream))
+        Ok(cancel.wrap_stream(Box::pin(stream)))
+    }
+
+    fn capabilities(&self, model: &str) -> ProviderCapabilities {
+        Self::model_capabilities(model, self.use_responses_api)
     }
 
     async fn cancel_request(&self, request_id: &str) -> bool {
-        if let Some(notify) = self.active_requests.lock().await.get(request_id) {
-            notify.notify_waiters();
-            true
-        } else {
-            false
-        }
+        self.active_requests.cancel(request_id)
     }
 
     fn provider_name(&self) -> &str {
         if self.api_version.is_some() {
-            "Azure OpenAI"
+            Self::AZURE_NAME
         } else {
-            "OpenAI"
+            Self::NAME
         }
     }
 }
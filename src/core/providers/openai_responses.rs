@@ -0,0 +1,661 @@
+//! OpenAI Responses API conversion
+//!
+//! Converts chat completion requests into `/responses` requests and translates
+//! Responses output items and streaming events back into chat completion
+//! responses and chunks. Reasoning summaries are surfaced as
+//! `reasoning_content` and encrypted reasoning state as the signature of a
+//! `reasoning.text` detail (`reasoning_signature` on stream deltas).
+
+use crate::core::provider::ProviderError;
+use crate::models::openai::{
+    OpenAIChatCompletionRequest, OpenAIChatCompletionResponse, OpenAIChoice, OpenAIDelta,
+    OpenAIFunction, OpenAIFunctionDelta, OpenAIMessage, OpenAIStreamingChunk, OpenAIToolCall,
+    OpenAIToolCallDelta, OpenAIUsage,
+};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+/// Check whether a model is a reasoning model
+///
+/// Reasoning models reject sampling parameters and are the only ones that
+/// return reasoning summaries.
+pub fn is_reasoning_model(model: &str) -> bool {
+    let model = model.rsplit('/').next().unwrap_or(model);
+    ["o1", "o3", "o4", "gpt-5", "codex"]
+        .iter()
+        .any(|prefix| model.starts_with(prefix))
+}
+
+/// Map a thinking budget in tokens to a reasoning effort
+///
+/// Claude Code asks for about 4k tokens to "think", 10k to "think hard" and
+/// 32k to "ultrathink".
+pub fn reasoning_effort_for_budget(budget: u32) -> &'static str {
+    match budget {
+        0..8_192 => "low",
+        8_192..24_576 => "medium",
+        _ => "high",
+    }
+}
+
+/// Convert chat message content into Responses input content parts
+fn convert_content(content: &Value, text_type: &str) -> Value {
+    match content {
+        Value::Array(parts) => Value::Array(
+            parts
+                .iter()
+                .filter_map(|part| match part.get("type").and_then(|t| t.as_str()) {
+                    Some("text") => Some(json!({
+                        "type": text_type,
+                        "text": part.get("text").and_then(|t| t.as_str()).unwrap_or_default()
+                    })),
+                    Some("image_url") => part
+                        .get("image_url")
+                        .and_then(|i| i.get("url"))
+                        .map(|url| json!({"type": "input_image", "image_url": url})),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Flatten message content to plain text
+fn content_text(content: &Option<Value>) -> String {
+    match content {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    }
+}
+
+/// Convert OpenAI chat completion request to a Responses API request
+pub fn convert_request(request: &OpenAIChatCompletionRequest) -> Value {
+    let mut instructions = Vec::new();
+    let mut input = Vec::new();
+
+    for msg in &request.messages {
+        match msg.role.as_str() {
+            "system" | "developer" => {
+                let text = content_text(&msg.content);
+                if !text.is_empty() {
+                    instructions.push(text);
+                }
+            }
+            "assistant" => {
+                // Reasoning items must precede the output they produced
+                input.extend(reasoning_items(msg));
+
+                let text = content_text(&msg.content);
+                if !text.is_empty() {
+                    input.push(json!({"role": "assistant", "content": text}));
+                }
+
+                for tool_call in msg.tool_calls.iter().flatten() {
+                    input.push(json!({
+                        "type": "function_call",
+                        "call_id": tool_call.id,
+                        "name": tool_call.function.name,
+                        "arguments": tool_call.function.arguments
+                    }));
+                }
+            }
+            "tool" => {
+                input.push(json!({
+                    "type": "function_call_output",
+                    "call_id": msg.tool_call_id.clone().unwrap_or_default(),
+                    "output": content_text(&msg.content)
+                }));
+            }
+            _ => {
+                if let Some(ref content) = msg.content {
+                    input.push(json!({
+                        "role": "user",
+                        "content": convert_content(content, "input_text")
+                    }));
+                }
+            }
+        }
+    }
+
+    let mut responses_request = json!({
+        "model": request.model,
+        "input": input,
+        "stream": request.stream,
+        // Reasoning state is carried in the conversation, not stored upstream
+        "store": false
+    });
+
+    if !instructions.is_empty() {
+        responses_request["instructions"] = json!(instructions.join("\n\n"));
+    }
+    if let Some(max_tokens) = request.max_tokens {
+        responses_request["max_output_tokens"] = json!(max_tokens);
+    }
+
+    if is_reasoning_model(&request.model) {
+        responses_request["reasoning"] = json!({"summary": "auto"});
+        if let Some(ref effort) = request.reasoning_effort {
+            responses_request["reasoning"]["effort"] = json!(effort);
+        }
+        responses_request["include"] = json!(["reasoning.encrypted_content"]);
+    } else {
+        if let Some(temperature) = request.temperature {
+            responses_request["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = request.top_p {
+            responses_request["top_p"] = json!(top_p);
+        }
+    }
+
+    if let Some(ref tools) = request.tools {
+        responses_request["tools"] = Value::Array(
+            tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "type": "function",
+                        "name": tool.function.name,
+                        "description": tool.function.description,
+                        "parameters": tool.function.parameters
+                    })
+                })
+                .collect(),
+        );
+    }
+
+    match request.tool_choice {
+        Some(Value::Object(ref choice)) => {
+            if let Some(name) = choice
+                .get("function")
+                .and_then(|f| f.get("name"))
+                .and_then(|n| n.as_str())
+            {
+                responses_request["tool_choice"] = json!({"type": "function", "name": name});
+            }
+        }
+        Some(ref choice) => responses_request["tool_choice"] = choice.clone(),
+        None => {}
+    }
+    if let Some(parallel_tool_calls) = request.parallel_tool_calls {
+        responses_request["parallel_tool_calls"] = json!(parallel_tool_calls);
+    }
+
+    responses_request
+}
+
+/// Extract usage from a Responses `response` object
+fn convert_usage(response: &Value) -> OpenAIUsage {
+    let usage = &response["usage"];
+    let prompt_tokens = usage["input_tokens"].as_u64().unwrap_or(0) as u32;
+    let completion_tokens = usage["output_tokens"].as_u64().unwrap_or(0) as u32;
+    OpenAIUsage {
+        prompt_tokens,
+        completion_tokens,
+        total_tokens: usage["total_tokens"]
+            .as_u64()
+            .map(|t| t as u32)
+            .unwrap_or(prompt_tokens + completion_tokens),
+    }
+}
+
+/// Determine the chat finish reason for a Responses `response` object
+fn finish_reason(response: &Value, has_tool_calls: bool) -> String {
+    if has_tool_calls {
+        return "tool_calls".to_string();
+    }
+
+    match response["incomplete_details"]["reason"].as_str() {
+        Some("max_output_tokens") => "length",
+        Some("content_filter") => "content_filter",
+        _ => "stop",
+    }
+    .to_string()
+}
+
+/// Concatenate the summary texts of a reasoning item
+fn reasoning_summary(item: &Value) -> Option<String> {
+    let parts: Vec<&str> = item["summary"]
+        .as_array()?
+        .iter()
+        .filter_map(|s| s["text"].as_str())
+        .collect();
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("\n\n"))
+    }
+}
+
+/// Reasoning input items for an assistant message, one per thinking block
+fn reasoning_items(msg: &OpenAIMessage) -> Vec<Value> {
+    let reasoning_item = |text: Option<&str>, signature: Option<&str>| {
+        let mut item = json!({
+            "type": "reasoning",
+            "summary": text
+                .filter(|text| !text.is_empty())
+                .map(|text| json!({"type": "summary_text", "text": text}))
+                .into_iter()
+                .collect::<Vec<_>>()
+        });
+        if let Some(signature) = signature {
+            item["encrypted_content"] = json!(signature);
+        }
+        item
+    };
+
+    let items: Vec<Value> = msg
+        .reasoning_details
+        .iter()
+        .flatten()
+        .filter(|detail| detail["type"] == "reasoning.text")
+        .map(|detail| reasoning_item(detail["text"].as_str(), detail["signature"].as_str()))
+        .collect();
+    if !items.is_empty() {
+        return items;
+    }
+
+    msg.reasoning_text()
+        .map(|text| reasoning_item(Some(text), None))
+        .into_iter()
+        .collect()
+}
+
+/// Convert a Responses API response to chat completion format
+pub fn convert_response(response: &Value, model: &str) -> OpenAIChatCompletionResponse {
+    let mut text_parts = Vec::new();
+    let mut tool_calls = Vec::new();
+    let mut reasoning_content = None;
+    let mut reasoning_details = Vec::new();
+
+    for item in response["output"].as_array().into_iter().flatten() {
+        match item["type"].as_str() {
+            Some("message") => {
+                for part in item["content"].as_array().into_iter().flatten() {
+                    if let Some(text) = part["text"].as_str() {
+                        text_parts.push(text.to_string());
+                    } else if let Some(refusal) = part["refusal"].as_str() {
+                        text_parts.push(refusal.to_string());
+                    }
+                }
+            }
+            Some("function_call") => tool_calls.push(OpenAIToolCall {
+                id: item["call_id"].as_str().unwrap_or_default().to_string(),
+                call_type: "function".to_string(),
+                function: OpenAIFunction {
+                    name: item["name"].as_str().unwrap_or_default().to_string(),
+                    arguments: item["arguments"].as_str().unwrap_or("{}").to_string(),
+                },
+            }),
+            Some("reasoning") => {
+                let summary = reasoning_summary(item);
+                let mut detail = json!({
+                    "type": "reasoning.text",
+                    "text": summary.clone().unwrap_or_default()
+                });
+                if let Some(signature) = item["encrypted_content"].as_str() {
+                    detail["signature"] = json!(signature);
+                }
+                reasoning_details.push(detail);
+                reasoning_content = summary.or(reasoning_content);
+            }
+            _ => {}
+        }
+    }
+
+    let finish_reason = finish_reason(response, !tool_calls.is_empty());
+
+    OpenAIChatCompletionResponse {
+        id: response["id"]
+            .as_str()
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| format!("chatcmpl-{}", uuid::Uuid::new_v4())),
+        object: "chat.completion".to_string(),
+        created: response["created_at"]
+            .as_i64()
+            .unwrap_or_else(|| chrono::Utc::now().timestamp()),
+        model: model.to_string(),
+        choices: vec![OpenAIChoice {
+            index: 0,
+            message: OpenAIMessage {
+                role: "assistant".to_string(),
+                content: Some(Value::String(text_parts.join(""))),
+                tool_calls: if tool_calls.is_empty() {
+                    None
+                } else {
+                    Some(tool_calls)
+                },
+                tool_call_id: None,
+                reasoning_content,
+                reasoning: None,
+                reasoning_details: (!reasoning_details.is_empty()).then_some(reasoning_details),
+            },
+            finish_reason: Some(finish_reason),
+        }],
+        usage: convert_usage(response),
+    }
+}
+
+/// Translates Responses streaming events into chat completion chunk lines
+pub struct ResponsesStreamTranslator {
+    chunk_id: String,
+    model: String,
+    /// Output item id -> chat tool call index
+    tool_indices: HashMap<String, u32>,
+    finished: bool,
+}
+
+impl ResponsesStreamTranslator {
+    pub fn new(model: &str) -> Self {
+        Self {
+            chunk_id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+            model: model.to_string(),
+            tool_indices: HashMap::new(),
+            finished: false,
+        }
+    }
+
+    fn chunk(&self, delta: OpenAIDelta) -> String {
+        OpenAIStreamingChunk::new(&self.chunk_id, &self.model, delta, None).to_sse_line()
+    }
+
+    fn tool_delta(
+        index: u32,
+        id: Option<String>,
+        name: Option<String>,
+        arguments: String,
+    ) -> OpenAIDelta {
+        OpenAIDelta {
+            tool_calls: Some(vec![OpenAIToolCallDelta {
+                index,
+                call_type: id.as_ref().map(|_| "function".to_string()),
+                id,
+                function: Some(OpenAIFunctionDelta {
+                    name,
+                    arguments: Some(arguments),
+                }),
+            }]),
+            ..Default::default()
+        }
+    }
+
+    /// Whether the terminal event has been translated
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Translate one SSE line into zero or more chat chunk lines
+    ///
+    /// `event:` lines are ignored; every data payload carries its own `type`.
+    pub fn translate_line(&mut self, line: &str) -> Result<Vec<String>, ProviderError> {
+        let Some(data) = line.strip_prefix("data:") else {
+            return Ok(Vec::new());
+        };
+        let Ok(event) = serde_json::from_str::<Value>(data.trim()) else {
+            return Ok(Vec::new());
+        };
+        self.translate(&event)
+    }
+
+    fn translate(&mut self, event: &Value) -> Result<Vec<String>, ProviderError> {
+        let lines = match event["type"].as_str().unwrap_or_default() {
+            "response.created" => vec![self.chunk(OpenAIDelta {
+                role: Some("assistant".to_string()),
+                ..Default::default()
+            })],
+            "response.output_text.delta" | "response.refusal.delta" => {
+                vec![self.chunk(OpenAIDelta {
+                    content: event["delta"].as_str().map(|s| s.to_string()),
+                    ..Default::default()
+                })]
+            }
+            "response.reasoning_summary_text.delta" => vec![self.chunk(OpenAIDelta {
+                reasoning_content: event["delta"].as_str().map(|s| s.to_string()),
+                ..Default::default()
+            })],
+            // Separate consecutive summary parts like the non-streaming path
+            "response.reasoning_summary_part.added"
+                if event["summary_index"].as_u64().unwrap_or(0) > 0 =>
+            {
+                vec![self.chunk(OpenAIDelta {
+                    reasoning_content: Some("\n\n".to_string()),
+                    ..Default::default()
+                })]
+            }
+            "response.output_item.added" if event["item"]["type"] == "function_call" => {
+                let item = &event["item"];
+                let index = self.tool_indices.len() as u32;
+                self.tool_indices
+                    .insert(item["id"].as_str().unwrap_or_default().to_string(), index);
+                vec![self.chunk(Self::tool_delta(
+                    index,
+                    item["call_id"].as_str().map(|s| s.to_string()),
+                    item["name"].as_str().map(|s| s.to_string()),
+                    String::new(),
+                ))]
+            }
+            "response.function_call_arguments.delta" => {
+                let index = event["item_id"]
+                    .as_str()
+                    .and_then(|id| self.tool_indices.get(id))
+                    .copied()
+                    .unwrap_or(0);
+                vec![self.chunk(Self::tool_delta(
+                    index,
+                    None,
+                    None,
+                    event["delta"].as_str().unwrap_or_default().to_string(),
+                ))]
+            }
+            "response.output_item.done" if event["item"]["type"] == "reasoning" => {
+                match event["item"]["encrypted_content"].as_str() {
+                    Some(signature) => vec![self.chunk(OpenAIDelta {
+                        reasoning_signature: Some(signature.to_string()),
+                        ..Default::default()
+                    })],
+                    None => Vec::new(),
+                }
+            }
+            "response.completed" | "response.incomplete" => {
+                self.finished = true;
+                let response = &event["response"];
+                let mut final_chunk = OpenAIStreamingChunk::new(
+                    &self.chunk_id,
+                    &self.model,
+                    OpenAIDelta::default(),
+                    Some(finish_reason(response, !self.tool_indices.is_empty())),
+                );
+                final_chunk.usage = Some(convert_usage(response));
+                vec![final_chunk.to_sse_line(), "data: [DONE]".to_string()]
+            }
+            "response.failed" => {
+                let message = event["response"]["error"]["message"]
+                    .as_str()
+                    .unwrap_or("Response failed");
+                return Err(ProviderError::Unexpected(message.to_string()));
+            }
+            "error" => {
+                let message = event["message"].as_str().unwrap_or("Unknown error");
+                return Err(ProviderError::Unexpected(message.to_string()));
+            }
+            _ => Vec::new(),
+        };
+
+        Ok(lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_request_items() {
+        let request: OpenAIChatCompletionRequest = serde_json::from_value(json!({
+            "model": "o4-mini",
+            "max_tokens": 2048,
+            "temperature": 1.0,
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "List files"},
+                {"role": "assistant", "reasoning_details": [
+                    {"type": "reasoning.text", "text": "Look first", "signature": "enc-1"},
+                    {"type": "reasoning.text", "text": "", "signature": "enc-2"}
+                ], "tool_calls": [
+                    {"id": "call_1", "type": "function", "function": {"name": "ls", "arguments": "{}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "a.txt"}
+            ],
+            "tool_choice": {"type": "function", "function": {"name": "ls"}},
+            "parallel_tool_calls": false,
+            "reasoning_effort": "high"
+        }))
+        .unwrap();
+
+        let body = convert_request(&request);
+
+        assert_eq!(body["instructions"], "Be brief.");
+        assert_eq!(body["reasoning"]["effort"], "high");
+        assert_eq!(body["max_output_tokens"], 2048);
+        assert!(body.get("temperature").is_none());
+        assert_eq!(body["input"][1]["type"], "reasoning");
+        assert_eq!(body["input"][1]["summary"][0]["text"], "Look first");
+        assert_eq!(body["input"][1]["encrypted_content"], "enc-1");
+        assert_eq!(body["input"][2]["summary"], json!([]));
+        assert_eq!(body["input"][2]["encrypted_content"], "enc-2");
+        assert_eq!(body["input"][3]["type"], "function_call");
+        assert_eq!(body["input"][4]["type"], "function_call_output");
+        assert_eq!(body["input"][4]["output"], "a.txt");
+        assert_eq!(
+            body["tool_choice"],
+            json!({"type": "function", "name": "ls"})
+        );
+        assert_eq!(body["parallel_tool_calls"], false);
+    }
+
+    #[test]
+    fn test_reasoning_effort_for_budget() {
+        assert_eq!(reasoning_effort_for_budget(4_000), "low");
+        assert_eq!(reasoning_effort_for_budget(10_000), "medium");
+        assert_eq!(reasoning_effort_for_budget(31_999), "high");
+    }
+
+    #[test]
+    fn test_convert_response_output_items() {
+        let response = json!({
+            "id": "resp_1",
+            "created_at": 1700000000,
+            "status": "completed",
+            "output": [
+                {"type": "reasoning", "summary": [{"type": "summary_text", "text": "Thinking"}],
+                 "encrypted_content": "enc"},
+                {"type": "message", "content": [{"type": "output_text", "text": "Done"}]},
+                {"type": "function_call", "call_id": "call_1", "name": "ls", "arguments": "{}"}
+            ],
+            "usage": {"input_tokens": 10, "output_tokens": 4, "total_tokens": 14}
+        });
+
+        let converted = convert_response(&response, "o4-mini");
+        let choice = &converted.choices[0];
+
+        assert_eq!(choice.finish_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(
+            choice.message.reasoning_content.as_deref(),
+            Some("Thinking")
+        );
+        assert_eq!(
+            choice.message.reasoning_details,
+            Some(vec![
+                json!({"type": "reasoning.text", "text": "Thinking", "signature": "enc"})
+            ])
+        );
+        assert_eq!(choice.message.tool_calls.as_ref().unwrap()[0].id, "call_1");
+        assert_eq!(converted.usage.total_tokens, 14);
+    }
+
+    #[test]
+    fn test_stream_translator_events() {
+        let mut translator = ResponsesStreamTranslator::new("o4-mini");
+        let events = [
+            r#"data: {"type":"response.created","response":{}}"#,
+            r#"data: {"type":"response.reasoning_summary_text.delta","delta":"Hmm"}"#,
+            r#"data: {"type":"response.output_item.done","item":{"type":"reasoning","encrypted_content":"enc"}}"#,
+            r#"data: {"type":"response.output_item.added","item":{"type":"function_call","id":"fc_1","call_id":"call_1","name":"ls"}}"#,
+            r#"data: {"type":"response.function_call_arguments.delta","item_id":"fc_1","delta":"{}"}"#,
+            r#"data: {"type":"response.completed","response":{"usage":{"input_tokens":3,"output_tokens":2}}}"#,
+        ];
+
+        let mut lines = Vec::new();
+        for event in events {
+            lines.extend(translator.translate_line(event).unwrap());
+        }
+
+        let reasoning: Value = serde_json::from_str(&lines[1][6..]).unwrap();
+        assert_eq!(reasoning["choices"][0]["delta"]["reasoning_content"], "Hmm");
+        let finish: Value = serde_json::from_str(&lines[5][6..]).unwrap();
+        assert_eq!(finish["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(finish["usage"]["total_tokens"], 5);
+        assert_eq!(lines[6], "data: [DONE]");
+        assert!(translator.is_finished());
+
+        let error = translator.translate_line(
+            r#"data: {"type":"response.failed","response":{"error":{"message":"boom"}}}"#,
+        );
+        assert!(error.is_err());
+    }
+
+    #[test]
+    fn test_streamed_reasoning_without_summary_keeps_signature() {
+        use futures::StreamExt;
+
+        let mut translator = ResponsesStreamTranslator::new("o4-mini");
+        let events = [
+            r#"data: {"type":"response.created","response":{}}"#,
+            r#"data: {"type":"response.output_item.done","item":{"type":"reasoning","summary":[],"encrypted_content":"enc"}}"#,
+            r#"data: {"type":"response.output_text.delta","delta":"Hi"}"#,
+            r#"data: {"type":"response.completed","response":{"usage":{"input_tokens":3,"output_tokens":2}}}"#,
+        ];
+        let mut lines = Vec::new();
+        for event in events {
+            lines.extend(translator.translate_line(event).unwrap());
+        }
+
+        let lines = lines.into_iter().map(Ok::<_, std::io::Error>);
+        let events: Vec<Value> = futures::executor::block_on(async {
+            crate::conversion::response_converter::convert_openai_streaming_to_claude(
+                futures::stream::iter(lines),
+                "claude".to_string(),
+                false,
+            )
+            .await
+            .map(|event| {
+                let event = event.unwrap();
+                serde_json::from_str(&event.lines().nth(1).unwrap()[6..]).unwrap()
+            })
+            .collect()
+            .await
+        });
+
+        let thinking_start = events
+            .iter()
+            .find(|e| e["type"] == "content_block_start" && e["index"] == 0)
+            .unwrap();
+        assert_eq!(
+            thinking_start["content_block"],
+            json!({"type": "thinking", "thinking": ""})
+        );
+        assert!(events.iter().any(|e| e["index"] == 0
+            && e["delta"] == json!({"type": "signature_delta", "signature": "enc"})));
+        let text_start = events
+            .iter()
+            .find(|e| e["type"] == "content_block_start" && e["index"] == 1)
+            .unwrap();
+        assert_eq!(text_start["content_block"]["type"], "text");
+    }
+}
@@ -1,6 +1,9 @@
 //! OpenRouter provider implementation
 
-use crate::core::provider::{Provider, ProviderError};
+use crate::core::cancellation::ActiveRequests;
+use crate::core::key_pool::{KeyLease, KeyPool};
+use crate::core::provider::{Provider, ProviderCapabilities, ProviderError};
+use crate::core::retry::RetryPolicy;
 use crate::models::openai::{
     OpenAIChatCompletionRequest, OpenAIChatCompletionResponse, OpenAIStreamOptions,
 };
@@ -8,39 +11,52 @@ use async_trait::async_trait;
 use futures::StreamExt;
 use futures::stream::Stream;
 use reqwest::Client;
-use std::collections::HashMap;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{Mutex, Notify};
 use tracing::{error, warn};
 
 /// OpenRouter provider
 pub struct OpenRouterProvider {
     client: Client,
-    api_key: String,
+    key_pool: Arc<KeyPool>,
     base_url: String,
     site_url: Option<String>,
     app_name: Option<String>,
-    active_requests: Arc<Mutex<HashMap<String, Arc<Notify>>>>,
+    retry_policy: RetryPolicy,
+    active_requests: Arc<ActiveRequests>,
 }
 
 impl OpenRouterProvider {
+    /// Name reported for this provider
+    pub const NAME: &'static str = "OpenRouter";
+
+    /// Capabilities shared by every OpenRouter model
+    pub fn model_capabilities() -> ProviderCapabilities {
+        // Thinking is sent back as reasoning details
+        ProviderCapabilities {
+            reasoning_input: true,
+            ..ProviderCapabilities::default()
+        }
+    }
+
     /// Create a new OpenRouter provider
     ///
     /// # Arguments
     ///
-    /// * `api_key` - OpenRouter API key
+    /// * `key_pool` - OpenRouter API keys
     /// * `base_url` - OpenRouter API base URL (default: https://openrouter.ai/api/v1)
     /// * `timeout` - Request timeout in seconds
     /// * `site_url` - Optional site URL for OpenRouter credits
     /// * `app_name` - Optional application name
+    /// * `max_retries` - Retries for rate-limited or failed requests
     pub fn new(
-        api_key: String,
+        key_pool: Arc<KeyPool>,
         base_url: Option<String>,
         timeout: u64,
         site_url: Option<String>,
         app_name: Option<String>,
+        max_retries: u32,
     ) -> Self {
         let client = Client::builder()
             .timeout(Duration::from_secs(timeout))
@@ -49,11 +65,12 @@ impl OpenRouterProvider {
 
         Self {
             client,
-            api_key,
+            key_pool,
             base_url: base_url.unwrap_or_else(|| "https://openrouter.ai/api/v1".to_string()),
             site_url,
             app_name,
-            active_requests: Arc::new(Mutex::new(HashMap::new())),
+            retry_policy: RetryPolicy::new(max_retries),
+            active_requests: Arc::default(),
         }
     }
 
@@ -84,11 +101,46 @@ impl OpenRouterProvider {
         error_detail.to_string()
     }
 
+    /// Post a request with a pooled key, retrying with a fresh key on failure
+    async fn post_request(
+        &self,
+        url: &str,
+        request: &OpenAIChatCompletionRequest,
+    ) -> Result<(reqwest::Response, KeyLease), ProviderError> {
+        self.retry_policy
+            .send(|| {
+                let lease = self.key_pool.acquire();
+                let mut req_builder = self
+                    .client
+                    .post(url)
+                    .header("Content-Type", "application/json")
+                    .bearer_auth(lease.key());
+
+                // Add OpenRouter-specific headers
+                if let Some(ref site_url) = self.site_url {
+                    req_builder = req_builder.header("HTTP-Referer", site_url);
+                }
+                if let Some(ref app_name) = self.app_name {
+                    req_builder = req_builder.header("X-Title", app_name);
+                }
+
+                let req_builder = req_builder.json(request);
+
+                async move {
+                    let response = req_builder.send().await?;
+                    if !response.status().is_success() {
+                        lease.report(response.status().as_u16(), response.headers());
+                    }
+                    Ok((response, lease))
+                }
+            })
+            .await
+    }
+
     /// Internal method to send completion request
     async fn send_completion_request(
         &self,
         request: &OpenAIChatCompletionRequest,
-        _cancel_notify: Option<Arc<Notify>>,
     ) -> Result<OpenAIChatCompletionResponse, ProviderError> {
         let url = format!("{}/chat/completions", self.base_url);
 
@@ -103,13 +155,15 @@ impl OpenRouterProvider {
 
         // Debug log to show message content for token count investigation
         if request.messages.len() > 10 {
-            let total_content_len: usize = request.messages
+            let total_content_len: usize = request
+                .messages
                 .iter()
                 .map(|msg| {
                     if let Some(content) = &msg.content {
                         match content {
                             serde_json::Value::String(s) => s.len(),
-                            serde_json::Value::Array(arr) => arr.iter()
+                            serde_json::Value::Array(arr) => arr
+                                .iter()
                                 .filter_map(|v| v.get("text").and_then(|t| t.as_str()))
                                 .map(|s| s.len())
                                 .sum::<usize>(),
@@ -128,26 +182,7 @@ impl OpenRouterProvider {
             );
         }
 
-        let mut req_builder = self
-            .client
-            .post(&url)
-            .header("Content-Type", "application/json")
-            .bearer_auth(&self.api_key);
-
-        // Add OpenRouter-specific headers
-        if let Some(ref site_url) = self.site_url {
-            req_builder = req_builder.header("HTTP-Referer", site_url);
-        }
-        if let Some(ref app_name) = self.app_name {
-            req_builder = req_builder.header("X-Title", app_name);
-        }
-
-        let req_builder = req_builder.json(request);
-
-        let response = req_builder
-            .send()
-            .await
-            .map_err(|e| ProviderError::Unexpected(e.to_string()))?;
+        let (response, _lease) = self.post_request(&url, request).await?;
 
         let status = response.status();
 
@@ -194,7 +229,7 @@ impl OpenRouterProvider {
     async fn send_stream_request(
         &self,
         request: &OpenAIChatCompletionRequest,
-    ) -> Result<reqwest::Response, ProviderError> {
+    ) -> Result<(reqwest::Response, KeyLease), ProviderError> {
         let url = format!("{}/chat/completions", self.base_url);
 
         // Log outgoing streaming request to OpenRouter
@@ -206,25 +241,7 @@ impl OpenRouterProvider {
             request.max_tokens.unwrap_or(0)
         );
 
-        let mut req_builder = self
-            .client
-            .post(&url)
-            .header("Content-Type", "application/json")
-            .bearer_auth(&self.api_key);
-
-        // Add OpenRouter-specific headers
-        if let Some(ref site_url) = self.site_url {
-            req_builder = req_builder.header("HTTP-Referer", site_url);
-        }
-        if let Some(ref app_name) = self.app_name {
-            req_builder = req_builder.header("X-Title", app_name);
-        }
-
-        let response = req_builder
-            .json(request)
-            .send()
-            .await
-            .map_err(|e| ProviderError::Unexpected(e.to_string()))?;
+        let (response, lease) = self.post_request(&url, request).await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -249,7 +266,7 @@ impl OpenRouterProvider {
             });
         }
 
-        Ok(response)
+        Ok((response, lease))
     }
 }
 
@@ -260,24 +277,8 @@ impl Provider for OpenRouterProvider {
         request: &OpenAIChatCompletionRequest,
         request_id: Option<String>,
     ) -> Result<OpenAIChatCompletionResponse, ProviderError> {
-        let cancel_notify = if let Some(ref id) = request_id {
-            let notify = Arc::new(Notify::new());
-            self.active_requests
-                .lock()
-                .await
-                .insert(id.clone(), notify.clone());
-            Some(notify)
-        } else {
-            None
-        };
-
-        let result = self.send_completion_request(request, cancel_notify).await;
-
-        if let Some(id) = request_id {
-            self.active_requests.lock().await.remove(&id);
-        }
-
-        result
+        let cancel = self.active_requests.register(request_id);
+        cancel.run(self.send_completion_request(request)).await
     }
 
     async fn create_chat_completion_stream(
@@ -286,16 +287,7 @@ impl Provider for OpenRouterProvider {
         request_id: Option<String>,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<String, ProviderError>> + Send>>, ProviderError>
     {
-        let cancel_notify = if let Some(ref id) = request_id {
-            let notify = Arc::new(Notify::new());
-            self.active_requests
-                .lock()
-                .await
-                .insert(id.clone(), notify.clone());
-            Some(notify)
-        } else {
-            None
-        };
+        let cancel = self.active_requests.register(request_id);
 
         request.stream = true;
         if request.stream_options.is_none() {
@@ -304,7 +296,7 @@ impl Provider for OpenRouterProvider {
             });
         }
 
-        let response = self.send_stream_request(&request).await?;
+        let (response, lease) = cancel.run(self.send_stream_request(&request)).await?;
 
         use futures::TryStreamExt;
         use tokio::io::AsyncBufReadExt;
@@ -319,23 +311,24 @@ impl Provider for OpenRouterProvider {
         let lines = buf_reader.lines();
         let line_stream = LinesStream::new(lines);
 
-        let stream = line_stream.map(|result: Result<String, std::io::Error>| {
+        // The key stays in flight until the stream is dropped
+        let stream = line_stream.map(move |result: Result<String, std::io::Error>| {
+            let _ = &lease;
             result.map_err(|e| ProviderError::Unexpected(e.to_string()))
         });
 
-        Ok(Box::pin(stream))
+        Ok(cancel.wrap_stream(Box::pin(stream)))
+    }
+
+    fn capabilities(&self, _model: &str) -> ProviderCapabilities {
+        Self::model_capabilities()
     }
 
     async fn cancel_request(&self, request_id: &str) -> bool {
-        if let Some(notify) = self.active_requests.lock().await.get(request_id) {
-            notify.notify_waiters();
-            true
-        } else {
-            false
-        }
+        self.active_requests.cancel(request_id)
     }
 
     fn provider_name(&self) -> &str {
-        "OpenRouter"
+        Self::NAME
     }
 }
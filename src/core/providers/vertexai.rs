@@ -1,98 +1,60 @@
 //! Vertex AI provider implementation
-
-use crate::core::provider::{Provider, ProviderError};
-use crate::models::openai::{
-    OpenAIChatCompletionRequest, OpenAIChatCompletionResponse, OpenAIChoice, OpenAIMessage
-    , OpenAIUsage,
-};
+//!
+//! Request and response conversion is shared with Google AI Studio in `gemini.rs`.
+//! Claude models are served by the Anthropic publisher through `rawPredict`,
+//! which takes Anthropic Messages bodies as-is.
+
+use crate::core::cancellation::ActiveRequests;
+use crate::core::gcp_auth::GcpTokenProvider;
+use crate::core::provider::{Provider, ProviderCapabilities, ProviderError};
+use crate::core::providers::gemini::{self, GeminiResponse, GeminiSafetySetting};
+use crate::core::retry::RetryPolicy;
+use crate::models::openai::{OpenAIChatCompletionRequest, OpenAIChatCompletionResponse};
 use async_trait::async_trait;
 use futures::stream::Stream;
-use futures::StreamExt;
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{Mutex, Notify};
 
-/// Vertex AI provider for Google Cloud's Gemini models
+/// `anthropic_version` required by Claude models on Vertex AI
+pub const VERTEX_ANTHROPIC_VERSION: &str = "vertex-2023-10-16";
+
+/// Vertex AI provider for Google Cloud's Gemini and Claude models
 pub struct VertexAIProvider {
     client: Client,
     project_id: String,
     location: String,
-    access_token: String,
-    active_requests: Arc<Mutex<HashMap<String, Arc<Notify>>>>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct VertexAIRequest {
-    contents: Vec<VertexAIContent>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    generation_config: Option<VertexAIGenerationConfig>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    safety_settings: Option<Vec<VertexAISafetySetting>>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct VertexAIContent {
-    role: String,
-    parts: Vec<VertexAIPart>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct VertexAIPart {
-    text: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct VertexAIGenerationConfig {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    temperature: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    top_p: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    max_output_tokens: Option<u32>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct VertexAISafetySetting {
-    category: String,
-    threshold: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct VertexAIResponse {
-    candidates: Vec<VertexAICandidate>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    usage_metadata: Option<VertexAIUsageMetadata>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct VertexAICandidate {
-    content: VertexAIContent,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    finish_reason: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct VertexAIUsageMetadata {
-    prompt_token_count: u32,
-    candidates_token_count: u32,
-    total_token_count: u32,
+    token_provider: GcpTokenProvider,
+    safety_settings: Option<Vec<GeminiSafetySetting>>,
+    retry_policy: RetryPolicy,
+    active_requests: Arc<ActiveRequests>,
 }
 
 impl VertexAIProvider {
+    /// Name reported for this provider
+    pub const NAME: &'static str = "Vertex AI";
+
     /// Create a new Vertex AI provider
     ///
     /// # Arguments
     ///
     /// * `project_id` - Google Cloud project ID
     /// * `location` - Google Cloud location (e.g., "us-central1")
-    /// * `access_token` - Google Cloud access token (from gcloud auth or service account)
+    /// * `token_provider` - Source of Google Cloud access tokens
     /// * `timeout` - Request timeout in seconds
-    pub fn new(project_id: String, location: String, access_token: String, timeout: u64) -> Self {
+    /// * `safety_settings` - Gemini safety thresholds by harm category
+    /// * `max_retries` - Retries for rate-limited or failed requests
+    pub fn new(
+        project_id: String,
+        location: String,
+        token_provider: GcpTokenProvider,
+        timeout: u64,
+        safety_settings: HashMap<String, String>,
+        max_retries: u32,
+    ) -> Self {
         let client = Client::builder()
             .timeout(Duration::from_secs(timeout))
             .build()
@@ -102,141 +64,137 @@ impl VertexAIProvider {
             client,
             project_id,
             location,
-            access_token,
-            active_requests: Arc::new(Mutex::new(HashMap::new())),
-        }
-    }
-
-    /// Convert OpenAI request to Vertex AI format
-    fn convert_request_to_vertex(&self, request: &OpenAIChatCompletionRequest) -> VertexAIRequest {
-        let mut contents = Vec::new();
-
-        for msg in &request.messages {
-            let role = match msg.role.as_str() {
-                "system" => "user", // Vertex AI doesn't have system role, merge with user
-                "assistant" => "model",
-                _ => "user",
-            };
-
-            if let Some(ref content) = msg.content {
-                let text = match content {
-                    serde_json::Value::String(s) => s.clone(),
-                    serde_json::Value::Array(arr) => {
-                        // Handle array of content blocks
-                        arr.iter()
-                            .filter_map(|v| v.get("text").and_then(|t| t.as_str()))
-                            .collect::<Vec<_>>()
-                            .join("\n")
-                    }
-                    _ => content.to_string(),
-                };
-
-                contents.push(VertexAIContent {
-                    role: role.to_string(),
-                    parts: vec![VertexAIPart { text }],
-                });
-            }
-        }
-
-        let generation_config = Some(VertexAIGenerationConfig {
-            temperature: request.temperature,
-            top_p: request.top_p,
-            max_output_tokens: request.max_tokens,
-        });
-
-        VertexAIRequest {
-            contents,
-            generation_config,
-            safety_settings: None,
+            token_provider,
+            safety_settings: gemini::safety_settings_from_thresholds(&safety_settings),
+            retry_policy: RetryPolicy::new(max_retries),
+            active_requests: Arc::default(),
         }
     }
 
-    /// Convert Vertex AI response to OpenAI format
-    fn convert_response_from_vertex(
-        &self,
-        response: VertexAIResponse,
-        model: &str,
-    ) -> OpenAIChatCompletionResponse {
-        let choice = if let Some(candidate) = response.candidates.first() {
-            let content = candidate
-                .content
-                .parts
-                .iter()
-                .map(|p| p.text.clone())
-                .collect::<Vec<_>>()
-                .join("\n");
-
-            let finish_reason = candidate
-                .finish_reason
-                .as_ref()
-                .map(|r| match r.as_str() {
-                    "STOP" => "stop",
-                    "MAX_TOKENS" => "length",
-                    _ => "stop",
-                })
-                .unwrap_or("stop")
-                .to_string();
-
-            OpenAIChoice {
-                index: 0,
-                message: OpenAIMessage {
-                    role: "assistant".to_string(),
-                    content: Some(serde_json::Value::String(content)),
-                    tool_calls: None,
-                    tool_call_id: None,
-                },
-                finish_reason: Some(finish_reason),
-            }
+    /// Get the base URL for a model publisher in the configured project
+    fn publisher_url(&self, publisher: &str) -> String {
+        // The global endpoint has no regional host prefix
+        let host = if self.location == "global" {
+            "aiplatform.googleapis.com".to_string()
         } else {
-            OpenAIChoice {
-                index: 0,
-                message: OpenAIMessage {
-                    role: "assistant".to_string(),
-                    content: Some(serde_json::Value::String("".to_string())),
-                    tool_calls: None,
-                    tool_call_id: None,
-                },
-                finish_reason: Some("stop".to_string()),
-            }
+            format!("{}-aiplatform.googleapis.com", self.location)
         };
 
-        let usage = response
-            .usage_metadata
-            .map(|u| OpenAIUsage {
-                prompt_tokens: u.prompt_token_count,
-                completion_tokens: u.candidates_token_count,
-                total_tokens: u.total_token_count,
-            })
-            .unwrap_or(OpenAIUsage {
-                prompt_tokens: 0,
-                completion_tokens: 0,
-                total_tokens: 0,
-            });
-
-        OpenAIChatCompletionResponse {
-            id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
-            object: "chat.completion".to_string(),
-            created: chrono::Utc::now().timestamp(),
-            model: model.to_string(),
-            choices: vec![choice],
-            usage,
-        }
+        format!(
+            "https://{}/v1/projects/{}/locations/{}/publishers/{}",
+            host, self.project_id, self.location, publisher
+        )
     }
 
     /// Get the endpoint URL for the model
     fn get_endpoint_url(&self, model: &str, stream: bool) -> String {
+        // alt=sse streams one JSON chunk per `data:` line instead of a JSON array
         let method = if stream {
-            "streamGenerateContent"
+            "streamGenerateContent?alt=sse"
         } else {
             "generateContent"
         };
 
         format!(
-            "https://{}-aiplatform.googleapis.com/v1/projects/{}/locations/{}/publishers/google/models/{}:{}",
-            self.location, self.project_id, self.location, model, method
+            "{}/models/{}:{}",
+            self.publisher_url("google"),
+            model,
+            method
+        )
+    }
+
+    /// Get the rawPredict endpoint URL for a Claude model
+    fn get_anthropic_endpoint_url(&self, model: &str, stream: bool) -> String {
+        let method = if stream {
+            "streamRawPredict"
+        } else {
+            "rawPredict"
+        };
+
+        format!(
+            "{}/models/{}:{}",
+            self.publisher_url("anthropic"),
+            model,
+            method
         )
     }
 
+    /// Post a JSON body with a fresh access token, retrying on failure
+    async fn post_json<T: serde::Serialize + ?Sized>(
+        &self,
+        url: &str,
+        body: &T,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let access_token = self.token_provider.access_token().await?;
+
+        self.retry_policy
+            .send(|| {
+                self.client
+                    .post(url)
+                    .header("Content-Type", "application/json")
+                    .bearer_auth(&access_token)
+                    .json(body)
+                    .send()
+            })
+            .await
+    }
+
+    /// Whether the model is served by the Anthropic publisher
+    pub fn is_claude_model(model: &str) -> bool {
+        model.starts_with("claude-")
+    }
+
+    /// Capabilities of `model` under its publisher
+    pub fn model_capabilities(model: &str) -> ProviderCapabilities {
+        if Self::is_claude_model(model) {
+            ProviderCapabilities::default()
+        } else {
+            gemini::capabilities(model)
+        }
+    }
+
+    /// Send an Anthropic Messages request to the Anthropic publisher
+    ///
+    /// Vertex takes the model from the URL and the API version from the body.
+    async fn send_claude_request(
+        &self,
+        mut request: Value,
+        stream: bool,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let model = request
+            .get("model")
+            .and_then(|m| m.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let url = self.get_anthropic_endpoint_url(&model, stream);
+
+        if let Some(body) = request.as_object_mut() {
+            body.remove("model");
+            body.insert(
+                "anthropic_version".to_string(),
+                Value::String(VERTEX_ANTHROPIC_VERSION.to_string()),
+            );
+            body.insert("stream".to_string(), Value::Bool(stream));
+        }
+
+        let response = self.post_json(&url, &request).await?;
+
+        let status = response.status();
+
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(gemini::error_from_status(
+                status.as_u16(),
+                Self::classify_error(&error_text),
+            ));
+        }
+
+        Ok(response)
+    }
+
     /// Classify Vertex AI errors
     fn classify_error(error_detail: &str) -> String {
         let error_lower = error_detail.to_lowercase();
@@ -267,20 +225,12 @@ impl VertexAIProvider {
     async fn send_completion_request(
         &self,
         request: &OpenAIChatCompletionRequest,
-        _cancel_notify: Option<Arc<Notify>>,
     ) -> Result<OpenAIChatCompletionResponse, ProviderError> {
         let url = self.get_endpoint_url(&request.model, false);
-        let vertex_request = self.convert_request_to_vertex(request);
-
-        let response = self
-            .client
-            .post(&url)
-            .header("Content-Type", "application/json")
-            .bearer_auth(&self.access_token)
-            .json(&vertex_request)
-            .send()
-            .await
-            .map_err(|e| ProviderError::Unexpected(e.to_string()))?;
+        let mut vertex_request = gemini::convert_request(request);
+        vertex_request.safety_settings = self.safety_settings.clone();
+
+        let response = self.post_json(&url, &vertex_request).await?;
 
         let status = response.status();
 
@@ -289,25 +239,18 @@ impl VertexAIProvider {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            let classified_error = Self::classify_error(&error_text);
-
-            return Err(match status.as_u16() {
-                401 | 403 => ProviderError::Authentication(classified_error),
-                429 => ProviderError::RateLimit(classified_error),
-                400 | 404 => ProviderError::BadRequest(classified_error),
-                _ => ProviderError::ApiError {
-                    status: status.as_u16(),
-                    message: classified_error,
-                },
-            });
+            return Err(gemini::error_from_status(
+                status.as_u16(),
+                Self::classify_error(&error_text),
+            ));
         }
 
-        let vertex_response: VertexAIResponse = response
+        let vertex_response: GeminiResponse = response
             .json()
             .await
             .map_err(|e| ProviderError::Unexpected(format!("Failed to parse response: {}", e)))?;
 
-        Ok(self.convert_response_from_vertex(vertex_response, &request.model))
+        Ok(gemini::convert_response(vertex_response, &request.model))
     }
 
     /// Internal method to send streaming request
@@ -316,17 +259,10 @@ impl VertexAIProvider {
         request: &OpenAIChatCompletionRequest,
     ) -> Result<reqwest::Response, ProviderError> {
         let url = self.get_endpoint_url(&request.model, true);
-        let vertex_request = self.convert_request_to_vertex(request);
-
-        let response = self
-            .client
-            .post(&url)
-            .header("Content-Type", "application/json")
-            .bearer_auth(&self.access_token)
-            .json(&vertex_request)
-            .send()
-            .await
-            .map_err(|e| ProviderError::Unexpected(e.to_string()))?;
+        let mut vertex_request = gemini::convert_request(request);
+        vertex_request.safety_settings = self.safety_settings.clone();
+
+        let response = self.post_json(&url, &vertex_request).await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -334,17 +270,10 @@ impl VertexAIProvider {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            let classified_error = Self::classify_error(&error_text);
-
-            return Err(match status.as_u16() {
-                401 | 403 => ProviderError::Authentication(classified_error),
-                429 => ProviderError::RateLimit(classified_error),
-                400 | 404 => ProviderError::BadRequest(classified_error),
-                _ => ProviderError::ApiError {
-                    status: status.as_u16(),
-                    message: classified_error,
-                },
-            });
+            return Err(gemini::error_from_status(
+                status.as_u16(),
+                Self::classify_error(&error_text),
+            ));
         }
 
         Ok(response)
@@ -358,75 +287,123 @@ impl Provider for VertexAIProvider {
         request: &OpenAIChatCompletionRequest,
         request_id: Option<String>,
     ) -> Result<OpenAIChatCompletionResponse, ProviderError> {
-        let cancel_notify = if let Some(ref id) = request_id {
-            let notify = Arc::new(Notify::new());
-            self.active_requests
-                .lock()
-                .await
-                .insert(id.clone(), notify.clone());
-            Some(notify)
-        } else {
-            None
-        };
+        let cancel = self.active_requests.register(request_id);
+        cancel.run(self.send_completion_request(request)).await
+    }
+
+    async fn create_chat_completion_stream(
+        &self,
+        request: OpenAIChatCompletionRequest,
+        request_id: Option<String>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String, ProviderError>> + Send>>, ProviderError>
+    {
+        let cancel = self.active_requests.register(request_id);
 
-        let result = self.send_completion_request(request, cancel_notify).await;
+        let response = cancel.run(self.send_stream_request(&request)).await?;
 
-        if let Some(id) = request_id {
-            self.active_requests.lock().await.remove(&id);
-        }
+        Ok(cancel.wrap_stream(gemini::translate_stream(response, &request.model)))
+    }
 
-        result
+    fn capabilities(&self, model: &str) -> ProviderCapabilities {
+        Self::model_capabilities(model)
     }
 
-    async fn create_chat_completion_stream(
+    fn accepts_claude_format(&self, model: &str) -> bool {
+        Self::is_claude_model(model)
+    }
+
+    async fn create_claude_message(
         &self,
-        request: OpenAIChatCompletionRequest,
+        request: &Value,
+        request_id: Option<String>,
+    ) -> Result<Value, ProviderError> {
+        let cancel = self.active_requests.register(request_id);
+
+        cancel
+            .run(async {
+                self.send_claude_request(request.clone(), false)
+                    .await?
+                    .json::<Value>()
+                    .await
+                    .map_err(|e| {
+                        ProviderError::Unexpected(format!("Failed to parse response: {}", e))
+                    })
+            })
+            .await
+    }
+
+    async fn create_claude_message_stream(
+        &self,
+        request: Value,
         request_id: Option<String>,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<String, ProviderError>> + Send>>, ProviderError>
     {
-        let cancel_notify = if let Some(ref id) = request_id {
-            let notify = Arc::new(Notify::new());
-            self.active_requests
-                .lock()
-                .await
-                .insert(id.clone(), notify.clone());
-            Some(notify)
-        } else {
-            None
-        };
+        let cancel = self.active_requests.register(request_id);
 
-        let response = self.send_stream_request(&request).await?;
+        let response = cancel.run(self.send_claude_request(request, true)).await?;
 
-        use futures::TryStreamExt;
+        use futures::{StreamExt, TryStreamExt};
         use tokio::io::AsyncBufReadExt;
         use tokio_stream::wrappers::LinesStream;
 
-        let byte_stream = response.bytes_stream();
-        let byte_stream =
-            byte_stream.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e));
-
+        // streamRawPredict relays Anthropic SSE events unchanged
+        let byte_stream = response.bytes_stream().map_err(std::io::Error::other);
         let reader = tokio_util::io::StreamReader::new(byte_stream);
-        let buf_reader = tokio::io::BufReader::new(reader);
-        let lines = buf_reader.lines();
-        let line_stream = LinesStream::new(lines);
+        let lines = LinesStream::new(tokio::io::BufReader::new(reader).lines());
 
-        let stream = line_stream.map(|result: Result<String, std::io::Error>| {
+        let stream = lines.map(|result: Result<String, std::io::Error>| {
             result.map_err(|e| ProviderError::Unexpected(e.to_string()))
         });
 
-        Ok(Box::pin(stream))
+        Ok(cancel.wrap_stream(Box::pin(stream)))
     }
 
     async fn cancel_request(&self, request_id: &str) -> bool {
-        if let Some(notify) = self.active_requests.lock().await.get(request_id) {
-            notify.notify_waiters();
-            true
-        } else {
-            false
-        }
+        self.active_requests.cancel(request_id)
     }
 
     fn provider_name(&self) -> &str {
-        "Vertex AI"
+        Self::NAME
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::gcp_auth::GcpCredentials;
+
+    fn provider(location: &str) -> VertexAIProvider {
+        VertexAIProvider::new(
+            "my-project".to_string(),
+            location.to_string(),
+            GcpTokenProvider::new(GcpCredentials::StaticToken("token".to_string()), None),
+            30,
+            HashMap::new(),
+            0,
+        )
+    }
+
+    #[test]
+    fn test_claude_models_use_anthropic_publisher() {
+        let provider = provider("us-east5");
+
+        assert!(provider.accepts_claude_format("claude-sonnet-4@20250514"));
+        assert!(!provider.accepts_claude_format("gemini-2.5-pro"));
+        assert_eq!(
+            provider.get_anthropic_endpoint_url("claude-sonnet-4@20250514", true),
+            "https://us-east5-aiplatform.googleapis.com/v1/projects/my-project/locations/us-east5/publishers/anthropic/models/claude-sonnet-4@20250514:streamRawPredict"
+        );
+        assert_eq!(
+            provider.get_endpoint_url("gemini-2.5-pro", false),
+            "https://us-east5-aiplatform.googleapis.com/v1/projects/my-project/locations/us-east5/publishers/google/models/gemini-2.5-pro:generateContent"
+        );
+    }
+
+    #[test]
+    fn test_global_location_has_no_regional_host() {
+        assert_eq!(
+            provider("global").get_anthropic_endpoint_url("claude-opus-4-1@20250805", false),
+            "https://aiplatform.googleapis.com/v1/projects/my-project/locations/global/publishers/anthropic/models/claude-opus-4-1@20250805:rawPredict"
+        );
     }
 }
@@ -0,0 +1,136 @@
+//! Provider registry
+//!
+//! Builds one provider instance per configured provider type so that
+//! different Claude model tiers can be served by different upstreams.
+
+use crate::core::cassette::{CassetteMode, CassetteProvider, ReplayTarget};
+use crate::core::config::{Config, ProviderConfig};
+use crate::core::gcp_auth::GcpTokenProvider;
+use crate::core::key_pool::KeyPool;
+use crate::core::provider::{Provider, ProviderType};
+use crate::core::providers::{
+    AnthropicProvider, BedrockProvider, GoogleAIProvider, MockProvider, OllamaProvider,
+    OpenAIProvider, OpenRouterProvider, VertexAIProvider,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Create a provider instance from its settings
+///
+/// # Arguments
+///
+/// * `config` - Settings for the provider
+/// * `timeout` - Request timeout in seconds
+/// * `max_retries` - Retries for rate-limited or failed requests
+pub fn create_provider(
+    config: &ProviderConfig,
+    timeout: u64,
+    max_retries: u32,
+) -> Arc<dyn Provider> {
+    match config.provider {
+        ProviderType::OpenAI => Arc::new(OpenAIProvider::new(
+            KeyPool::new(config.api_keys.clone(), config.key_selection),
+            config.base_url.clone(),
+            timeout,
+            config.azure_api_version.clone(),
+            config.openai_use_responses_api,
+            max_retries,
+        )),
+        ProviderType::OpenRouter => Arc::new(OpenRouterProvider::new(
+            KeyPool::new(config.api_keys.clone(), config.key_selection),
+            Some(config.base_url.clone()),
+            timeout,
+            config.openrouter_site_url.clone(),
+            config.openrouter_app_name.clone(),
+            max_retries,
+        )),
+        ProviderType::VertexAI => Arc::new(VertexAIProvider::new(
+            config.vertexai_project_id.clone().unwrap(),
+            config.vertexai_location.clone().unwrap(),
+            GcpTokenProvider::new(
+                config.vertexai_credentials.clone().unwrap(),
+                config.vertexai_token_url.clone(),
+            ),
+            timeout,
+            config.vertexai_safety_settings.clone(),
+            max_retries,
+        )),
+        ProviderType::Anthropic => Arc::new(AnthropicProvider::new(
+            config.api_key.clone(),
+            Some(config.base_url.clone()),
+            timeout,
+            config.anthropic_version.clone(),
+        )),
+        ProviderType::Ollama => Arc::new(OllamaProvider::new(
+            Some(config.base_url.clone()),
+            timeout,
+            config.ollama_num_ctx,
+            config.ollama_keep_alive.clone(),
+            config.ollama_options.clone(),
+        )),
+        ProviderType::Bedrock => Arc::new(BedrockProvider::new(
+            config.bedrock_region.clone().unwrap(),
+            Some(config.base_url.clone()),
+            config.bedrock_credentials.clone().unwrap(),
+            timeout,
+        )),
+        ProviderType::GoogleAI => Arc::new(GoogleAIProvider::new(
+            config.api_key.clone(),
+            Some(config.base_url.clone()),
+            timeout,
+        )),
+        ProviderType::Mock => Arc::new(MockProvider::new(config.mock_fixtures.clone())),
+    }
+}
+
+/// Provider instances keyed by provider type
+#[derive(Default)]
+pub struct ProviderRegistry {
+    providers: HashMap<ProviderType, Arc<dyn Provider>>,
+}
+
+impl ProviderRegistry {
+    /// Build a provider for every provider in the configuration
+    ///
+    /// With a cassette mode configured, every provider records or replays
+    /// its upstream exchanges. Replaying providers are stand-ins that need no
+    /// credentials.
+    pub fn from_config(config: &Config) -> Self {
+        let mut registry = Self::default();
+        for (provider_type, provider_config) in &config.providers {
+            let provider: Arc<dyn Provider> = match config.cassette_mode {
+                // Replay never reaches the upstream, so none is built
+                Some(CassetteMode::Replay) => Arc::new(CassetteProvider::new(
+                    Arc::new(ReplayTarget::new(provider_config.clone())),
+                    CassetteMode::Replay,
+                    &config.cassette_dir,
+                )),
+                Some(mode) => Arc::new(CassetteProvider::new(
+                    create_provider(provider_config, config.request_timeout, config.max_retries),
+                    mode,
+                    &config.cassette_dir,
+                )),
+                None => {
+                    create_provider(provider_config, config.request_timeout, config.max_retries)
+                }
+            };
+            registry.register(*provider_type, provider);
+        }
+        registry
+    }
+
+    /// Add or replace the provider for `provider_type`
+    pub fn register(&mut self, provider_type: ProviderType, provider: Arc<dyn Provider>) {
+        self.providers.insert(provider_type, provider);
+    }
+
+    /// Get the provider for `provider_type`, if configured
+    pub fn get(&self, provider_type: ProviderType) -> Option<Arc<dyn Provider>> {
+        self.providers.get(&provider_type).cloned()
+    }
+
+    /// Iterate over all registered providers
+    pub fn iter(&self) -> impl Iterator<Item = (&ProviderType, &Arc<dyn Provider>)> {
+        self.providers.iter()
+    }
+}
@@ -0,0 +1,259 @@
+//! Retries for upstream HTTP requests
+//!
+//! Requests that fail with a rate limit (429), a transient server error
+//! (500, 502, 503, 529) or a dropped connection are sent again after a
+//! jittered exponential backoff, or after the upstream's `Retry-After` delay
+//! when one is given. Only sending is retried: once a response is returned,
+//! its body (including a stream) is never requested again.
+
+use crate::core::key_pool::parse_retry_after;
+use crate::core::provider::ProviderError;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use tracing::warn;
+
+/// Delay before the first retry, doubled for each further retry
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Upper bound on the backoff between retries
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(10);
+
+/// Longest `Retry-After` that is waited out instead of returned to the caller
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Whether an upstream status is worth retrying
+pub fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 429 | 500 | 502 | 503 | 529)
+}
+
+/// Whether a send error means the connection failed or was dropped
+///
+/// Timeouts are not retried; the request already took the full timeout.
+fn is_retryable_error(e: &reqwest::Error) -> bool {
+    if e.is_connect() {
+        return true;
+    }
+
+    let mut source = std::error::Error::source(e);
+    while let Some(err) = source {
+        if let Some(io_err) = err.downcast_ref::<std::io::Error>() {
+            return matches!(
+                io_err.kind(),
+                std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::UnexpectedEof
+            );
+        }
+        source = err.source();
+    }
+
+    false
+}
+
+/// An upstream response, possibly along with resources held for it
+pub trait UpstreamResponse {
+    fn response(&self) -> &reqwest::Response;
+}
+
+impl UpstreamResponse for reqwest::Response {
+    fn response(&self) -> &reqwest::Response {
+        self
+    }
+}
+
+impl<T> UpstreamResponse for (reqwest::Response, T) {
+    fn response(&self) -> &reqwest::Response {
+        &self.0
+    }
+}
+
+/// How many times and how long to wait before resending a failed request
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Create a retry policy
+    ///
+    /// # Arguments
+    ///
+    /// * `max_retries` - Retries after the first attempt; 0 disables retrying
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            base_delay: RETRY_BASE_DELAY,
+            max_delay: RETRY_MAX_DELAY,
+        }
+    }
+
+    /// Backoff before retry number `retry` (starting at 0)
+    ///
+    /// Picks a random delay between half and all of the exponential backoff.
+    fn backoff(&self, retry: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        let half = ceiling / 2;
+        let jitter = RandomState::new().build_hasher().finish();
+        let spread = half.as_nanos() as u64 + 1;
+        half + Duration::from_nanos(jitter % spread)
+    }
+
+    /// Send a request, resending it while it fails in a retryable way
+    ///
+    /// `attempt` builds and sends the request once. The last response is
+    /// returned even if its status is an error, so callers classify it as
+    /// before; send errors are converted with `ProviderError::from_send_error`.
+    pub async fn send<R, F, Fut>(&self, mut attempt: F) -> Result<R, ProviderError>
+    where
+        R: UpstreamResponse,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<R, reqwest::Error>>,
+    {
+        let mut retry = 0;
+
+        loop {
+            let delay = match attempt().await {
+                Ok(result) => {
+                    let response = result.response();
+                    let status = response.status().as_u16();
+                    if retry >= self.max_retries || !is_retryable_status(status) {
+                        return Ok(result);
+                    }
+
+                    let delay = match parse_retry_after(response.headers()) {
+                        Some(retry_after) if retry_after > MAX_RETRY_AFTER => {
+                            return Ok(result);
+                        }
+                        Some(retry_after) => retry_after,
+                        None => self.backoff(retry),
+                    };
+                    warn!(
+                        "Upstream returned {}; retrying in {:?} ({}/{})",
+                        status,
+                        delay,
+                        retry + 1,
+                        self.max_retries
+                    );
+                    delay
+                }
+                Err(e) => {
+                    if retry >= self.max_retries || !is_retryable_error(&e) {
+                        return Err(ProviderError::from_send_error(e));
+                    }
+
+                    let delay = self.backoff(retry);
+                    warn!(
+                        "Upstream connection failed: {}; retrying in {:?} ({}/{})",
+                        e,
+                        delay,
+                        retry + 1,
+                        self.max_retries
+                    );
+                    delay
+                }
+            };
+
+            tokio::time::sleep(delay).await;
+            retry += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn fast_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+        }
+    }
+
+    /// Serve the given raw HTTP responses, one per connection
+    async fn serve(responses: Vec<&'static str>) -> (String, Arc<AtomicU32>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicU32::new(0));
+        let counter = hits.clone();
+
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        (url, hits)
+    }
+
+    #[test]
+    fn test_backoff_is_jittered_and_capped() {
+        let policy = RetryPolicy::new(5);
+        for retry in 0..8 {
+            let delay = policy.backoff(retry);
+            let ceiling = (RETRY_BASE_DELAY * 2u32.pow(retry)).min(RETRY_MAX_DELAY);
+            assert!(delay >= ceiling / 2 && delay <= ceiling, "{:?}", delay);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_retryable_status_until_success() {
+        let (url, hits) = serve(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+        ])
+        .await;
+        let client = reqwest::Client::new();
+
+        let response = fast_policy(2)
+            .send(|| client.get(&url).send())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries_and_skips_client_errors() {
+        let (url, hits) = serve(vec![
+            "HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        let client = reqwest::Client::new();
+
+        let response = fast_policy(1)
+            .send(|| client.get(&url).send())
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 502);
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        let response = fast_policy(3)
+            .send(|| client.get(&url).send())
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 400);
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+}
@@ -9,11 +9,12 @@ mod core;
 mod models;
 
 use crate::api::endpoints::{AppState, create_router};
+use crate::core::cassette::CassetteMode;
+use crate::core::circuit_breaker::CircuitBreakers;
 use crate::core::config::Config;
 use crate::core::logging::init_logging;
 use crate::core::model_manager::ModelManager;
-use crate::core::provider::{Provider, ProviderType};
-use crate::core::providers::{OpenAIProvider, OpenRouterProvider, VertexAIProvider};
+use crate::core::registry::ProviderRegistry;
 use std::sync::Arc;
 use tracing::{error, info};
 
@@ -40,48 +41,39 @@ async fn main() {
     // Print startup banner
     print_startup_banner(&config);
 
-    // Validate API key
-    if !config.validate_api_key() {
-        error!(
-            "Invalid API key configuration for provider: {:?}",
-            config.provider
-        );
-        std::process::exit(1);
+    // Validate API key; replayed exchanges need no credentials
+    if config.cassette_mode != Some(CassetteMode::Replay) {
+        for provider_config in config.providers.values() {
+            if !provider_config.validate_api_key() {
+                error!(
+                    "Invalid API key configuration for provider: {:?}",
+                    provider_config.provider
+                );
+                std::process::exit(1);
+            }
+        }
     }
 
     // Create model manager
     let model_manager = Arc::new(ModelManager::new((*config).clone()));
 
-    // Create provider based on configuration
-    let provider: Arc<dyn Provider> = match config.provider {
-        ProviderType::OpenAI => Arc::new(OpenAIProvider::new(
-            config.openai_api_key.clone(),
-            config.openai_base_url.clone(),
-            config.request_timeout,
-            config.azure_api_version.clone(),
-        )),
-        ProviderType::OpenRouter => Arc::new(OpenRouterProvider::new(
-            config.openai_api_key.clone(),
-            Some(config.openai_base_url.clone()),
-            config.request_timeout,
-            config.openrouter_site_url.clone(),
-            config.openrouter_app_name.clone(),
-        )),
-        ProviderType::VertexAI => Arc::new(VertexAIProvider::new(
-            config.vertexai_project_id.clone().unwrap(),
-            config.vertexai_location.clone().unwrap(),
-            config.vertexai_access_token.clone().unwrap(),
-            config.request_timeout,
-        )),
-    };
+    // Create a provider for every configured provider type
+    let registry = Arc::new(ProviderRegistry::from_config(&config));
 
-    info!("Using provider: {}", provider.provider_name());
+    for (provider_type, provider) in registry.iter() {
+        info!(
+            "Using provider: {} ({:?})",
+            provider.provider_name(),
+            provider_type
+        );
+    }
 
     // Create application state
     let app_state = AppState {
         config: config.clone(),
         model_manager,
-        provider,
+        registry,
+        circuit_breakers: Arc::new(CircuitBreakers::new(config.circuit_breaker.clone())),
     };
 
     // Create router
@@ -111,12 +103,22 @@ fn print_startup_banner(config: &Config) {
     println!("Claude API Proxy v1.0.0");
     println!("Configuration loaded successfully");
     println!("   Provider: {:?}", config.provider);
-    if !config.openai_base_url.is_empty() {
-        println!("   Base URL: {}", config.openai_base_url);
+    let base_url = &config.default_provider_config().base_url;
+    if !base_url.is_empty() {
+        println!("   Base URL: {}", base_url);
     }
-    println!("   Big Model (opus): {}", config.big_model);
-    println!("   Middle Model (sonnet): {}", config.middle_model);
-    println!("   Small Model (haiku): {}", config.small_model);
+    println!(
+        "   Big Model (opus): {} via {:?}",
+        config.big_model, config.big_provider
+    );
+    println!(
+        "   Middle Model (sonnet): {} via {:?}",
+        config.middle_model, config.middle_provider
+    );
+    println!(
+        "   Small Model (haiku): {} via {:?}",
+        config.small_model, config.small_provider
+    );
     println!("   Max Tokens Limit: {}", config.max_tokens_limit);
     println!("   Request Timeout: {}s", config.request_timeout);
     println!("   Server: {}:{}", config.host, config.port);
@@ -141,25 +143,75 @@ fn print_help() {
     println!("  --help    Display this help message");
     println!();
     println!("Environment variables:");
-    println!("  PROVIDER - Provider type: openai, openrouter, vertexai (default: openai)");
+    println!(
+        "  PROVIDER - Provider type: openai, openrouter, vertexai, anthropic, ollama, \
+         bedrock, googleai, mock (default: openai)"
+    );
     println!();
     println!("OpenAI/OpenRouter provider:");
     println!("  OPENAI_API_KEY / OPENROUTER_API_KEY - Your API key (required)");
     println!("  OPENAI_BASE_URL / OPENROUTER_BASE_URL - API base URL");
     println!("  AZURE_API_VERSION - Azure API version (for Azure OpenAI)");
+    println!("  [openai] use_responses_api - Use /responses instead of /chat/completions");
+    println!("  [openai] / [openrouter] api_keys - Extra API keys to spread requests across");
+    println!("  [openai] / [openrouter] key_selection - round_robin (default) or least_in_flight");
+    println!("    (keys are ejected on 429 for Retry-After, or on 401 for 5 minutes)");
     println!("  OPENROUTER_SITE_URL - Site URL for OpenRouter credits");
     println!("  OPENROUTER_APP_NAME - Application name for OpenRouter");
     println!();
     println!("Vertex AI provider:");
     println!("  VERTEXAI_PROJECT_ID - Google Cloud project ID (required)");
     println!("  VERTEXAI_LOCATION - GCP location (default: us-central1)");
-    println!("  VERTEXAI_ACCESS_TOKEN - GCP access token (required)");
+    println!("  VERTEXAI_ACCESS_TOKEN - GCP access token (or use a credentials file)");
+    println!("  [vertexai] credentials_file - Service-account key or ADC JSON");
+    println!(
+        "    (default: GOOGLE_APPLICATION_CREDENTIALS, then \
+         ~/.config/gcloud/application_default_credentials.json)"
+    );
+    println!("  [vertexai] token_url - OAuth token endpoint override");
+    println!("  [vertexai.safety_settings] - Harm category thresholds (e.g. BLOCK_ONLY_HIGH)");
+    println!("  claude-* models are forwarded to the Anthropic publisher via rawPredict");
+    println!();
+    println!("Anthropic provider:");
+    println!("  [anthropic] api_key - Upstream Anthropic API key (required)");
+    println!("  [anthropic] base_url - API base URL (default: https://api.anthropic.com/v1)");
+    println!("  [anthropic] anthropic_version - anthropic-version header (default: 2023-06-01)");
+    println!();
+    println!("Ollama provider:");
+    println!("  [ollama] base_url - Ollama server URL (default: http://localhost:11434)");
+    println!("  [ollama] num_ctx - Context window size");
+    println!("  [ollama] keep_alive - How long to keep the model loaded (e.g. 10m)");
+    println!("  [ollama] options - Extra model options table");
+    println!();
+    println!("AWS Bedrock provider:");
+    println!("  [bedrock] region - AWS region (default: AWS_REGION or us-east-1)");
+    println!("  [bedrock] base_url - Endpoint override for proxies or local testing");
+    println!("  [bedrock] access_key_id / secret_access_key / session_token - Credentials");
+    println!("    (default: AWS_ACCESS_KEY_ID / AWS_SECRET_ACCESS_KEY / AWS_SESSION_TOKEN)");
+    println!();
+    println!("Google AI Studio provider:");
+    println!("  [googleai] api_key - Gemini API key (required)");
+    println!(
+        "  [googleai] base_url - API base URL \
+         (default: https://generativelanguage.googleapis.com/v1beta)"
+    );
+    println!();
+    println!("Mock provider:");
+    println!("  [mock] fixtures_dir - Directory of JSON fixtures with scripted replies");
+    println!("    (requests no fixture matches are echoed back)");
     println!();
     println!("Common settings:");
     println!("  ANTHROPIC_API_KEY - Expected Anthropic API key for client validation");
     println!("  BIG_MODEL - Model for opus requests (default: gpt-4o)");
     println!("  MIDDLE_MODEL - Model for sonnet requests (default: gpt-4o)");
     println!("  SMALL_MODEL - Model for haiku requests (default: gpt-4o-mini)");
+    println!("  [models] big_provider / middle_provider / small_provider - Provider per tier");
+    println!("    (default: PROVIDER; each provider needs its own config section)");
+    println!("  [fallbacks] big / middle / small - Ordered {{ provider, model }} targets tried");
+    println!("    on rate limits, 5xx and connection errors");
+    println!("  [cassette] mode - record: save every upstream exchange; replay: serve saved");
+    println!("    exchanges without network access or credentials");
+    println!("  [cassette] dir - Directory for recorded exchanges (default: cassettes)");
     println!("  HOST - Server host (default: 0.0.0.0)");
     println!("  PORT - Server port (default: 8082)");
     println!("  LOG_LEVEL - Logging level (default: info)");
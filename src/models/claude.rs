@@ -92,6 +92,24 @@ mod tool_result_content {
     }
 }
 
+/// Thinking content block from an earlier assistant turn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaudeContentBlockThinking {
+    #[serde(rename = "type")]
+    pub content_type: String,
+    pub thinking: String,
+    #[serde(default)]
+    pub signature: String,
+}
+
+/// Redacted (encrypted) thinking content block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaudeContentBlockRedactedThinking {
+    #[serde(rename = "type")]
+    pub content_type: String,
+    pub data: String,
+}
+
 /// Union type for different content block types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -100,6 +118,8 @@ pub enum ClaudeContentBlock {
     Image(ClaudeContentBlockImage),
     ToolUse(ClaudeContentBlockToolUse),
     ToolResult(ClaudeContentBlockToolResult),
+    Thinking(ClaudeContentBlockThinking),
+    RedactedThinking(ClaudeContentBlockRedactedThinking),
 }
 
 /// System content block
@@ -166,14 +186,25 @@ pub struct ClaudeTool {
 }
 
 /// Thinking configuration for extended thinking
+/// (`{"type": "enabled", "budget_tokens": 10000}`)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClaudeThinkingConfig {
-    #[serde(default = "default_thinking_enabled")]
-    pub enabled: bool,
+    /// "enabled" or "disabled"
+    #[serde(rename = "type")]
+    pub thinking_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget_tokens: Option<u32>,
 }
 
-fn default_thinking_enabled() -> bool {
-    true
+impl ClaudeThinkingConfig {
+    /// Thinking budget in tokens, if thinking is enabled
+    pub fn budget(&self) -> Option<u32> {
+        if self.thinking_type == "enabled" {
+            self.budget_tokens
+        } else {
+            None
+        }
+    }
 }
 
 /// System content can be a string or array of system content blocks
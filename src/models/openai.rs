@@ -15,6 +15,25 @@ pub struct OpenAIMessage {
     pub tool_calls: Option<Vec<OpenAIToolCall>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_call_id: Option<String>,
+    /// Reasoning text (DeepSeek-style `reasoning_content`, Responses API summaries)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
+    /// Reasoning text as OpenRouter returns it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
+    /// Structured reasoning in OpenRouter's format, one entry per thinking
+    /// block (`reasoning.text` with its signature, `reasoning.encrypted`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_details: Option<Vec<serde_json::Value>>,
+}
+
+impl OpenAIMessage {
+    /// Reasoning text under whichever key the upstream used
+    pub fn reasoning_text(&self) -> Option<&str> {
+        self.reasoning_content
+            .as_deref()
+            .or(self.reasoning.as_deref())
+    }
 }
 
 /// OpenAI tool call
@@ -50,6 +69,16 @@ pub struct OpenAIFunctionDef {
     pub parameters: HashMap<String, serde_json::Value>,
 }
 
+/// Reasoning settings in OpenRouter's unified format
+///
+/// Also carries the Claude thinking budget to providers that translate it
+/// into their own reasoning controls.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenAIReasoning {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
 /// Stream options for OpenAI requests
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenAIStreamOptions {
@@ -77,6 +106,14 @@ pub struct OpenAIChatCompletionRequest {
     pub tools: Option<Vec<OpenAITool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<serde_json::Value>,
+    /// `false` limits the reply to at most one tool call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_tool_calls: Option<bool>,
+    /// Reasoning effort for OpenAI reasoning models ("low", "medium", "high")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<OpenAIReasoning>,
 }
 
 /// OpenAI chat completion response
@@ -114,6 +151,37 @@ pub struct OpenAIStreamingChunk {
     pub created: i64,
     pub model: String,
     pub choices: Vec<OpenAIStreamChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<OpenAIUsage>,
+}
+
+impl OpenAIStreamingChunk {
+    /// Create a chunk carrying a single choice
+    pub fn new(id: &str, model: &str, delta: OpenAIDelta, finish_reason: Option<String>) -> Self {
+        Self {
+            id: id.to_string(),
+            object: "chat.completion.chunk".to_string(),
+            created: chrono::Utc::now().timestamp(),
+            model: model.to_string(),
+            choices: vec![OpenAIStreamChoice {
+                index: 0,
+                delta,
+                finish_reason,
+            }],
+            usage: None,
+        }
+    }
+
+    /// Format the chunk as an SSE `data:` line
+    ///
+    /// Providers with non-OpenAI streaming formats use this to feed the
+    /// response converter the lines it expects.
+    pub fn to_sse_line(&self) -> String {
+        format!(
+            "data: {}",
+            serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
+        )
+    }
 }
 
 /// OpenAI streaming choice
@@ -125,7 +193,7 @@ pub struct OpenAIStreamChoice {
 }
 
 /// OpenAI delta for streaming
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct OpenAIDelta {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub role: Option<String>,
@@ -133,6 +201,10 @@ pub struct OpenAIDelta {
     pub content: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<OpenAIToolCallDelta>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_signature: Option<String>,
 }
 
 /// OpenAI tool call delta for streaming